@@ -0,0 +1,66 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Writes a temporary file and renames it over `file`, so a failed write leaves the old one.
+pub(crate) fn write(file: &Path, bytes: &[u8]) -> io::Result<()> {
+    let file = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+    // Renaming would replace a file the user may not write to.
+    if fs::metadata(&file).map_or(false, |metadata| metadata.is_file()) {
+        OpenOptions::new().write(true).open(&file)?;
+    }
+    let temp = temp_path(&file);
+    let mut out = match OpenOptions::new().write(true).create_new(true).open(&temp) {
+        Ok(out) => out,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            return write_in_place(&file, bytes)
+        }
+        Err(err) => return Err(err),
+    };
+    let result = fill(&mut out, &file, bytes).and_then(|_| fs::rename(&temp, &file));
+    if result.is_err() {
+        fs::remove_file(&temp).ok();
+    }
+    result
+}
+
+/// In the same directory, as a rename is only atomic within one file system.
+fn temp_path(file: &Path) -> PathBuf {
+    let name = file
+        .file_name()
+        .map_or("file".into(), |name| name.to_string_lossy());
+    file.with_file_name(format!(".{}.{}~", name, process::id()))
+}
+
+fn fill(out: &mut File, file: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Ok(metadata) = fs::metadata(file) {
+        set_owner(out, &metadata);
+        out.set_permissions(metadata.permissions())?;
+    }
+    out.write_all(bytes)?;
+    out.sync_all()
+}
+
+fn write_in_place(file: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut out = OpenOptions::new().write(true).create(true).open(file)?;
+    out.set_len(bytes.len() as u64)?;
+    out.write_all(bytes)?;
+    out.sync_all()
+}
+
+#[cfg(unix)]
+fn set_owner(out: &File, metadata: &fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+
+    // Changing the owner to someone else takes privileges, but the group can
+    // usually be kept still, so each is tried on its own.
+    unsafe {
+        libc::fchown(out.as_raw_fd(), metadata.uid(), u32::MAX);
+        libc::fchown(out.as_raw_fd(), u32::MAX, metadata.gid());
+    }
+}
+
+#[cfg(not(unix))]
+fn set_owner(_: &File, _: &fs::Metadata) {}
@@ -0,0 +1,41 @@
+use std::time::Instant;
+
+use crate::Editor;
+
+impl Editor {
+    pub(crate) fn autosave(&mut self) {
+        let config = &self.output.config;
+        let idle = config
+            .autosave_idle
+            .map_or(false, |idle| self.last_input.elapsed() >= idle);
+        let interval = config
+            .autosave_interval
+            .map_or(false, |interval| self.last_autosave.elapsed() >= interval);
+        if interval {
+            self.last_autosave = Instant::now();
+        }
+        let editor_rows = &self.output.buffer.editor_rows;
+        if !(idle || interval)
+            || self.output.buffer.dirty == 0
+            || editor_rows.filename.is_none()
+            || editor_rows.read_only
+        {
+            return;
+        }
+        match self.output.write_buffer() {
+            Ok(len) => {
+                self.output.buffer.dirty = 0;
+                self.output
+                    .status_message
+                    .set_message(format!("Autosaved, {} bytes written", len));
+            }
+            Err(err) => {
+                // Wait the idle time again before retrying.
+                self.last_input = Instant::now();
+                self.output
+                    .status_message
+                    .set_message(format!("Can't autosave! I/O error: {}", err));
+            }
+        }
+    }
+}
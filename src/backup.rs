@@ -0,0 +1,91 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+pub(crate) fn path_hash(file: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    fs::canonicalize(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    let cache = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache.join("pound"))
+}
+
+pub(crate) fn cache_file(kind: &str, file: &Path, suffix: &str) -> Option<PathBuf> {
+    let name = file.file_name()?.to_string_lossy();
+    Some(
+        cache_dir()?
+            .join(kind)
+            .join(format!("{}-{:016x}{}", name, path_hash(file), suffix)),
+    )
+}
+
+/// Owner only, for what a buffer holds whatever the permissions of its file.
+#[cfg(unix)]
+pub(crate) fn create_private(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // Left behind by an older session, it keeps its mode otherwise.
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn create_private(path: &Path) -> io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) enum Backup {
+    Off,
+    Beside,
+    In(PathBuf),
+}
+
+impl Backup {
+    pub(crate) fn directory(dir: &str) -> Self {
+        match (dir.strip_prefix("~/"), env::var_os("HOME")) {
+            (Some(rest), Some(home)) => Backup::In(Path::new(&home).join(rest)),
+            _ => Backup::In(dir.into()),
+        }
+    }
+
+    pub(crate) fn write(&self, file: &Path) -> io::Result<()> {
+        let target = match self {
+            _ if !file.exists() => return Ok(()),
+            Backup::Off => return Ok(()),
+            Backup::Beside => {
+                let mut name = file.as_os_str().to_owned();
+                name.push("~");
+                PathBuf::from(name)
+            }
+            Backup::In(dir) => {
+                let name = file
+                    .file_name()
+                    .map_or("file".into(), |name| name.to_string_lossy());
+                fs::create_dir_all(dir)?;
+                dir.join(format!("{}-{:016x}~", name, path_hash(file)))
+            }
+        };
+        fs::copy(file, &target).map(|_| ()).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("can't back up to {}: {}", target.display(), err),
+            )
+        })
+    }
+}
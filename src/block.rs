@@ -0,0 +1,203 @@
+use std::cmp;
+
+use crossterm::event::KeyCode;
+
+use crate::modal::Mode;
+use crate::{CursorController, EditorRows, Output, Row};
+
+impl CursorController {
+    pub(crate) fn block_columns(&self) -> Option<(usize, usize)> {
+        self.block
+            .map(|(anchor, cursor)| (cmp::min(anchor, cursor), cmp::max(anchor, cursor)))
+    }
+
+    fn render_column(&self, editor_rows: &EditorRows, (x, y): (usize, usize)) -> usize {
+        if y < editor_rows.number_of_rows() {
+            editor_rows.get_editor_row(y).render_x(x)
+        } else {
+            0
+        }
+    }
+}
+
+impl Row {
+    pub(crate) fn column_range(
+        &self,
+        left: usize,
+        right: usize,
+        inclusive: bool,
+    ) -> (usize, usize) {
+        let from = self.cursor_x(left);
+        let to = self.cursor_x(right);
+        let to = if inclusive && to < self.row_content.len() {
+            self.next_boundary(to)
+        } else {
+            to
+        };
+        (from, cmp::max(from, to))
+    }
+
+    fn width(&self) -> usize {
+        self.render_x(self.row_content.len())
+    }
+}
+
+impl Output {
+    pub(crate) fn start_block(&mut self) {
+        let editor_rows = &self.buffer.editor_rows;
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let anchor = *cursor_controller.anchor.get_or_insert(cursor);
+        cursor_controller.block = Some((
+            cursor_controller.render_column(editor_rows, anchor),
+            cursor_controller.render_column(editor_rows, cursor),
+        ));
+    }
+
+    pub(crate) fn select_block(&mut self, direction: KeyCode) {
+        if self.buffer.cursor_controller.block.is_none() {
+            self.start_block();
+        }
+        self.move_cursor(direction);
+        self.track_block(matches!(direction, KeyCode::Up | KeyCode::Down));
+    }
+
+    pub(crate) fn track_block(&mut self, vertical: bool) {
+        let editor_rows = &self.buffer.editor_rows;
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let column = cursor_controller.render_column(editor_rows, cursor);
+        if let Some((_, wanted)) = &mut cursor_controller.block {
+            if !vertical {
+                *wanted = column;
+            } else if cursor.1 < editor_rows.number_of_rows() {
+                cursor_controller.cursor_x = editor_rows.get_editor_row(cursor.1).cursor_x(*wanted);
+            }
+        }
+    }
+
+    pub(crate) fn block_ranges(&self) -> Option<Vec<(usize, usize, usize)>> {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let (left, right) = cursor_controller.block_columns()?;
+        let (_, anchor_y) = cursor_controller.anchor?;
+        let editor_rows = &self.buffer.editor_rows;
+        let rows = editor_rows.number_of_rows();
+        let first = cmp::min(anchor_y, cursor_controller.cursor_y);
+        let last = cmp::max(anchor_y, cursor_controller.cursor_y);
+        if rows == 0 || first >= rows {
+            return None;
+        }
+        let inclusive = self.mode == Some(Mode::VisualBlock);
+        let ranges = (first..=cmp::min(last, rows - 1))
+            .map(|y| {
+                let (from, to) = editor_rows
+                    .get_editor_row(y)
+                    .column_range(left, right, inclusive);
+                (y, from, to)
+            })
+            .collect();
+        Some(ranges)
+    }
+
+    pub(crate) fn block_text(&self) -> Option<String> {
+        let editor_rows = &self.buffer.editor_rows;
+        let lines: Vec<&str> = self
+            .block_ranges()?
+            .into_iter()
+            .map(|(y, from, to)| &editor_rows.get_row(y)[from..to])
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    pub(crate) fn type_in_block(&mut self, text: &str) -> bool {
+        let ranges = match self.block_ranges() {
+            Some(ranges) if !text.contains('\n') => ranges,
+            _ => return false,
+        };
+        if self.reject_edit() {
+            return true;
+        }
+        let (left, _) = self.buffer.cursor_controller.block_columns().unwrap();
+        let editor_rows = &mut self.buffer.editor_rows;
+        let mut column = None;
+        for (y, from, to) in ranges {
+            if editor_rows.get_editor_row(y).width() < left {
+                continue;
+            }
+            editor_rows.replace_range(y, from, to, text);
+            column.get_or_insert(editor_rows.get_editor_row(y).render_x(from + text.len()));
+        }
+        self.collapse_block(column.unwrap_or(left));
+        self.buffer.dirty += 1;
+        true
+    }
+
+    pub(crate) fn delete_block(&mut self) -> bool {
+        let ranges = match self.block_ranges() {
+            Some(ranges) => ranges,
+            None => return false,
+        };
+        if self.reject_edit() {
+            return true;
+        }
+        let (left, _) = self.buffer.cursor_controller.block_columns().unwrap();
+        for (y, from, to) in ranges {
+            self.buffer.editor_rows.replace_range(y, from, to, "");
+        }
+        self.buffer.dirty += 1;
+        self.collapse_block(left);
+        true
+    }
+
+    pub(crate) fn delete_in_block(&mut self, forward: bool) -> bool {
+        let (left, right) = match self.buffer.cursor_controller.block_columns() {
+            Some(columns) => columns,
+            None => return false,
+        };
+        if left < right || self.mode == Some(Mode::VisualBlock) {
+            return self.delete_block();
+        }
+        let ranges = match self.block_ranges() {
+            Some(ranges) => ranges,
+            None => return false,
+        };
+        if self.reject_edit() {
+            return true;
+        }
+        let editor_rows = &mut self.buffer.editor_rows;
+        let mut column = None;
+        for (y, at, _) in ranges {
+            let row = editor_rows.get_editor_row(y);
+            let (from, to) = match forward {
+                _ if row.width() < left => continue,
+                true if at < row.row_content.len() => (at, row.next_boundary(at)),
+                false if at > 0 => (row.previous_boundary(at), at),
+                _ => continue,
+            };
+            column.get_or_insert(row.render_x(from));
+            editor_rows.replace_range(y, from, to, "");
+        }
+        if column.is_some() {
+            self.buffer.dirty += 1;
+        }
+        self.collapse_block(column.unwrap_or(left));
+        true
+    }
+
+    pub(crate) fn collapse_block(&mut self, column: usize) {
+        let editor_rows = &self.buffer.editor_rows;
+        let x_at = |y: usize| {
+            if y < editor_rows.number_of_rows() {
+                editor_rows.get_editor_row(y).cursor_x(column)
+            } else {
+                0
+            }
+        };
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        if let Some((x, y)) = &mut cursor_controller.anchor {
+            *x = x_at(*y);
+        }
+        cursor_controller.cursor_x = x_at(cursor_controller.cursor_y);
+        cursor_controller.block = Some((column, column));
+    }
+}
@@ -0,0 +1,189 @@
+use std::cmp;
+
+use crate::syntax::HighlightType;
+use crate::{EditorRows, Output, Row};
+
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+const SCAN_ROWS: usize = 5000;
+
+fn in_literal(row: &Row, x: usize) -> bool {
+    matches!(
+        row.highlight.get(row.render_index(x)),
+        Some(
+            HighlightType::String
+                | HighlightType::CharLiteral
+                | HighlightType::Comment
+                | HighlightType::MultilineComment
+        )
+    )
+}
+
+pub(crate) fn matching_bracket(
+    editor_rows: &EditorRows,
+    (x, y): (usize, usize),
+) -> Option<(usize, usize)> {
+    let row = editor_rows.row_contents.get(y)?;
+    let c = row.row_content.get(x..)?.chars().next()?;
+    let (open, close) = PAIRS
+        .iter()
+        .copied()
+        .find(|&(open, close)| c == open || c == close)?;
+    let literal = in_literal(row, x);
+    let mut depth = 0;
+    let mut closes = |row: &Row, at: usize, ch: char| {
+        if (ch != open && ch != close) || in_literal(row, at) != literal {
+            return false;
+        }
+        if ch == c {
+            depth += 1;
+        } else {
+            depth -= 1;
+        }
+        depth == 0
+    };
+    if c == open {
+        let last = cmp::min(editor_rows.number_of_rows(), y + SCAN_ROWS);
+        for row_y in y..last {
+            let row = editor_rows.get_editor_row(row_y);
+            let start = if row_y == y { x } else { 0 };
+            for (i, ch) in row.row_content[start..].char_indices() {
+                if closes(row, start + i, ch) {
+                    return Some((start + i, row_y));
+                }
+            }
+        }
+    } else {
+        for row_y in (y.saturating_sub(SCAN_ROWS)..=y).rev() {
+            let row = editor_rows.get_editor_row(row_y);
+            let end = if row_y == y {
+                x + c.len_utf8()
+            } else {
+                row.row_content.len()
+            };
+            for (at, ch) in row.row_content[..end].char_indices().rev() {
+                if closes(row, at, ch) {
+                    return Some((at, row_y));
+                }
+            }
+        }
+    }
+    None
+}
+
+pub(crate) fn bracket_pair(
+    editor_rows: &EditorRows,
+    (x, y): (usize, usize),
+) -> Option<((usize, usize), (usize, usize))> {
+    let before = editor_rows
+        .row_contents
+        .get(y)
+        .filter(|_| x > 0)
+        .map(|row| (row.previous_boundary(x), y));
+    [Some((x, y)), before]
+        .into_iter()
+        .flatten()
+        .find_map(|at| matching_bracket(editor_rows, at).map(|other| (at, other)))
+}
+
+impl Output {
+    pub(crate) fn jump_to_bracket(&mut self) {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        match bracket_pair(&self.buffer.editor_rows, cursor) {
+            Some((_, (x, y))) => {
+                self.buffer.cursor_controller.cursor_x = x;
+                self.buffer.cursor_controller.cursor_y = y;
+            }
+            None => self
+                .status_message
+                .set_message("No bracket at the cursor".into()),
+        }
+    }
+
+    /// Returns whether `ch` was handled.
+    pub(crate) fn type_closing(&mut self, ch: char) -> bool {
+        if !self.config.auto_close {
+            return false;
+        }
+        if self.reject_edit() {
+            return true;
+        }
+        let (before, after) = self.chars_around_cursor();
+        if after == Some(ch) && (is_closer(ch) || is_quote(ch)) {
+            self.buffer.cursor_controller.cursor_x += ch.len_utf8();
+            return true;
+        }
+        let close = match ch {
+            '"' if before.map_or(false, |c| c.is_alphanumeric() || c == '"' || c == '\\') => {
+                return false
+            }
+            // Only after a space or bracket, so apostrophes and lifetimes
+            // stay single.
+            '\'' if before.map_or(false, |c| !c.is_whitespace() && !"([{".contains(c)) => {
+                return false
+            }
+            '"' | '\'' => ch,
+            _ => match PAIRS.iter().find(|&&(open, _)| open == ch) {
+                Some(&(_, close)) => close,
+                None => return false,
+            },
+        };
+        if !after.map_or(true, |c| c.is_whitespace() || is_closer(c)) {
+            return false;
+        }
+        self.insert_char(ch);
+        self.insert_char(close);
+        if self.buffer.cursor_controller.cursor_x > 0 {
+            self.buffer.cursor_controller.cursor_x -= close.len_utf8();
+        }
+        true
+    }
+
+    pub(crate) fn delete_pair(&mut self) -> bool {
+        if !self.config.auto_close {
+            return false;
+        }
+        let pair = match self.chars_around_cursor() {
+            (Some(open), Some(close)) => {
+                PAIRS.contains(&(open, close)) || (is_quote(open) && open == close)
+            }
+            _ => false,
+        };
+        if !pair || self.reject_edit() {
+            return pair;
+        }
+        let (x, y) = (
+            self.buffer.cursor_controller.cursor_x,
+            self.buffer.cursor_controller.cursor_y,
+        );
+        self.buffer.editor_rows.replace_range(y, x - 1, x + 1, "");
+        self.buffer.cursor_controller.cursor_x = x - 1;
+        self.buffer.dirty += 1;
+        true
+    }
+
+    fn chars_around_cursor(&self) -> (Option<char>, Option<char>) {
+        let cursor_controller = &self.buffer.cursor_controller;
+        match self
+            .buffer
+            .editor_rows
+            .row_contents
+            .get(cursor_controller.cursor_y)
+        {
+            Some(row) => {
+                let (before, after) = row.row_content.split_at(cursor_controller.cursor_x);
+                (before.chars().next_back(), after.chars().next())
+            }
+            None => (None, None),
+        }
+    }
+}
+
+fn is_closer(c: char) -> bool {
+    PAIRS.iter().any(|&(_, close)| c == close)
+}
+
+fn is_quote(c: char) -> bool {
+    c == '"' || c == '\''
+}
@@ -0,0 +1,308 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{cmp, fs, mem};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+
+use crate::config::Config;
+use crate::theme::Theme;
+use crate::{EditorContents, Reader, StatusMessage};
+
+struct Entry {
+    name: String,
+    is_dir: bool,
+}
+
+pub(crate) struct Browser {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    row_offset: usize,
+    win_size: (usize, usize),
+    editor_contents: EditorContents,
+    status_message: StatusMessage,
+    theme: Theme,
+}
+
+impl Browser {
+    pub(crate) fn open(dir: &Path, config: &Config) -> io::Result<Self> {
+        let win_size = terminal::size()
+            .map(|(x, y)| (x as usize, y as usize - 2))
+            .unwrap();
+        let mut browser = Self {
+            dir: fs::canonicalize(dir)?,
+            entries: Vec::new(),
+            selected: 0,
+            row_offset: 0,
+            win_size,
+            editor_contents: EditorContents::new(),
+            status_message: StatusMessage::new(
+                "HELP: Enter = Open | - = Up | %/d = New file/dir | R = Rename | D = Delete".into(),
+            ),
+            theme: config.theme().unwrap_or_default(),
+        };
+        browser.read_dir()?;
+        Ok(browser)
+    }
+
+    pub(crate) fn run(&mut self) -> crossterm::Result<Option<PathBuf>> {
+        let reader = Reader;
+        loop {
+            self.refresh_screen()?;
+            let key = reader.read_key()?;
+            match key {
+                KeyEvent {
+                    code: KeyCode::Char('q'),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::CONTROL,
+                }
+                | KeyEvent {
+                    code: KeyCode::Esc, ..
+                } => return Ok(None),
+                KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                } => {
+                    if let Some(file) = self.enter() {
+                        return Ok(Some(file));
+                    }
+                }
+                KeyEvent {
+                    code: KeyCode::Char('%'),
+                    ..
+                } => {
+                    if let Some(name) = self.prompt("New file: ")? {
+                        return Ok(Some(self.dir.join(name)));
+                    }
+                }
+                _ => self.process_key(key)?,
+            }
+        }
+    }
+
+    fn process_key(&mut self, key: KeyEvent) -> crossterm::Result<()> {
+        let len = self.entries.len();
+        match key.code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = cmp::min(self.selected + 1, len.saturating_sub(1)),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(self.win_size.1),
+            KeyCode::PageDown => {
+                self.selected = cmp::min(self.selected + self.win_size.1, len.saturating_sub(1))
+            }
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = len.saturating_sub(1),
+            KeyCode::Char('-') | KeyCode::Backspace => self.go_up(),
+            KeyCode::Char('d') => {
+                if let Some(name) = self.prompt("New directory: ")? {
+                    let result = fs::create_dir(self.dir.join(&name));
+                    self.after(result, &name);
+                }
+            }
+            KeyCode::Char('R') => {
+                if let Some(from) = self.entries.get(self.selected).map(|it| it.name.clone()) {
+                    if let Some(to) = self.prompt(&format!("Rename {} to: ", from))? {
+                        let result = fs::rename(self.dir.join(&from), self.dir.join(&to));
+                        self.after(result, &to);
+                    }
+                }
+            }
+            KeyCode::Char('D') => {
+                if let Some(entry) = self.entries.get(self.selected) {
+                    let path = self.dir.join(&entry.name);
+                    let is_dir = entry.is_dir;
+                    let question = format!("Delete {}? (y/n) ", entry.name);
+                    if self.prompt(&question)?.as_deref() == Some("y") {
+                        let result = if is_dir {
+                            fs::remove_dir(path)
+                        } else {
+                            fs::remove_file(path)
+                        };
+                        self.after(result, "");
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self) -> Option<PathBuf> {
+        let entry = self.entries.get(self.selected)?;
+        let path = self.dir.join(&entry.name);
+        if !entry.is_dir {
+            return Some(path);
+        }
+        let previous = mem::replace(&mut self.dir, path);
+        if let Err(err) = self.read_dir() {
+            self.dir = previous;
+            self.status_message
+                .set_message(format!("Can't open directory: {}", err));
+        } else {
+            self.selected = 0;
+        }
+        None
+    }
+
+    fn go_up(&mut self) {
+        let parent = match self.dir.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => return,
+        };
+        let left = self
+            .dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        let previous = mem::replace(&mut self.dir, parent);
+        match self.read_dir() {
+            Ok(()) => self.select(left.as_deref().unwrap_or("")),
+            Err(err) => {
+                self.dir = previous;
+                self.status_message
+                    .set_message(format!("Can't open directory: {}", err));
+            }
+        }
+    }
+
+    fn after(&mut self, result: io::Result<()>, name: &str) {
+        if let Err(err) = result.and_then(|_| self.read_dir()) {
+            self.status_message
+                .set_message(format!("I/O error: {}", err));
+            return;
+        }
+        self.select(name);
+    }
+
+    fn select(&mut self, name: &str) {
+        self.selected = match self.entries.iter().position(|entry| entry.name == name) {
+            Some(at) => at,
+            None => cmp::min(self.selected, self.entries.len().saturating_sub(1)),
+        };
+    }
+
+    fn read_dir(&mut self) -> io::Result<()> {
+        let mut entries = fs::read_dir(&self.dir)?
+            .filter_map(Result::ok)
+            .map(|entry| Entry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                is_dir: entry.path().is_dir(),
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        self.entries = entries;
+        Ok(())
+    }
+
+    fn prompt(&mut self, message: &str) -> crossterm::Result<Option<String>> {
+        let mut input = String::new();
+        loop {
+            self.status_message
+                .set_message(format!("{}{}", message, input));
+            self.refresh_screen()?;
+            match Reader.read_key()? {
+                KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                } => break,
+                KeyEvent {
+                    code: KeyCode::Esc, ..
+                } => {
+                    input.clear();
+                    break;
+                }
+                KeyEvent {
+                    code: KeyCode::Backspace,
+                    ..
+                } => {
+                    input.pop();
+                }
+                KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                } => input.push(c),
+                _ => {}
+            }
+        }
+        self.status_message.set_message(String::new());
+        Ok(Some(input).filter(|input| !input.is_empty()))
+    }
+
+    fn scroll(&mut self) {
+        self.row_offset = cmp::min(self.row_offset, self.selected);
+        if self.selected >= self.row_offset + self.win_size.1 {
+            self.row_offset = self.selected - self.win_size.1 + 1;
+        }
+    }
+
+    fn refresh_screen(&mut self) -> crossterm::Result<()> {
+        self.scroll();
+        queue!(self.editor_contents, cursor::Hide, cursor::MoveTo(0, 0))?;
+        let width = self.win_size.0;
+        for i in 0..self.win_size.1 {
+            match self.entries.get(self.row_offset + i) {
+                Some(entry) => {
+                    let mut line = entry.name.clone();
+                    if entry.is_dir {
+                        line.push('/');
+                    }
+                    let line: String = line.chars().take(width).collect();
+                    if self.row_offset + i == self.selected {
+                        self.editor_contents
+                            .push_str(&style::Attribute::Reverse.to_string());
+                    }
+                    if entry.is_dir {
+                        self.editor_contents
+                            .push_str(&style::Attribute::Bold.to_string());
+                    }
+                    self.editor_contents.push_str(&line);
+                    self.editor_contents
+                        .push_str(&style::Attribute::Reset.to_string());
+                }
+                None if i == 0 => self.editor_contents.push_str("(empty)"),
+                None => self.editor_contents.push('~'),
+            }
+            queue!(
+                self.editor_contents,
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+            self.editor_contents.push_str("\r\n");
+        }
+        self.draw_status_bar();
+        queue!(
+            self.editor_contents,
+            terminal::Clear(ClearType::UntilNewLine)
+        )?;
+        if let Some(msg) = self.status_message.message() {
+            let msg: String = msg.chars().take(width).collect();
+            self.editor_contents.push_str(&msg);
+        }
+        let cursor_y = self.selected.saturating_sub(self.row_offset);
+        queue!(
+            self.editor_contents,
+            cursor::MoveTo(0, cursor_y as u16),
+            cursor::Show
+        )?;
+        self.editor_contents.flush()
+    }
+
+    fn draw_status_bar(&mut self) {
+        let info = format!("{} -- {} entries", self.dir.display(), self.entries.len());
+        let line_info = format!("{}/{}", self.selected + 1, self.entries.len());
+        let width = self.win_size.0;
+        let mut status: String = info.chars().take(width).collect();
+        let status_len = status.chars().count();
+        if status_len + line_info.len() <= width {
+            status.push_str(&" ".repeat(width - status_len - line_info.len()));
+            status.push_str(&line_info);
+        }
+        self.editor_contents
+            .set_foreground(self.theme.status_bar_foreground);
+        self.editor_contents
+            .set_background(self.theme.status_bar_background);
+        self.editor_contents.push_str(&status);
+        self.editor_contents
+            .push_str(&style::Attribute::Reset.to_string());
+        self.editor_contents.push_str("\r\n");
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+pub(crate) const USAGE: &str = "\
+Usage: pound [options] [+line] [file[:line[:column]]...]
+
+Options:
+  -R, --readonly  open the files read-only
+      --view      page through one file without loading it into memory
+      --hex       edit one file as bytes
+  -d, --diff      compare two files side by side
+  -h, --help      print this help and exit
+  -V, --version   print the version and exit
+";
+
+pub(crate) enum Invocation {
+    Edit(Args),
+    Help,
+    Version,
+}
+
+#[derive(Default)]
+pub(crate) struct Args {
+    pub(crate) files: Vec<PathBuf>,
+    /// The 1-based line and column to start the first file at.
+    pub(crate) position: Option<(usize, usize)>,
+    pub(crate) read_only: bool,
+    pub(crate) view: bool,
+    pub(crate) hex: bool,
+    pub(crate) diff: bool,
+}
+
+pub(crate) fn parse(arguments: impl Iterator<Item = String>) -> Result<Invocation, String> {
+    let mut args = Args::default();
+    let mut line = None;
+    let mut options = true;
+    for argument in arguments {
+        if options && argument.starts_with('-') && argument != "-" {
+            match argument.as_str() {
+                "--" => options = false,
+                "-h" | "--help" => return Ok(Invocation::Help),
+                "-V" | "--version" => return Ok(Invocation::Version),
+                "-R" | "--readonly" => args.read_only = true,
+                "--view" => args.view = true,
+                "--hex" => args.hex = true,
+                "-d" | "--diff" => args.diff = true,
+                _ => return Err(format!("unknown option '{}'", argument)),
+            }
+        } else if let Some(number) = argument.strip_prefix('+').filter(|_| options) {
+            line = Some(
+                number
+                    .parse()
+                    .map_err(|_| format!("invalid line number '{}'", argument))?,
+            );
+        } else {
+            let (file, position) = split_position(&argument);
+            if args.files.is_empty() {
+                args.position = position;
+            }
+            args.files.push(file);
+        }
+    }
+    if let Some(line) = line {
+        args.position = Some((line, 1));
+    }
+    if args.view && args.hex {
+        return Err("--view and --hex can't be combined".into());
+    }
+    if args.diff && (args.view || args.hex) {
+        return Err("--diff can't be combined with --view or --hex".into());
+    }
+    if args.diff && args.files.len() != 2 {
+        return Err("--diff needs exactly two files".into());
+    }
+    for (flag, given) in [("--view", args.view), ("--hex", args.hex)] {
+        if given && args.files.len() != 1 {
+            return Err(format!("{} needs exactly one file", flag));
+        }
+    }
+    if args.hex && args.read_only {
+        return Err("the hex editor has no read-only mode, use --view".into());
+    }
+    Ok(Invocation::Edit(args))
+}
+
+/// Splits `file:line:column`, unless a file is really named that way.
+fn split_position(arg: &str) -> (PathBuf, Option<(usize, usize)>) {
+    if Path::new(arg).exists() {
+        return (arg.into(), None);
+    }
+    let trimmed = arg.trim_end_matches(':');
+    if let Some((rest, last)) = trimmed.rsplit_once(':') {
+        if let Ok(last) = last.parse() {
+            return match rest.rsplit_once(':') {
+                Some((file, line)) => match line.parse() {
+                    Ok(line) => (file.into(), Some((line, last))),
+                    Err(_) => (rest.into(), Some((last, 1))),
+                },
+                None => (rest.into(), Some((last, 1))),
+            };
+        }
+    }
+    (arg.into(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(arguments: &[&str]) -> Result<Args, String> {
+        match parse(arguments.iter().map(|argument| argument.to_string()))? {
+            Invocation::Edit(args) => Ok(args),
+            Invocation::Help => Err("help".into()),
+            Invocation::Version => Err("version".into()),
+        }
+    }
+
+    #[test]
+    fn options_and_files() {
+        let args = parse_args(&["-R", "a", "--", "-b", "+3"]).unwrap();
+        assert!(args.read_only);
+        assert_eq!(args.files, [PathBuf::from("a"), "-b".into(), "+3".into()]);
+        assert_eq!(args.position, None);
+        assert_eq!(parse_args(&["x", "--help"]).err().unwrap(), "help");
+        assert_eq!(parse_args(&["-V"]).err().unwrap(), "version");
+        assert_eq!(parse_args(&["-"]).unwrap().files, [PathBuf::from("-")]);
+    }
+
+    #[test]
+    fn positions() {
+        let args = parse_args(&["+12", "no/such/file"]).unwrap();
+        assert_eq!(args.position, Some((12, 1)));
+        let args = parse_args(&["no/such/file:4:2:", "other:9"]).unwrap();
+        assert_eq!(args.files, [PathBuf::from("no/such/file"), "other".into()]);
+        assert_eq!(args.position, Some((4, 2)));
+        assert_eq!(split_position("a:b:5"), ("a:b".into(), Some((5, 1))));
+        assert_eq!(split_position("a:b"), ("a:b".into(), None));
+        assert_eq!(split_position("Cargo.toml"), ("Cargo.toml".into(), None));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |arguments: &[&str]| parse_args(arguments).err().unwrap();
+        assert_eq!(error(&["--nope"]), "unknown option '--nope'");
+        assert_eq!(error(&["+x", "a"]), "invalid line number '+x'");
+        assert_eq!(
+            error(&["--view", "--hex", "a"]),
+            "--view and --hex can't be combined"
+        );
+        assert_eq!(error(&["-d", "a"]), "--diff needs exactly two files");
+        assert_eq!(error(&["--hex", "a", "b"]), "--hex needs exactly one file");
+        assert_eq!(
+            error(&["--hex", "-R", "a"]),
+            "the hex editor has no read-only mode, use --view"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crate::theme::invalid_data;
+
+struct Provider {
+    copy: &'static [&'static str],
+    paste: &'static [&'static str],
+    display: Option<&'static str>,
+}
+
+const PROVIDERS: [Provider; 4] = [
+    Provider {
+        copy: &["pbcopy"],
+        paste: &["pbpaste"],
+        display: None,
+    },
+    Provider {
+        copy: &["wl-copy"],
+        paste: &["wl-paste", "--no-newline"],
+        display: Some("WAYLAND_DISPLAY"),
+    },
+    Provider {
+        copy: &["xclip", "-selection", "clipboard"],
+        paste: &["xclip", "-selection", "clipboard", "-o"],
+        display: Some("DISPLAY"),
+    },
+    Provider {
+        copy: &["xsel", "--clipboard", "--input"],
+        paste: &["xsel", "--clipboard", "--output"],
+        display: Some("DISPLAY"),
+    },
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Backend {
+    Auto,
+    Native,
+    Osc52,
+    Internal,
+}
+
+impl Backend {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "native" => Some(Self::Native),
+            "osc52" => Some(Self::Osc52),
+            "internal" => Some(Self::Internal),
+            _ => None,
+        }
+    }
+}
+
+enum Target {
+    Tools(&'static Provider),
+    Osc52,
+    Memory,
+}
+
+pub(crate) struct Clipboard {
+    target: Target,
+    last: Option<(String, bool)>,
+}
+
+impl Clipboard {
+    pub(crate) fn new(backend: Backend) -> Self {
+        let provider = || {
+            PROVIDERS.iter().find(|provider| {
+                provider
+                    .display
+                    .map_or(true, |display| env::var_os(display).is_some())
+                    && installed(provider.copy[0])
+                    && installed(provider.paste[0])
+            })
+        };
+        let remote = env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some();
+        let target = match backend {
+            Backend::Auto => match provider() {
+                Some(provider) => Target::Tools(provider),
+                None if remote => Target::Osc52,
+                None => Target::Memory,
+            },
+            Backend::Native => provider().map_or(Target::Memory, Target::Tools),
+            Backend::Osc52 => Target::Osc52,
+            Backend::Internal => Target::Memory,
+        };
+        Self { target, last: None }
+    }
+
+    pub(crate) fn copy(&mut self, text: String, linewise: bool) -> io::Result<()> {
+        let result = match self.target {
+            Target::Tools(provider) => run_copy(provider.copy, &text),
+            Target::Osc52 => {
+                let mut stdout = io::stdout();
+                write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))
+                    .and_then(|_| stdout.flush())
+            }
+            Target::Memory => Ok(()),
+        };
+        self.last = Some((text, linewise));
+        result
+    }
+
+    pub(crate) fn paste(&self) -> Option<(String, bool)> {
+        let text = match self.target {
+            Target::Tools(provider) => run_paste(provider.paste).ok(),
+            Target::Osc52 | Target::Memory => None,
+        }
+        .map(|text| text.replace("\r\n", "\n"));
+        match (text, &self.last) {
+            (Some(text), Some((last, linewise))) => {
+                let linewise = *linewise && text == *last;
+                Some((text, linewise))
+            }
+            (Some(text), None) => Some((text, false)),
+            (None, last) => last.clone(),
+        }
+        .filter(|(text, _)| !text.is_empty())
+    }
+}
+
+fn installed(program: &str) -> bool {
+    env::var_os("PATH").map_or(false, |path| {
+        env::split_paths(&path).any(|dir| dir.join(program).is_file())
+    })
+}
+
+fn run_copy(command: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(text.as_bytes())?;
+    child.wait()?;
+    Ok(())
+}
+
+fn run_paste(command: &[&str]) -> io::Result<String> {
+    let output = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(invalid_data(format!("{} failed", command[0])));
+    }
+    String::from_utf8(output.stdout).map_err(invalid_data)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_test_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (text, encoded) in vectors {
+            assert_eq!(base64(text.as_bytes()), encoded);
+        }
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+    }
+}
@@ -0,0 +1,259 @@
+use crate::conflict::Resolution;
+use crate::modal::Mode;
+use crate::{Editor, LineNumbers};
+
+/// Returns `false` to quit the editor.
+pub(crate) type Run = fn(&mut Editor, &str) -> crossterm::Result<bool>;
+
+struct Command {
+    names: &'static [&'static str],
+    run: Run,
+}
+
+pub(crate) struct Commands {
+    commands: Vec<Command>,
+}
+
+impl Commands {
+    pub(crate) fn new() -> Self {
+        let mut commands = Self {
+            commands: Vec::new(),
+        };
+        commands.register(&["w", "write"], write);
+        commands.register(&["q", "quit"], quit);
+        commands.register(&["q!", "quit!"], force_quit);
+        commands.register(&["wq", "x"], write_quit);
+        commands.register(&["e", "edit"], edit);
+        commands.register(&["set"], set);
+        commands.register(&["bn", "bnext"], |editor, _| {
+            editor.output.cycle_buffer(1);
+            Ok(true)
+        });
+        commands.register(&["bp", "bprevious"], |editor, _| {
+            let count = editor.output.buffers.len();
+            editor.output.cycle_buffer(count);
+            Ok(true)
+        });
+        commands.register(&["n", "next"], |editor, _| {
+            editor.output.step_argument(true);
+            Ok(true)
+        });
+        commands.register(&["N", "prev", "previous"], |editor, _| {
+            editor.output.step_argument(false);
+            Ok(true)
+        });
+        commands.register(&["ls", "buffers"], |editor, _| {
+            editor.output.list_buffers();
+            Ok(true)
+        });
+        commands.register(&["marks"], |editor, _| {
+            editor.output.list_marks();
+            Ok(true)
+        });
+        commands.register(&["sp", "split"], |editor, _| {
+            editor.output.split_window(false);
+            Ok(true)
+        });
+        commands.register(&["vs", "vsplit"], |editor, _| {
+            editor.output.split_window(true);
+            Ok(true)
+        });
+        commands.register(&["close"], |editor, _| {
+            editor.output.close_window();
+            Ok(true)
+        });
+        commands.register(&["retab"], |editor, _| {
+            editor.output.retab();
+            Ok(true)
+        });
+        commands.register(&["trim"], |editor, _| {
+            editor.output.trim_trailing_whitespace();
+            Ok(true)
+        });
+        commands.register(&["ours"], |editor, _| {
+            editor.output.resolve_conflict(Resolution::Ours);
+            Ok(true)
+        });
+        commands.register(&["theirs"], |editor, _| {
+            editor.output.resolve_conflict(Resolution::Theirs);
+            Ok(true)
+        });
+        commands.register(&["both"], |editor, _| {
+            editor.output.resolve_conflict(Resolution::Both);
+            Ok(true)
+        });
+        commands.register(&["!"], |editor, command| {
+            editor.output.shell(command);
+            Ok(true)
+        });
+        commands.register(&["filter"], |editor, command| {
+            editor.output.filter(command);
+            Ok(true)
+        });
+        commands.register(&["r", "read"], |editor, source| {
+            editor.output.read(source);
+            Ok(true)
+        });
+        commands.register(&["blame"], |editor, _| {
+            editor.output.blame();
+            Ok(true)
+        });
+        commands.register(&["make"], |editor, args| {
+            editor.output.make(args)?;
+            Ok(true)
+        });
+        commands.register(&["cl", "clist", "copen"], |editor, _| {
+            editor.output.quickfix_list()?;
+            Ok(true)
+        });
+        commands.register(&["cn", "cnext"], |editor, _| {
+            editor.output.step_error(true);
+            Ok(true)
+        });
+        commands.register(&["cp", "cprevious"], |editor, _| {
+            editor.output.step_error(false);
+            Ok(true)
+        });
+        commands.register(&["grep"], |editor, pattern| {
+            editor.output.live_grep(pattern)?;
+            Ok(true)
+        });
+        commands.register(&["h", "help"], |editor, _| {
+            editor.show_help();
+            Ok(true)
+        });
+        commands
+    }
+
+    pub(crate) fn register(&mut self, names: &'static [&'static str], run: Run) {
+        self.commands.push(Command { names, run });
+    }
+
+    pub(crate) fn names(&self) -> Vec<Vec<&'static str>> {
+        self.commands
+            .iter()
+            .enumerate()
+            .map(|(i, command)| {
+                let later = &self.commands[i + 1..];
+                command
+                    .names
+                    .iter()
+                    .copied()
+                    .filter(|name| !later.iter().any(|other| other.names.contains(name)))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|names| !names.is_empty())
+            .collect()
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<Run> {
+        self.commands
+            .iter()
+            .rev()
+            .find(|command| command.names.contains(&name))
+            .map(|command| command.run)
+    }
+}
+
+fn write(editor: &mut Editor, path: &str) -> crossterm::Result<bool> {
+    if !path.is_empty() {
+        editor.output.buffer.editor_rows.set_filename(path.into());
+    }
+    editor.output.save()?;
+    Ok(true)
+}
+
+fn quit(editor: &mut Editor, _: &str) -> crossterm::Result<bool> {
+    if editor.output.is_dirty() {
+        editor
+            .output
+            .status_message
+            .set_message("File has unsaved changes (add ! to quit anyway)".into());
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+fn force_quit(_: &mut Editor, _: &str) -> crossterm::Result<bool> {
+    Ok(false)
+}
+
+fn write_quit(editor: &mut Editor, path: &str) -> crossterm::Result<bool> {
+    write(editor, path)?;
+    quit(editor, "")
+}
+
+fn edit(editor: &mut Editor, path: &str) -> crossterm::Result<bool> {
+    if path.is_empty() {
+        editor
+            .output
+            .status_message
+            .set_message("Usage: e <file>".into());
+    } else {
+        editor.output.open_buffer(path.into());
+    }
+    Ok(true)
+}
+
+fn set(editor: &mut Editor, option: &str) -> crossterm::Result<bool> {
+    let output = &mut editor.output;
+    if let Some((name, value)) = option.split_once('=') {
+        match (name, value.parse()) {
+            ("shiftwidth" | "sw", Ok(width)) if width > 0 => {
+                output.buffer.editor_rows.indent.width = width
+            }
+            ("colorcolumn" | "cc", Ok(column)) => {
+                output.buffer.editor_rows.color_column = Some(column).filter(|&column| column > 0)
+            }
+            ("scrolloff" | "so", Ok(margin)) => {
+                output.buffer.cursor_controller.scroll_margin = margin
+            }
+            _ => output
+                .status_message
+                .set_message(format!("Invalid option: {}", option)),
+        }
+        return Ok(true);
+    }
+    let (name, enable) = match option.strip_prefix("no") {
+        Some(name) => (name, false),
+        None => (option, true),
+    };
+    let line_numbers = &mut output.buffer.cursor_controller.line_numbers;
+    match name {
+        "number" | "nu" => {
+            *line_numbers = if enable {
+                LineNumbers::Absolute
+            } else {
+                LineNumbers::Off
+            }
+        }
+        "relativenumber" | "rnu" => {
+            *line_numbers = if enable {
+                LineNumbers::Relative
+            } else {
+                LineNumbers::Off
+            }
+        }
+        "wrap" => output.buffer.editor_rows.soft_wrap = enable,
+        "list" => output.buffer.editor_rows.show_invisibles = enable,
+        "spell" => output.enable_spelling(enable),
+        "occurrences" => output.buffer.editor_rows.highlight_occurrences = enable,
+        "readonly" | "ro" => output.buffer.editor_rows.read_only = enable,
+        "autoclose" => output.config.auto_close = enable,
+        "autoindent" => output.config.auto_indent = enable,
+        "cursorline" | "cul" => output.config.cursor_line = enable,
+        "cursorcolumn" | "cuc" => output.config.cursor_column = enable,
+        "scrollbar" => output.config.scrollbar = enable,
+        "trimwhitespace" => output.config.trim_trailing_whitespace = enable,
+        "expandtab" | "et" => output.buffer.editor_rows.indent.spaces = enable,
+        "modal" => {
+            output.buffer.cursor_controller.anchor = None;
+            output.buffer.cursor_controller.block = None;
+            output.mode = enable.then(|| Mode::Normal);
+        }
+        _ => output
+            .status_message
+            .set_message(format!("Unknown option: {}", option)),
+    }
+    Ok(true)
+}
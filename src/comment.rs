@@ -0,0 +1,90 @@
+use std::cmp;
+
+use crate::Output;
+
+impl Output {
+    pub(crate) fn toggle_comment(&mut self) {
+        let leader = match self
+            .buffer
+            .editor_rows
+            .syntax
+            .as_ref()
+            .and_then(|syntax| syntax.line_comment())
+        {
+            Some(leader) => leader.to_string(),
+            None => {
+                self.status_message
+                    .set_message("No line comments for this file type".into());
+                return;
+            }
+        };
+        let (first, last) = match self.selected_rows() {
+            Some(rows) => rows,
+            None => return,
+        };
+        if self.reject_edit() {
+            return;
+        }
+        let editor_rows = &self.buffer.editor_rows;
+        let lines: Vec<usize> = (first..=last)
+            .filter(|&y| !editor_rows.get_row(y).trim().is_empty())
+            .collect();
+        let indent = |y: usize| {
+            let row = editor_rows.get_row(y);
+            row.len() - row.trim_start().len()
+        };
+        let commented = !lines.is_empty()
+            && lines
+                .iter()
+                .all(|&y| editor_rows.get_row(y)[indent(y)..].starts_with(&leader));
+        // The byte range taken out of, or inserted into, each line.
+        let edits: Vec<(usize, usize, usize)> = if commented {
+            lines
+                .iter()
+                .map(|&y| {
+                    let at = indent(y);
+                    let after = at + leader.len();
+                    let end = if editor_rows.get_row(y)[after..].starts_with(' ') {
+                        after + 1
+                    } else {
+                        after
+                    };
+                    (y, at, end)
+                })
+                .collect()
+        } else {
+            let at = lines.iter().map(|&y| indent(y)).min().unwrap_or(0);
+            lines.iter().map(|&y| (y, at, at)).collect()
+        };
+        if edits.is_empty() {
+            return;
+        }
+        let marker = format!("{} ", leader);
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        for (y, from, to) in edits {
+            let shift = |x: &mut usize| {
+                if commented {
+                    if *x > from {
+                        *x = cmp::max(from, x.saturating_sub(to - from));
+                    }
+                } else if *x >= from {
+                    *x += marker.len();
+                }
+            };
+            if commented {
+                self.buffer.editor_rows.replace_range(y, from, to, "");
+            } else {
+                self.buffer.editor_rows.replace_range(y, from, to, &marker);
+            }
+            if cursor_controller.cursor_y == y {
+                shift(&mut cursor_controller.cursor_x);
+            }
+            if let Some((x, anchor_y)) = &mut cursor_controller.anchor {
+                if *anchor_y == y {
+                    shift(x);
+                }
+            }
+        }
+        self.buffer.dirty += 1;
+    }
+}
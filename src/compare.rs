@@ -0,0 +1,169 @@
+use std::cmp;
+use std::ops::Range;
+
+use crate::{diff, EditorRows, Output};
+
+pub(crate) struct Mark {
+    pub(crate) only: bool,
+    pub(crate) ranges: Vec<Range<usize>>,
+}
+
+pub(crate) struct Comparison {
+    buffers: [usize; 2],
+    marks: [Vec<Option<Mark>>; 2],
+    hunks: Vec<(Range<usize>, Range<usize>)>,
+    changes: Option<[u64; 2]>,
+}
+
+impl Comparison {
+    pub(crate) fn mark(&self, buffer: usize, y: usize) -> Option<&Mark> {
+        let side = self.buffers.iter().position(|&it| it == buffer)?;
+        self.marks[side].get(y)?.as_ref()
+    }
+
+    fn counterpart(&self, side: usize, y: usize) -> usize {
+        let mut shift = 0;
+        for (old, new) in &self.hunks {
+            let (from, to) = if side == 0 { (old, new) } else { (new, old) };
+            if y < from.start {
+                break;
+            }
+            if y < from.end {
+                return to.start + cmp::min(y - from.start, to.len().saturating_sub(1));
+            }
+            shift = to.end as isize - from.end as isize;
+        }
+        cmp::max(y as isize + shift, 0) as usize
+    }
+}
+
+fn changed_ranges(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let (old_chars, new_chars): (Vec<_>, Vec<_>) =
+        (old.char_indices().collect(), new.char_indices().collect());
+    let bytes = |chars: &[(usize, char)], line: &str, range: Range<usize>| {
+        let at = |i: usize| chars.get(i).map_or(line.len(), |&(byte, _)| byte);
+        at(range.start)..at(range.end)
+    };
+    let old_text: Vec<char> = old_chars.iter().map(|&(_, c)| c).collect();
+    let new_text: Vec<char> = new_chars.iter().map(|&(_, c)| c).collect();
+    diff::diff(&old_text, &new_text)
+        .into_iter()
+        .map(|(from, to)| (bytes(&old_chars, old, from), bytes(&new_chars, new, to)))
+        .unzip()
+}
+
+impl Output {
+    pub(crate) fn start_diff(&mut self) {
+        self.split_window(true);
+        self.switch_buffer(1);
+        self.focus_window(0);
+        self.comparison = Some(Comparison {
+            buffers: [0, 1],
+            marks: [Vec::new(), Vec::new()],
+            hunks: Vec::new(),
+            changes: None,
+        });
+    }
+
+    fn buffer_rows(&mut self, buffer: usize) -> &mut EditorRows {
+        match self.buffer_slot(buffer) {
+            Some(slot) => &mut self.buffers[slot].editor_rows,
+            None => &mut self.buffer.editor_rows,
+        }
+    }
+
+    pub(crate) fn update_comparison(&mut self) {
+        let buffers = match &self.comparison {
+            Some(comparison) => comparison.buffers,
+            None => return,
+        };
+        let mut changes = [0; 2];
+        for side in 0..2 {
+            let editor_rows = self.buffer_rows(buffers[side]);
+            editor_rows.load_all();
+            changes[side] = editor_rows.changes;
+        }
+        if self.comparison.as_ref().unwrap().changes == Some(changes) {
+            return;
+        }
+        let rows = |editor_rows: &EditorRows| -> Vec<String> {
+            editor_rows
+                .row_contents
+                .iter()
+                .map(|row| row.row_content.clone())
+                .collect()
+        };
+        let old = rows(self.buffer_rows(buffers[0]));
+        let new = rows(self.buffer_rows(buffers[1]));
+        let mut marks = [
+            old.iter().map(|_| None).collect::<Vec<_>>(),
+            new.iter().map(|_| None).collect::<Vec<_>>(),
+        ];
+        let hunks = diff::diff(&old, &new);
+        for (old_range, new_range) in &hunks {
+            for i in 0..cmp::max(old_range.len(), new_range.len()) {
+                let (y, other_y) = (old_range.start + i, new_range.start + i);
+                if y >= old_range.end || other_y >= new_range.end {
+                    let (side, y) = if y < old_range.end {
+                        (0, y)
+                    } else {
+                        (1, other_y)
+                    };
+                    marks[side][y] = Some(Mark {
+                        only: true,
+                        ranges: Vec::new(),
+                    });
+                    continue;
+                }
+                let (old_ranges, new_ranges) = changed_ranges(&old[y], &new[other_y]);
+                marks[0][y] = Some(Mark {
+                    only: false,
+                    ranges: old_ranges,
+                });
+                marks[1][other_y] = Some(Mark {
+                    only: false,
+                    ranges: new_ranges,
+                });
+            }
+        }
+        let comparison = self.comparison.as_mut().unwrap();
+        comparison.marks = marks;
+        comparison.hunks = hunks;
+        comparison.changes = Some(changes);
+    }
+
+    pub(crate) fn sync_comparison(&mut self) {
+        let comparison = match &self.comparison {
+            Some(comparison) => comparison,
+            None => return,
+        };
+        let side = match comparison
+            .buffers
+            .iter()
+            .position(|&buffer| buffer == self.current_buffer)
+        {
+            Some(side) => side,
+            None => return,
+        };
+        let other = comparison.buffers[1 - side];
+        let window = match (0..self.windows.len())
+            .find(|&window| window != self.current_window && self.window_buffer(window) == other)
+        {
+            Some(window) => window,
+            None => return,
+        };
+        let current = self.buffer.cursor_controller;
+        let cursor_controller = &mut self.windows[window].cursor_controller;
+        cursor_controller.cursor_y = comparison.counterpart(side, current.cursor_y);
+        cursor_controller.cursor_x = current.cursor_x;
+        // The two cursor lines side by side.
+        cursor_controller.row_offset = cursor_controller
+            .cursor_y
+            .saturating_sub(current.cursor_y - current.row_offset);
+        cursor_controller.column_offset = current.column_offset;
+        let rect = self.window_rect(window);
+        if rect.height > 0 {
+            self.scroll_window(window, rect);
+        }
+    }
+}
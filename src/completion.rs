@@ -0,0 +1,272 @@
+use std::cmp;
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::json::Json;
+use crate::lsp::Request;
+use crate::Output;
+
+const POPUP_HEIGHT: usize = 10;
+const POPUP_WIDTH: usize = 60;
+
+struct Item {
+    label: String,
+    detail: String,
+    text: String,
+    filter: String,
+    sort: String,
+}
+
+impl Item {
+    fn parse(item: &Json) -> Option<Self> {
+        let label = item.get("label")?.as_str()?;
+        let text = item
+            .get("textEdit")
+            .and_then(|edit| edit.get("newText"))
+            .or_else(|| item.get("insertText"))
+            .and_then(Json::as_str)
+            .unwrap_or(label);
+        let string = |key: &str| item.get(key).and_then(Json::as_str).unwrap_or(label);
+        Some(Self {
+            label: label.into(),
+            detail: item
+                .get("detail")
+                .and_then(Json::as_str)
+                .unwrap_or("")
+                .into(),
+            text: text.into(),
+            filter: string("filterText").into(),
+            sort: string("sortText").into(),
+        })
+    }
+}
+
+pub(crate) struct Completion {
+    buffer: usize,
+    start: (usize, usize),
+    items: Vec<Item>,
+    incomplete: bool,
+    typed: String,
+    shown: Vec<usize>,
+    selected: usize,
+    row_offset: usize,
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn fuzzy_score(candidate: &str, typed: &str) -> Option<(bool, usize)> {
+    let candidate = candidate.to_lowercase();
+    let typed = typed.to_lowercase();
+    let mut chars = candidate.chars();
+    let mut skipped = 0;
+    for c in typed.chars() {
+        loop {
+            if chars.next()? == c {
+                break;
+            }
+            skipped += 1;
+        }
+    }
+    Some((!candidate.starts_with(&typed), skipped))
+}
+
+impl Output {
+    fn word_start(&self) -> (usize, usize) {
+        let (x, y) = (
+            self.buffer.cursor_controller.cursor_x,
+            self.buffer.cursor_controller.cursor_y,
+        );
+        if y >= self.buffer.editor_rows.number_of_rows() {
+            return (x, y);
+        }
+        let row = &self.buffer.editor_rows.get_row(y)[..x];
+        let start = row
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_word(c))
+            .last()
+            .map_or(x, |(at, _)| at);
+        (start, y)
+    }
+
+    fn typed_since(&self, buffer: usize, start: (usize, usize)) -> Option<String> {
+        let cursor_controller = &self.buffer.cursor_controller;
+        if buffer != self.current_buffer
+            || cursor_controller.cursor_y != start.1
+            || cursor_controller.cursor_x < start.0
+            || start.1 >= self.buffer.editor_rows.number_of_rows()
+        {
+            return None;
+        }
+        let typed = &self.buffer.editor_rows.get_row(start.1)[start.0..cursor_controller.cursor_x];
+        typed.chars().all(is_word).then(|| typed.into())
+    }
+
+    pub(crate) fn complete_typed(&mut self, c: char) {
+        let triggers = match self.server_triggers("completionProvider") {
+            Some(triggers) => triggers,
+            None => return,
+        };
+        if !is_word(c) && !triggers.contains(&c) {
+            self.completion = None;
+            return;
+        }
+        let start = self.word_start();
+        if let Some(completion) = &self.completion {
+            if completion.start == start && !completion.incomplete {
+                return;
+            }
+        }
+        let request = Request::Completion {
+            buffer: self.current_buffer,
+            start,
+        };
+        if !self.language_servers.awaits(&request) {
+            self.request_at_cursor("textDocument/completion", Vec::new(), request);
+        }
+    }
+
+    pub(crate) fn receive_completion(
+        &mut self,
+        buffer: usize,
+        start: (usize, usize),
+        result: &Json,
+    ) {
+        if self.typed_since(buffer, start).is_none() {
+            return;
+        }
+        let items = result
+            .as_array()
+            .or_else(|| result.get("items").and_then(Json::as_array))
+            .unwrap_or(&[]);
+        let mut items: Vec<Item> = items.iter().filter_map(Item::parse).collect();
+        items.sort_by(|one, other| one.sort.cmp(&other.sort));
+        self.completion = Some(Completion {
+            buffer,
+            start,
+            items,
+            incomplete: result.get("isIncomplete") == Some(&Json::Bool(true)),
+            typed: String::new(),
+            shown: Vec::new(),
+            selected: 0,
+            row_offset: 0,
+        });
+        self.update_completion();
+    }
+
+    pub(crate) fn update_completion(&mut self) {
+        let typed = match &self.completion {
+            Some(completion) => self.typed_since(completion.buffer, completion.start),
+            None => return,
+        };
+        let typed = match typed {
+            Some(typed) => typed,
+            None => {
+                self.completion = None;
+                return;
+            }
+        };
+        let completion = self.completion.as_mut().unwrap();
+        if typed != completion.typed || completion.shown.is_empty() {
+            let mut scored: Vec<((bool, usize), usize)> = completion
+                .items
+                .iter()
+                .enumerate()
+                .filter_map(|(i, item)| fuzzy_score(&item.filter, &typed).map(|score| (score, i)))
+                .collect();
+            scored.sort_by_key(|&(score, _)| score);
+            completion.shown = scored.into_iter().map(|(_, i)| i).collect();
+            completion.typed = typed;
+            completion.selected = 0;
+            completion.row_offset = 0;
+        }
+        if completion.shown.is_empty() {
+            self.completion = None;
+        }
+    }
+
+    pub(crate) fn completion_key(&mut self, key: KeyEvent) -> bool {
+        let completion = match &mut self.completion {
+            Some(completion) if key.modifiers.is_empty() => completion,
+            _ => return false,
+        };
+        let last = completion.shown.len() - 1;
+        match key.code {
+            KeyCode::Up => completion.selected = completion.selected.saturating_sub(1),
+            KeyCode::Down => completion.selected = cmp::min(completion.selected + 1, last),
+            KeyCode::PageUp => {
+                completion.selected = completion.selected.saturating_sub(POPUP_HEIGHT)
+            }
+            KeyCode::PageDown => {
+                completion.selected = cmp::min(completion.selected + POPUP_HEIGHT, last)
+            }
+            KeyCode::Tab | KeyCode::Enter => self.accept_completion(),
+            KeyCode::Esc => self.completion = None,
+            _ => return false,
+        }
+        true
+    }
+
+    fn accept_completion(&mut self) {
+        let completion = match self.completion.take() {
+            Some(completion) => completion,
+            None => return,
+        };
+        if self.reject_edit() {
+            return;
+        }
+        let text = &completion.items[completion.shown[completion.selected]].text;
+        let end = (
+            self.buffer.cursor_controller.cursor_x,
+            self.buffer.cursor_controller.cursor_y,
+        );
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.history.commit();
+        editor_rows.delete_range(completion.start, end);
+        self.buffer.cursor_controller.cursor_x = completion.start.0;
+        self.insert_text(text);
+        self.buffer.editor_rows.history.commit();
+    }
+
+    pub(crate) fn draw_completion(&mut self) -> crossterm::Result<()> {
+        let completion = match &mut self.completion {
+            Some(completion) => completion,
+            None => return Ok(()),
+        };
+        let height = cmp::min(completion.shown.len(), POPUP_HEIGHT);
+        completion.row_offset = cmp::min(completion.row_offset, completion.selected);
+        if completion.selected >= completion.row_offset + height {
+            completion.row_offset = completion.selected + 1 - height;
+        }
+        let visible = &completion.shown[completion.row_offset..completion.row_offset + height];
+        let label_width = visible
+            .iter()
+            .map(|&i| completion.items[i].label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<String> = visible
+            .iter()
+            .map(|&i| {
+                let item = &completion.items[i];
+                format!(
+                    " {:<width$} {} ",
+                    item.label,
+                    item.detail,
+                    width = label_width
+                )
+                .chars()
+                .take(POPUP_WIDTH)
+                .collect()
+            })
+            .collect();
+        let selected = completion.selected - completion.row_offset;
+        let editor_rows = &self.buffer.editor_rows;
+        let cursor_controller = &self.buffer.cursor_controller;
+        let row = editor_rows.get_editor_row(cursor_controller.cursor_y);
+        let word_width =
+            row.render_x(cursor_controller.cursor_x) - row.render_x(completion.start.0);
+        self.draw_popup(&lines, Some((selected, 0..usize::MAX)), word_width, false)
+    }
+}
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs};
+
+use crossterm::event::KeyEvent;
+use toml::Value;
+
+use crate::backup::Backup;
+use crate::clipboard::Backend;
+use crate::indent::Indent;
+use crate::keymap::{self, Action};
+use crate::theme::{invalid_data, Theme};
+use crate::{LineNumbers, TAB_STOP};
+
+/// `POUND_THEME`, `POUND_TAB_STOP` and `POUND_PERSISTENT_UNDO` override the file.
+#[derive(Clone)]
+pub(crate) struct Config {
+    pub(crate) tab_stop: usize,
+    pub(crate) line_numbers: LineNumbers,
+    pub(crate) theme: Option<String>,
+    pub(crate) scroll_margin: usize,
+    pub(crate) persistent_undo: bool,
+    pub(crate) backup: Backup,
+    pub(crate) swap: bool,
+    pub(crate) modal: bool,
+    pub(crate) auto_close: bool,
+    pub(crate) cursor_line: bool,
+    pub(crate) cursor_column: bool,
+    pub(crate) color_column: Option<usize>,
+    pub(crate) tint_past_column: bool,
+    pub(crate) scrollbar: bool,
+    pub(crate) status_format: Option<String>,
+    pub(crate) auto_indent: bool,
+    pub(crate) trim_trailing_whitespace: bool,
+    pub(crate) expand_tab: bool,
+    pub(crate) indent_width: Option<usize>,
+    pub(crate) autosave_idle: Option<Duration>,
+    pub(crate) autosave_interval: Option<Duration>,
+    pub(crate) detect_indent: bool,
+    pub(crate) file_types: HashMap<String, FileTypeConfig>,
+    pub(crate) clipboard: Backend,
+    pub(crate) make: String,
+    pub(crate) spell: bool,
+    pub(crate) highlight_occurrences: bool,
+    pub(crate) dictionary: Option<PathBuf>,
+    pub(crate) read_only: bool,
+    pub(crate) keys: Vec<(KeyEvent, Action)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tab_stop: TAB_STOP,
+            line_numbers: LineNumbers::Off,
+            theme: None,
+            scroll_margin: 0,
+            persistent_undo: false,
+            backup: Backup::Off,
+            swap: true,
+            modal: false,
+            auto_close: false,
+            cursor_line: false,
+            cursor_column: false,
+            color_column: None,
+            tint_past_column: false,
+            scrollbar: false,
+            status_format: None,
+            auto_indent: true,
+            trim_trailing_whitespace: false,
+            expand_tab: false,
+            indent_width: None,
+            autosave_idle: None,
+            autosave_interval: None,
+            detect_indent: true,
+            file_types: HashMap::new(),
+            clipboard: Backend::Auto,
+            make: "make".into(),
+            spell: false,
+            dictionary: None,
+            highlight_occurrences: true,
+            read_only: false,
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn load() -> io::Result<Self> {
+        let mut config = match config_file().map(fs::read_to_string) {
+            Some(Ok(source)) => Self::parse(&source)?,
+            Some(Err(err)) if err.kind() != ErrorKind::NotFound => return Err(err),
+            _ => Self::default(),
+        };
+        if let Ok(theme) = env::var("POUND_THEME") {
+            config.theme = Some(theme);
+        }
+        if let Some(tab_stop) = env::var("POUND_TAB_STOP")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&tab_stop| tab_stop > 0)
+        {
+            config.tab_stop = tab_stop;
+        }
+        if env::var_os("POUND_PERSISTENT_UNDO").is_some() {
+            config.persistent_undo = true;
+        }
+        Ok(config)
+    }
+
+    fn parse(source: &str) -> io::Result<Self> {
+        let value: Value = source.parse().map_err(invalid_data)?;
+        let mut config = Self::default();
+        if let Some(tab_stop) = read_number(&value, "tab_stop")? {
+            if tab_stop == 0 {
+                return Err(invalid_data("tab_stop must be at least 1"));
+            }
+            config.tab_stop = tab_stop;
+        }
+        if let Some(scroll_margin) = read_number(&value, "scroll_margin")? {
+            config.scroll_margin = scroll_margin;
+        }
+        config.line_numbers = match value.get("line_numbers") {
+            None => LineNumbers::Off,
+            Some(Value::Boolean(false)) => LineNumbers::Off,
+            Some(Value::Boolean(true)) => LineNumbers::Absolute,
+            Some(Value::String(mode)) => match mode.as_str() {
+                "off" => LineNumbers::Off,
+                "absolute" => LineNumbers::Absolute,
+                "relative" => LineNumbers::Relative,
+                _ => return Err(invalid_data(format!("unknown line_numbers {:?}", mode))),
+            },
+            Some(_) => return Err(invalid_data("invalid line_numbers")),
+        };
+        config.theme = match value.get("theme") {
+            None => None,
+            Some(Value::String(theme)) => Some(theme.clone()),
+            Some(_) => return Err(invalid_data("invalid theme")),
+        };
+        config.persistent_undo = read_bool(&value, "persistent_undo", false)?;
+        config.backup = match (value.get("backup"), value.get("backup_dir")) {
+            (Some(Value::Boolean(false)), _) | (None, None) => Backup::Off,
+            (Some(Value::Boolean(true)) | None, Some(Value::String(dir))) => Backup::directory(dir),
+            (Some(Value::Boolean(true)), None) => Backup::Beside,
+            (Some(Value::Boolean(true)) | None, Some(_)) => {
+                return Err(invalid_data("invalid backup_dir"))
+            }
+            (Some(_), _) => return Err(invalid_data("invalid backup")),
+        };
+        config.swap = read_bool(&value, "swap", true)?;
+        config.modal = read_bool(&value, "modal", false)?;
+        config.auto_close = read_bool(&value, "auto_close", false)?;
+        config.cursor_line = read_bool(&value, "cursor_line", false)?;
+        config.cursor_column = read_bool(&value, "cursor_column", false)?;
+        config.color_column = read_number(&value, "color_column")?;
+        config.tint_past_column = read_bool(&value, "tint_past_column", false)?;
+        config.scrollbar = read_bool(&value, "scrollbar", false)?;
+        config.status_format = match value.get("status_format") {
+            None => None,
+            Some(Value::String(format)) => Some(format.clone()),
+            Some(_) => return Err(invalid_data("invalid status_format")),
+        };
+        config.auto_indent = read_bool(&value, "auto_indent", true)?;
+        config.trim_trailing_whitespace = read_bool(&value, "trim_trailing_whitespace", false)?;
+        config.expand_tab = read_bool(&value, "expand_tab", false)?;
+        config.indent_width = read_indent_width(&value)?;
+        config.detect_indent = read_bool(&value, "detect_indent", true)?;
+        if let Some(autosave) = value.get("autosave") {
+            if !autosave.is_table() {
+                return Err(invalid_data("autosave must be a table"));
+            }
+            config.autosave_idle = read_seconds(autosave, "idle")?;
+            config.autosave_interval = read_seconds(autosave, "interval")?;
+        }
+        if let Some(file_types) = value.get("filetype") {
+            let file_types = file_types
+                .as_table()
+                .ok_or_else(|| invalid_data("filetype must be a table"))?;
+            for (name, settings) in file_types {
+                let file_type = FileTypeConfig::parse(settings)
+                    .map_err(|err| invalid_data(format!("filetype.{}: {}", name, err)))?;
+                config.file_types.insert(name.clone(), file_type);
+            }
+        }
+        config.clipboard = match value.get("clipboard") {
+            None => Backend::Auto,
+            Some(Value::String(name)) => Backend::from_name(name)
+                .ok_or_else(|| invalid_data(format!("unknown clipboard {:?}", name)))?,
+            Some(_) => return Err(invalid_data("invalid clipboard")),
+        };
+        config.make = match value.get("make") {
+            None => "make".into(),
+            Some(Value::String(command)) => command.clone(),
+            Some(_) => return Err(invalid_data("invalid make")),
+        };
+        config.spell = read_bool(&value, "spell", false)?;
+        config.highlight_occurrences = read_bool(&value, "highlight_occurrences", true)?;
+        config.dictionary = match value.get("dictionary") {
+            None => None,
+            Some(Value::String(path)) => Some(path.into()),
+            Some(_) => return Err(invalid_data("invalid dictionary")),
+        };
+        if let Some(keys) = value.get("keys") {
+            let keys = keys
+                .as_table()
+                .ok_or_else(|| invalid_data("keys must be a table"))?;
+            for (key, action) in keys {
+                let key = keymap::parse_key(key)
+                    .ok_or_else(|| invalid_data(format!("unknown key {:?}", key)))?;
+                let action = action
+                    .as_str()
+                    .and_then(Action::from_name)
+                    .ok_or_else(|| invalid_data(format!("unknown action {}", action)))?;
+                config.keys.push((key, action));
+            }
+        }
+        Ok(config)
+    }
+
+    pub(crate) fn indent_after(&self, file_type: Option<&str>) -> &str {
+        let configured = file_type
+            .and_then(|file_type| self.file_types.get(file_type))
+            .and_then(|settings| settings.indent_after.as_deref());
+        match (configured, file_type) {
+            (Some(indent_after), _) => indent_after,
+            (None, Some("python")) => ":([{",
+            (None, Some("markdown") | None) => "",
+            (None, Some(_)) => "([{",
+        }
+    }
+
+    pub(crate) fn indent(&self, file_type: Option<&str>) -> Indent {
+        let settings = file_type.and_then(|file_type| self.file_types.get(file_type));
+        Indent {
+            spaces: settings
+                .and_then(|settings| settings.expand_tab)
+                .unwrap_or(self.expand_tab),
+            width: settings
+                .and_then(|settings| settings.indent_width)
+                .or(self.indent_width)
+                .unwrap_or(self.tab_stop),
+        }
+    }
+
+    pub(crate) fn color_column(&self, file_type: Option<&str>) -> Option<usize> {
+        file_type
+            .and_then(|file_type| self.file_types.get(file_type))
+            .and_then(|settings| settings.color_column)
+            .or(self.color_column)
+            .filter(|&column| column > 0)
+    }
+
+    pub(crate) fn language_server(&self, file_type: &str) -> Option<&str> {
+        self.file_types
+            .get(file_type)
+            .and_then(|settings| settings.language_server.as_deref())
+    }
+
+    pub(crate) fn theme(&self) -> io::Result<Theme> {
+        match &self.theme {
+            Some(name) => Theme::load(name),
+            None => Ok(Theme::default()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct FileTypeConfig {
+    pub(crate) indent_after: Option<String>,
+    pub(crate) expand_tab: Option<bool>,
+    pub(crate) indent_width: Option<usize>,
+    pub(crate) language_server: Option<String>,
+    pub(crate) color_column: Option<usize>,
+}
+
+impl FileTypeConfig {
+    fn parse(value: &Value) -> io::Result<Self> {
+        if !value.is_table() {
+            return Err(invalid_data("must be a table"));
+        }
+        let indent_after = match value.get("indent_after") {
+            None => None,
+            Some(Value::String(chars)) => Some(chars.clone()),
+            Some(_) => return Err(invalid_data("invalid indent_after")),
+        };
+        let expand_tab = match value.get("expand_tab") {
+            None => None,
+            Some(Value::Boolean(enabled)) => Some(*enabled),
+            Some(_) => return Err(invalid_data("invalid expand_tab")),
+        };
+        let language_server = match value.get("language_server") {
+            None => None,
+            Some(Value::String(command)) => Some(command.clone()),
+            Some(_) => return Err(invalid_data("invalid language_server")),
+        };
+        Ok(Self {
+            indent_after,
+            expand_tab,
+            indent_width: read_indent_width(value)?,
+            language_server,
+            color_column: read_number(value, "color_column")?,
+        })
+    }
+}
+
+fn read_seconds(table: &Value, key: &str) -> io::Result<Option<Duration>> {
+    Ok(read_number(table, key)?
+        .filter(|&seconds| seconds > 0)
+        .map(|seconds| Duration::from_secs(seconds as u64)))
+}
+
+fn read_indent_width(table: &Value) -> io::Result<Option<usize>> {
+    match read_number(table, "indent_width")? {
+        Some(0) => Err(invalid_data("indent_width must be at least 1")),
+        width => Ok(width),
+    }
+}
+
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("pound"))
+}
+
+fn config_file() -> Option<PathBuf> {
+    Some(config_dir()?.join("pound.toml"))
+}
+
+fn read_bool(table: &Value, key: &str, default: bool) -> io::Result<bool> {
+    match table.get(key) {
+        None => Ok(default),
+        Some(Value::Boolean(value)) => Ok(*value),
+        Some(_) => Err(invalid_data(format!("invalid {}", key))),
+    }
+}
+
+fn read_number(table: &Value, key: &str) -> io::Result<Option<usize>> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::Integer(value)) if *value >= 0 => Ok(Some(*value as usize)),
+        Some(_) => Err(invalid_data(format!("invalid {}", key))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> String {
+        Config::parse(source).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn defaults_when_missing() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.tab_stop, Config::default().tab_stop);
+        assert!(config.swap && config.auto_indent && !config.modal);
+        assert!(config.file_types.is_empty() && config.keys.is_empty());
+    }
+
+    #[test]
+    fn reads_settings() {
+        let config = Config::parse(
+            r#"
+            tab_stop = 2
+            modal = true
+            swap = false
+            line_numbers = "relative"
+            backup_dir = "/tmp/pound-backups"
+            [autosave]
+            idle = 3
+            [filetype.python]
+            expand_tab = true
+            [keys]
+            "ctrl-alt-s" = "save"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.tab_stop, 2);
+        assert!(config.modal && !config.swap);
+        assert!(config.line_numbers == LineNumbers::Relative);
+        assert!(config.backup == Backup::In("/tmp/pound-backups".into()));
+        assert_eq!(config.autosave_idle, Some(Duration::from_secs(3)));
+        assert_eq!(config.file_types["python"].expand_tab, Some(true));
+        assert!(config.indent(Some("python")).spaces);
+        assert!(config.keys == [(keymap::parse_key("ctrl-alt-s").unwrap(), Action::Save)]);
+    }
+
+    #[test]
+    fn rejects_mistyped_values() {
+        assert_eq!(error("modal = 1"), "invalid modal");
+        assert_eq!(error("tab_stop = 0"), "tab_stop must be at least 1");
+        assert_eq!(error("tab_stop = -4"), "invalid tab_stop");
+        assert_eq!(
+            error("line_numbers = \"both\""),
+            "unknown line_numbers \"both\""
+        );
+        assert_eq!(error("autosave = 5"), "autosave must be a table");
+        assert_eq!(
+            error("[filetype.rust]\nexpand_tab = \"yes\""),
+            "filetype.rust: invalid expand_tab"
+        );
+        assert_eq!(
+            error("[keys]\n\"hyper-x\" = \"save\""),
+            "unknown key \"hyper-x\""
+        );
+        assert_eq!(error("[keys]\nx = \"fly\""), "unknown action \"fly\"");
+    }
+}
@@ -0,0 +1,193 @@
+use crossterm::style::Color;
+
+use crate::theme::Theme;
+use crate::{EditorRows, Output};
+
+#[derive(Clone, Copy)]
+pub(crate) struct Conflict {
+    start: usize,
+    base: Option<usize>,
+    middle: usize,
+    end: usize,
+}
+
+impl Conflict {
+    fn ours_end(self) -> usize {
+        self.base.unwrap_or(self.middle)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Conflicts {
+    regions: Vec<Conflict>,
+    changes: Option<u64>,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Part {
+    Marker,
+    Ours,
+    Base,
+    Theirs,
+}
+
+impl Part {
+    pub(crate) fn color(self, theme: &Theme) -> Color {
+        match self {
+            Part::Ours => theme.conflict_ours,
+            Part::Theirs => theme.conflict_theirs,
+            Part::Marker | Part::Base => theme.conflict_marker,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Resolution {
+    Ours,
+    Theirs,
+    Both,
+}
+
+fn is_marker(row: &str, c: char) -> bool {
+    let marker = c.to_string().repeat(7);
+    match row.strip_prefix(&marker) {
+        Some(rest) if c == '=' => rest.trim_end().is_empty(),
+        Some(rest) => rest.is_empty() || rest.starts_with(' '),
+        None => false,
+    }
+}
+
+impl EditorRows {
+    pub(crate) fn update_conflicts(&mut self) {
+        if self.loader.is_some() || self.conflicts.changes == Some(self.changes) {
+            return;
+        }
+        let mut regions = Vec::new();
+        let (mut start, mut base, mut middle) = (None, None, None);
+        for (y, row) in self.row_contents.iter().enumerate() {
+            let row = row.row_content.as_str();
+            if is_marker(row, '<') {
+                start = Some(y);
+                base = None;
+                middle = None;
+            } else if start.is_none() {
+                continue;
+            } else if middle.is_none() && is_marker(row, '|') {
+                base = Some(y);
+            } else if is_marker(row, '=') {
+                middle = Some(y);
+            } else if is_marker(row, '>') {
+                if let (Some(start), Some(middle)) = (start, middle) {
+                    regions.push(Conflict {
+                        start,
+                        base,
+                        middle,
+                        end: y,
+                    });
+                }
+                start = None;
+            }
+        }
+        self.conflicts = Conflicts {
+            regions,
+            changes: Some(self.changes),
+        };
+    }
+
+    fn conflict_at(&self, y: usize) -> Option<Conflict> {
+        let regions = &self.conflicts.regions;
+        let after = regions.partition_point(|conflict| conflict.start <= y);
+        after
+            .checked_sub(1)
+            .map(|i| regions[i])
+            .filter(|conflict| y <= conflict.end)
+    }
+
+    pub(crate) fn conflict_part(&self, y: usize) -> Option<Part> {
+        let conflict = self.conflict_at(y)?;
+        Some(
+            if y == conflict.start || y == conflict.middle || y == conflict.end {
+                Part::Marker
+            } else if y < conflict.ours_end() {
+                Part::Ours
+            } else if y < conflict.middle {
+                Part::Base
+            } else {
+                Part::Theirs
+            },
+        )
+    }
+}
+
+impl Output {
+    pub(crate) fn goto_conflict(&mut self, forward: bool) {
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.load_all();
+        editor_rows.update_conflicts();
+        let regions = &editor_rows.conflicts.regions;
+        if regions.is_empty() {
+            self.status_message.set_message("No merge conflicts".into());
+            return;
+        }
+        let y = self.buffer.cursor_controller.cursor_y;
+        let conflict = if forward {
+            regions
+                .iter()
+                .find(|conflict| conflict.start > y)
+                .unwrap_or(&regions[0])
+        } else {
+            regions
+                .iter()
+                .rev()
+                .find(|conflict| conflict.start < y)
+                .unwrap_or(&regions[regions.len() - 1])
+        };
+        let number = regions
+            .iter()
+            .position(|it| it.start == conflict.start)
+            .unwrap();
+        self.buffer.cursor_controller.cursor_y = conflict.start;
+        self.buffer.cursor_controller.cursor_x = 0;
+        self.status_message
+            .set_message(format!("Conflict {} of {}", number + 1, regions.len()));
+    }
+
+    pub(crate) fn resolve_conflict(&mut self, resolution: Resolution) {
+        if self.reject_edit() {
+            return;
+        }
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.load_all();
+        editor_rows.update_conflicts();
+        let conflict = match editor_rows.conflict_at(self.buffer.cursor_controller.cursor_y) {
+            Some(conflict) => conflict,
+            None => {
+                self.status_message
+                    .set_message("The cursor isn't in a merge conflict".into());
+                return;
+            }
+        };
+        let (ours, theirs) = match resolution {
+            Resolution::Ours => (true, false),
+            Resolution::Theirs => (false, true),
+            Resolution::Both => (true, true),
+        };
+        editor_rows.history.commit();
+        editor_rows.delete_rows(conflict.end, conflict.end);
+        if !theirs {
+            editor_rows.delete_rows(conflict.middle + 1, conflict.end - 1);
+        }
+        editor_rows.delete_rows(conflict.ours_end(), conflict.middle);
+        if !ours {
+            editor_rows.delete_rows(conflict.start + 1, conflict.ours_end() - 1);
+        }
+        editor_rows.delete_rows(conflict.start, conflict.start);
+        editor_rows.history.commit();
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        cursor_controller.cursor_y = conflict.start;
+        cursor_controller.cursor_x = 0;
+        cursor_controller.anchor = None;
+        cursor_controller.block = None;
+        self.buffer.dirty += 1;
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+use std::{fs, iter};
+
+use crate::json::Json;
+use crate::lsp::{self, Request};
+use crate::quickfix::{Location, Quickfix};
+use crate::Output;
+
+/// Lines and UTF-16 columns count from 0.
+fn target(location: &Json) -> Option<(PathBuf, usize, usize)> {
+    let uri = location
+        .get("targetUri")
+        .or_else(|| location.get("uri"))?
+        .as_str()?;
+    let start = location
+        .get("targetSelectionRange")
+        .or_else(|| location.get("range"))?
+        .get("start")?;
+    Some((
+        lsp::path(uri)?,
+        start.get("line")?.as_u64()? as usize,
+        start.get("character")?.as_u64()? as usize,
+    ))
+}
+
+impl Output {
+    pub(crate) fn goto_definition(&mut self) {
+        self.ask_locations(
+            "definitionProvider",
+            "textDocument/definition",
+            Vec::new(),
+            Request::Definition,
+        );
+    }
+
+    pub(crate) fn find_references(&mut self) {
+        let context = Json::object(vec![("includeDeclaration", true.into())]);
+        self.ask_locations(
+            "referencesProvider",
+            "textDocument/references",
+            vec![("context", context)],
+            Request::References,
+        );
+    }
+
+    fn ask_locations(
+        &mut self,
+        provider: &str,
+        method: &str,
+        extra: Vec<(&str, Json)>,
+        request: Request,
+    ) {
+        if self.server_provides(provider) {
+            self.request_at_cursor(method, extra, request);
+        }
+    }
+
+    pub(crate) fn receive_locations(
+        &mut self,
+        request: Request,
+        result: &Json,
+    ) -> crossterm::Result<()> {
+        let targets: Vec<(PathBuf, usize, usize)> = match result {
+            Json::Array(locations) => locations.iter().filter_map(target).collect(),
+            location => target(location).into_iter().collect(),
+        };
+        let locations: Vec<Location> = targets
+            .into_iter()
+            .map(|(file, line, character)| {
+                let text = self.line_text(&file, line);
+                let x = lsp::byte_index(&text, character);
+                Location {
+                    column: text[..x].chars().count() + 1,
+                    message: text.trim().into(),
+                    line: line + 1,
+                    file,
+                }
+            })
+            .collect();
+        let (title, none) = match request {
+            Request::References => ("references", "No references found"),
+            _ => ("definitions", "No definition found"),
+        };
+        match locations.len() {
+            0 => self.status_message.set_message(none.into()),
+            1 => {
+                let location = &locations[0];
+                let (file, line, column) = (location.file.clone(), location.line, location.column);
+                self.push_jump();
+                if self.visit(&file) {
+                    self.goto_position(line, column);
+                }
+            }
+            _ => {
+                self.quickfix = Quickfix::new(title.into(), locations);
+                self.quickfix_list()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn line_text(&mut self, file: &Path, y: usize) -> String {
+        let wanted = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        let buffer = iter::once(&mut self.buffer)
+            .chain(self.buffers.iter_mut())
+            .find(|buffer| {
+                buffer.editor_rows.filename.as_ref().map_or(false, |name| {
+                    fs::canonicalize(name).map_or(false, |name| name == wanted)
+                })
+            });
+        if let Some(buffer) = buffer {
+            let editor_rows = &mut buffer.editor_rows;
+            editor_rows.load_rows(y);
+            return if y < editor_rows.number_of_rows() {
+                editor_rows.get_row(y).into()
+            } else {
+                String::new()
+            };
+        }
+        fs::read(file)
+            .map(|bytes| {
+                String::from_utf8_lossy(&bytes)
+                    .lines()
+                    .nth(y)
+                    .unwrap_or("")
+                    .into()
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,124 @@
+use std::iter;
+use std::ops::Range;
+
+const MAX_EDITS: usize = 1000;
+
+/// One of the ranges of a hunk is empty for pure insertions and deletions.
+pub(crate) fn diff<A: PartialEq<B>, B>(old: &[A], new: &[B]) -> Vec<(Range<usize>, Range<usize>)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+    let common = common_pairs(old, new).unwrap_or_default();
+    let mut hunks = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (next_x, next_y) in common.into_iter().chain(iter::once((old.len(), new.len()))) {
+        if next_x > x || next_y > y {
+            hunks.push((prefix + x..prefix + next_x, prefix + y..prefix + next_y));
+        }
+        x = next_x + 1;
+        y = next_y + 1;
+    }
+    hunks
+}
+
+fn common_pairs<A: PartialEq<B>, B>(old: &[A], new: &[B]) -> Option<Vec<(usize, usize)>> {
+    let (old_len, new_len) = (old.len() as isize, new.len() as isize);
+    let offset = old_len + new_len + 1;
+    // The furthest x reached on each diagonal k = x - y, and a copy of it
+    // from before each round, for walking back along the path.
+    let mut furthest = vec![0; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+    for edits in 0..=old_len + new_len {
+        if edits as usize > MAX_EDITS {
+            return None;
+        }
+        trace.push(furthest[(offset - edits) as usize..=(offset + edits) as usize].to_vec());
+        for k in (-edits..=edits).step_by(2) {
+            let at = |k: isize| furthest[(offset + k) as usize];
+            let mut x = if k == -edits || (k != edits && at(k - 1) < at(k + 1)) {
+                at(k + 1)
+            } else {
+                at(k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < old_len && y < new_len && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[(offset + k) as usize] = x;
+            if x >= old_len && y >= new_len {
+                return Some(backtrack(&trace, old_len, new_len));
+            }
+        }
+    }
+    Some(Vec::new())
+}
+
+fn backtrack(trace: &[Vec<isize>], mut x: isize, mut y: isize) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (edits, furthest) in trace.iter().enumerate().rev() {
+        let edits = edits as isize;
+        let at = |k: isize| furthest[(k + edits) as usize];
+        let k = x - y;
+        let (previous_x, previous_y) = if edits == 0 {
+            (0, 0)
+        } else {
+            let previous_k = if k == -edits || (k != edits && at(k - 1) < at(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            (at(previous_k), at(previous_k) - previous_k)
+        };
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    pairs.reverse();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal() {
+        assert!(diff(&["a", "b"], &["a", "b"]).is_empty());
+        assert!(diff::<&str, &str>(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn insertion_and_deletion() {
+        assert_eq!(diff(&["a", "c"], &["a", "b", "c"]), vec![(1..1, 1..2)]);
+        assert_eq!(diff(&["a", "b", "c"], &["a", "c"]), vec![(1..2, 1..1)]);
+        assert_eq!(diff(&[] as &[&str], &["a"]), vec![(0..0, 0..1)]);
+    }
+
+    #[test]
+    fn several_hunks() {
+        let old = ["a", "b", "c", "d", "e", "f"];
+        let new = ["a", "x", "c", "d", "f", "g"];
+        assert_eq!(
+            diff(&old, &new),
+            vec![(1..2, 1..2), (4..5, 4..4), (6..6, 5..6)]
+        );
+    }
+
+    #[test]
+    fn too_many_edits_is_one_hunk() {
+        let old: Vec<usize> = (0..3000).collect();
+        let new: Vec<usize> = (0..3000).map(|i| i + 1_000_000).collect();
+        assert_eq!(diff(&old, &new), vec![(0..3000, 0..3000)]);
+    }
+}
@@ -0,0 +1,176 @@
+use std::io::{self, ErrorKind};
+
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+const UTF16_LE_BOM: &[u8] = &[0xff, 0xfe];
+const UTF16_BE_BOM: &[u8] = &[0xfe, 0xff];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8 bom",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+
+    pub(crate) fn encode(&self, text: &str) -> io::Result<Vec<u8>> {
+        let bytes = match self {
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            Encoding::Utf16Le => UTF16_LE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect(),
+            Encoding::Utf16Be => UTF16_BE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect(),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(u32::from(c)).map_err(|_| {
+                        io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!("{:?} can't be encoded as latin-1", c),
+                        )
+                    })
+                })
+                .collect::<io::Result<_>>()?,
+        };
+        Ok(bytes)
+    }
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> (String, Encoding) {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return (
+            String::from_utf8_lossy(rest).into_owned(),
+            Encoding::Utf8Bom,
+        );
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16_LE_BOM) {
+        return (decode_utf16(rest, u16::from_le_bytes), Encoding::Utf16Le);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16_BE_BOM) {
+        return (decode_utf16(rest, u16::from_be_bytes), Encoding::Utf16Be);
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.into(), Encoding::Utf8),
+        Err(_) => (
+            bytes.iter().map(|&byte| char::from(byte)).collect(),
+            Encoding::Latin1,
+        ),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub(crate) fn detect(text: &str) -> (Self, bool) {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        let line_ending = if crlf > lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        };
+        (line_ending, crlf > 0 && lf > 0)
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::Crlf => "crlf",
+        }
+    }
+
+    pub(crate) fn toggle(self) -> Self {
+        match self {
+            LineEnding::Lf => LineEnding::Crlf,
+            LineEnding::Crlf => LineEnding::Lf,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8], encoding: Encoding, text: &str) {
+        let (decoded, detected) = decode(bytes);
+        assert_eq!(decoded, text);
+        assert!(detected == encoding, "{}", detected.name());
+        assert_eq!(encoding.encode(&decoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn utf8() {
+        round_trip("héllo\n".as_bytes(), Encoding::Utf8, "héllo\n");
+        round_trip(b"\xef\xbb\xbfhi", Encoding::Utf8Bom, "hi");
+        round_trip(b"", Encoding::Utf8, "");
+    }
+
+    #[test]
+    fn utf16_boms() {
+        round_trip(b"\xff\xfeh\x00\xe9\x00", Encoding::Utf16Le, "hé");
+        round_trip(b"\xfe\xff\x00h\x00\xe9", Encoding::Utf16Be, "hé");
+        round_trip(b"\xff\xfe\x3d\xd8\x00\xde", Encoding::Utf16Le, "😀");
+    }
+
+    #[test]
+    fn invalid_utf8_is_latin1() {
+        round_trip(b"caf\xe9\n", Encoding::Latin1, "café\n");
+        assert!(Encoding::Latin1.encode("€").is_err());
+    }
+
+    #[test]
+    fn line_endings() {
+        let cases = [
+            ("a\nb\n", LineEnding::Lf, false),
+            ("a\r\nb\r\n", LineEnding::Crlf, false),
+            ("a\r\nb\r\nc\n", LineEnding::Crlf, true),
+            ("a\r\nb\nc\n", LineEnding::Lf, true),
+            ("no newline", LineEnding::Lf, false),
+            ("a\rb\n", LineEnding::Lf, false),
+        ];
+        for (text, line_ending, mixed) in cases {
+            assert!(
+                LineEnding::detect(text) == (line_ending, mixed),
+                "{:?}",
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::cmp;
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crossterm::style::Color;
+
+use crate::theme::Theme;
+use crate::{diff, EditorRows, Output};
+
+#[derive(Clone, Copy)]
+pub(crate) enum Sign {
+    Added,
+    Modified,
+    /// Rows were deleted below this one.
+    Deleted,
+    /// Rows were deleted above the first one.
+    DeletedAbove,
+}
+
+impl Sign {
+    pub(crate) fn symbol(self) -> char {
+        match self {
+            Sign::Added => '+',
+            Sign::Modified => '~',
+            Sign::Deleted => '_',
+            Sign::DeletedAbove => '‾',
+        }
+    }
+
+    pub(crate) fn color(self, theme: &Theme) -> Color {
+        match self {
+            Sign::Added => theme.git_added,
+            Sign::Modified => theme.git_modified,
+            Sign::Deleted | Sign::DeletedAbove => theme.git_deleted,
+        }
+    }
+}
+
+pub(crate) struct Gutter {
+    head: Vec<String>,
+    signs: Vec<Option<Sign>>,
+    hunks: Vec<usize>,
+    changes: Option<u64>,
+}
+
+impl Gutter {
+    pub(crate) fn load(file: &Path) -> Option<Self> {
+        let (dir, name) = location(file)?;
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .arg("show")
+            .arg(format!("HEAD:./{}", name))
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(Self {
+            head: String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(String::from)
+                .collect(),
+            signs: Vec::new(),
+            hunks: Vec::new(),
+            changes: None,
+        })
+    }
+
+    pub(crate) fn sign(&self, y: usize) -> Option<Sign> {
+        self.signs.get(y).copied().flatten()
+    }
+}
+
+impl EditorRows {
+    pub(crate) fn update_gutter(&mut self) {
+        let gutter = match &mut self.git {
+            Some(gutter) if self.loader.is_none() && gutter.changes != Some(self.changes) => gutter,
+            _ => return,
+        };
+        let rows: Vec<&str> = self
+            .row_contents
+            .iter()
+            .map(|row| row.row_content.as_str())
+            .collect();
+        gutter.signs = vec![None; rows.len()];
+        gutter.hunks.clear();
+        for (old, new) in diff::diff(&gutter.head, &rows) {
+            if new.is_empty() {
+                match new.start.checked_sub(1) {
+                    Some(y) => gutter.signs[y] = Some(Sign::Deleted),
+                    None if !rows.is_empty() => gutter.signs[0] = Some(Sign::DeletedAbove),
+                    None => continue,
+                }
+                gutter.hunks.push(new.start.saturating_sub(1));
+                continue;
+            }
+            let sign = if old.is_empty() {
+                Sign::Added
+            } else {
+                Sign::Modified
+            };
+            gutter.hunks.push(new.start);
+            for y in new {
+                gutter.signs[y] = Some(sign);
+            }
+        }
+        gutter.changes = Some(self.changes);
+    }
+}
+
+fn location(file: &Path) -> Option<(&Path, &str)> {
+    let dir = file
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    Some((dir, file.file_name()?.to_str()?))
+}
+
+pub(crate) fn branch(file: &Path) -> Option<String> {
+    let (dir, _) = location(file)?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().into())
+}
+
+fn blame(file: &Path, text: &str, line: usize) -> io::Result<String> {
+    let (dir, name) =
+        location(file).ok_or_else(|| io::Error::new(ErrorKind::Other, "no file name"))?;
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(&["blame", "--porcelain", "--contents", "-"])
+        .arg(format!("-L{},{}", line, line))
+        .arg("--")
+        .arg(name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(text.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        let error = error.lines().next().unwrap_or("git blame failed");
+        return Err(io::Error::new(ErrorKind::Other, error.to_string()));
+    }
+    let porcelain = String::from_utf8_lossy(&output.stdout);
+    let mut lines = porcelain.lines();
+    let hash = lines
+        .next()
+        .and_then(|line| line.split(' ').next())
+        .unwrap_or("");
+    if hash.bytes().all(|byte| byte == b'0') {
+        return Ok("Not committed yet".into());
+    }
+    let (mut author, mut time, mut zone, mut summary) = ("", 0, 0, "");
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("author", value)) => author = value,
+            Some(("author-time", value)) => time = value.parse().unwrap_or(0),
+            Some(("author-tz", value)) => zone = zone_offset(value),
+            Some(("summary", value)) => summary = value,
+            _ => {}
+        }
+    }
+    Ok(format!(
+        "{} {}, {}: {}",
+        &hash[..cmp::min(hash.len(), 8)],
+        author,
+        date(time + zone),
+        summary
+    ))
+}
+
+fn zone_offset(zone: &str) -> i64 {
+    let (sign, digits) = match zone.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, zone.trim_start_matches('+')),
+    };
+    let hhmm: i64 = digits.parse().unwrap_or(0);
+    sign * (hhmm / 100 * 3600 + hhmm % 100 * 60)
+}
+
+fn date(time: i64) -> String {
+    // Howard Hinnant's days-to-civil conversion.
+    let days = time.div_euclid(86400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+impl Output {
+    pub(crate) fn blame(&mut self) {
+        let file = match &self.buffer.editor_rows.filename {
+            Some(file) => file.clone(),
+            None => {
+                self.status_message
+                    .set_message("The buffer has no file to blame".into());
+                return;
+            }
+        };
+        let line = self.buffer.cursor_controller.cursor_y + 1;
+        if line > self.buffer.editor_rows.number_of_rows() {
+            return;
+        }
+        let text = self.buffer.editor_rows.text();
+        let message = match blame(&file, &text, line) {
+            Ok(blame) => blame,
+            Err(err) => format!("Can't blame: {}", err),
+        };
+        self.status_message.set_message(message);
+    }
+
+    pub(crate) fn goto_hunk(&mut self, forward: bool) {
+        self.buffer.editor_rows.update_gutter();
+        let hunks = match &self.buffer.editor_rows.git {
+            Some(gutter) => &gutter.hunks,
+            None => {
+                self.status_message
+                    .set_message("The file isn't tracked by git".into());
+                return;
+            }
+        };
+        let y = self.buffer.cursor_controller.cursor_y;
+        let hunk = if forward {
+            hunks.iter().find(|&&hunk| hunk > y)
+        } else {
+            hunks.iter().rev().find(|&&hunk| hunk < y)
+        };
+        match hunk {
+            Some(&hunk) => {
+                self.buffer.cursor_controller.cursor_y = hunk;
+                self.buffer.cursor_controller.cursor_x = 0;
+            }
+            None if forward => self
+                .status_message
+                .set_message("No more changes below".into()),
+            None => self
+                .status_message
+                .set_message("No more changes above".into()),
+        }
+    }
+}
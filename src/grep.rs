@@ -0,0 +1,329 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{cmp, fs, thread};
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+use regex::Regex;
+
+use crate::{Output, Reader};
+
+const MAX_MATCHES: usize = 1000;
+
+const REFRESH: Duration = Duration::from_millis(100);
+
+#[derive(Clone)]
+struct Match {
+    file: PathBuf,
+    line: usize,
+    text: String,
+}
+
+struct Search {
+    matches: Receiver<Match>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Search {
+    fn start(pattern: String) -> Self {
+        let (sender, matches) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        thread::spawn(move || match ripgrep(&pattern, &sender, &stopped) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                if let Ok(regex) = Regex::new(&pattern) {
+                    walk(Path::new("."), &regex, &sender, &stopped);
+                }
+            }
+            _ => {}
+        });
+        Self { matches, stop }
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn ripgrep(pattern: &str, sender: &Sender<Match>, stop: &AtomicBool) -> io::Result<()> {
+    let mut child = Command::new("rg")
+        .args(&["--null", "--line-number", "--no-heading", "--color=never"])
+        .arg("--regexp")
+        .arg(pattern)
+        .arg(".")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut sent = 0;
+    for line in stdout.lines() {
+        let line = line?;
+        let parsed = line.split_once('\0').and_then(|(file, rest)| {
+            let (line, text) = rest.split_once(':')?;
+            Some(Match {
+                file: file.strip_prefix("./").unwrap_or(file).into(),
+                line: line.parse().ok()?,
+                text: text.into(),
+            })
+        });
+        if let Some(found) = parsed {
+            sent += 1;
+            if stop.load(Ordering::Relaxed) || sender.send(found).is_err() || sent == MAX_MATCHES {
+                break;
+            }
+        }
+    }
+    child.kill().ok();
+    child.wait().map(|_| ())
+}
+
+fn walk(dir: &Path, regex: &Regex, sender: &Sender<Match>, stop: &AtomicBool) -> bool {
+    let mut entries: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(Result::ok).map(|it| it.path()).collect(),
+        Err(_) => return true,
+    };
+    entries.sort();
+    for path in entries {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        let hidden = path
+            .file_name()
+            .map_or(true, |name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            if !walk(&path, regex, sender, stop) {
+                return false;
+            }
+            continue;
+        }
+        let text = match fs::read_to_string(&path) {
+            Ok(text) if !text.contains('\0') => text,
+            _ => continue,
+        };
+        let file = path.strip_prefix(".").unwrap_or(&path);
+        for (y, line) in text.lines().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+            let found = Match {
+                file: file.into(),
+                line: y + 1,
+                text: line.into(),
+            };
+            if sender.send(found).is_err() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+struct Grep {
+    pattern: String,
+    search: Option<Search>,
+    matches: Vec<Match>,
+    done: bool,
+    selected: usize,
+    row_offset: usize,
+}
+
+impl Grep {
+    fn restart(&mut self) {
+        self.matches.clear();
+        self.selected = 0;
+        self.row_offset = 0;
+        self.done = self.pattern.is_empty();
+        self.search = if self.done {
+            None
+        } else {
+            Some(Search::start(self.pattern.clone()))
+        };
+    }
+
+    fn receive(&mut self) {
+        let search = match &self.search {
+            Some(search) => search,
+            None => return,
+        };
+        loop {
+            match search.matches.try_recv() {
+                Ok(found) if self.matches.len() < MAX_MATCHES => self.matches.push(found),
+                Ok(_) | Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    self.search = None;
+                    return;
+                }
+                Err(TryRecvError::Empty) => return,
+            }
+        }
+    }
+}
+
+impl Output {
+    pub(crate) fn live_grep(&mut self, pattern: &str) -> crossterm::Result<()> {
+        let mut grep = Grep {
+            pattern: pattern.into(),
+            search: None,
+            matches: Vec::new(),
+            done: true,
+            selected: 0,
+            row_offset: 0,
+        };
+        grep.restart();
+        let chosen = loop {
+            grep.receive();
+            self.draw_grep(&mut grep)?;
+            let key = match Reader.poll_event(REFRESH)? {
+                Some(Event::Key(key)) => key,
+                _ => continue,
+            };
+            let last = grep.matches.len().saturating_sub(1);
+            let page = self.win_size.1;
+            match key {
+                KeyEvent {
+                    code: KeyCode::Esc, ..
+                } => break None,
+                KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                } => {
+                    if let Some(found) = grep.matches.get(grep.selected) {
+                        break Some(found.clone());
+                    }
+                }
+                KeyEvent {
+                    code: KeyCode::Up, ..
+                } => grep.selected = grep.selected.saturating_sub(1),
+                KeyEvent {
+                    code: KeyCode::Down,
+                    ..
+                } => grep.selected = cmp::min(grep.selected + 1, last),
+                KeyEvent {
+                    code: KeyCode::PageUp,
+                    ..
+                } => grep.selected = grep.selected.saturating_sub(page),
+                KeyEvent {
+                    code: KeyCode::PageDown,
+                    ..
+                } => grep.selected = cmp::min(grep.selected + page, last),
+                KeyEvent {
+                    code: KeyCode::Backspace,
+                    ..
+                } => {
+                    if grep.pattern.pop().is_some() {
+                        grep.restart();
+                    }
+                }
+                KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                } => {
+                    grep.pattern.push(c);
+                    grep.restart();
+                }
+                _ => {}
+            }
+        };
+        self.status_message.set_message(String::new());
+        if let Some(found) = chosen {
+            if self.visit(&found.file) {
+                self.goto_position(found.line, 1);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn draw_list(
+        &mut self,
+        lines: &[String],
+        selected: usize,
+        row_offset: &mut usize,
+        info: &str,
+    ) -> crossterm::Result<()> {
+        let (width, height) = self.win_size;
+        *row_offset = cmp::min(*row_offset, selected);
+        if selected >= *row_offset + height {
+            *row_offset = selected - height + 1;
+        }
+        queue!(self.editor_contents, cursor::Hide, cursor::MoveTo(0, 0))?;
+        for i in 0..height {
+            if let Some(line) = lines.get(*row_offset + i) {
+                let line: String = line
+                    .chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .take(width)
+                    .collect();
+                if *row_offset + i == selected {
+                    self.editor_contents
+                        .push_str(&style::Attribute::Reverse.to_string());
+                }
+                self.editor_contents.push_str(&line);
+                self.editor_contents
+                    .push_str(&style::Attribute::Reset.to_string());
+            }
+            queue!(
+                self.editor_contents,
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+            self.editor_contents.push_str("\r\n");
+        }
+
+        let mut status: String = info.chars().take(width).collect();
+        status.push_str(&" ".repeat(width - status.chars().count()));
+        self.editor_contents
+            .set_foreground(self.theme.status_bar_foreground);
+        self.editor_contents
+            .set_background(self.theme.status_bar_background);
+        self.editor_contents.push_str(&status);
+        self.editor_contents
+            .push_str(&style::Attribute::Reset.to_string());
+        Ok(())
+    }
+
+    fn draw_grep(&mut self, grep: &mut Grep) -> crossterm::Result<()> {
+        let lines: Vec<String> = grep
+            .matches
+            .iter()
+            .map(|found| {
+                format!(
+                    "{}:{}: {}",
+                    found.file.display(),
+                    found.line,
+                    found.text.trim()
+                )
+            })
+            .collect();
+        let count = grep.matches.len();
+        let info = match (grep.done, count) {
+            (true, MAX_MATCHES) => format!("grep -- the first {} matches", count),
+            (true, _) => format!("grep -- {} matches", count),
+            (false, _) => format!("grep -- {} matches so far", count),
+        };
+        self.draw_list(&lines, grep.selected, &mut grep.row_offset, &info)?;
+
+        let (width, height) = self.win_size;
+        self.status_message
+            .set_message(format!("Grep: {} (Use ESC / Arrows / Enter)", grep.pattern));
+        self.draw_message_bar();
+        let column = "Grep: ".len() + grep.pattern.chars().count();
+        queue!(
+            self.editor_contents,
+            cursor::MoveTo(cmp::min(column, width) as u16, height as u16 + 1),
+            cursor::Show
+        )?;
+        self.editor_contents.flush()
+    }
+}
@@ -0,0 +1,22 @@
+use std::fmt::Write;
+
+use crate::Editor;
+
+impl Editor {
+    pub(crate) fn show_help(&mut self) {
+        let mut text = String::from("Keys\n\n");
+        for (action, keys) in self.keymap.bindings() {
+            let keys = if keys.is_empty() {
+                "(not bound)".into()
+            } else {
+                keys.join(", ")
+            };
+            writeln!(text, "  {:<24}{}", action, keys).unwrap();
+        }
+        text.push_str("\nCommands\n\n");
+        for names in self.commands.names() {
+            writeln!(text, "  :{}", names.join(", :")).unwrap();
+        }
+        self.output.open_scratch(&text);
+    }
+}
@@ -0,0 +1,302 @@
+use std::cmp;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+
+use crate::config::Config;
+use crate::theme::Theme;
+use crate::{EditorContents, Reader, StatusMessage, QUIT_TIMES};
+
+const BYTES_PER_ROW: usize = 16;
+const HEX_COLUMN: usize = 10;
+const ASCII_COLUMN: usize = HEX_COLUMN + BYTES_PER_ROW * 3 + 2;
+const BINARY_SNIFF_LEN: u64 = 8192;
+
+pub(crate) fn is_binary(path: &Path) -> bool {
+    let mut start = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(BINARY_SNIFF_LEN).read_to_end(&mut start))
+        .map_or(false, |_| start.contains(&0))
+}
+
+pub(crate) struct HexEditor {
+    bytes: Vec<u8>,
+    path: PathBuf,
+    cursor: usize,
+    low_nibble: bool,
+    in_ascii: bool,
+    row_offset: usize,
+    dirty: bool,
+    quit_times: u8,
+    win_size: (usize, usize),
+    editor_contents: EditorContents,
+    status_message: StatusMessage,
+    theme: Theme,
+}
+
+impl HexEditor {
+    pub(crate) fn open(path: &Path, config: &Config) -> io::Result<Self> {
+        let bytes = match fs::read(path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            result => result?,
+        };
+        let win_size = terminal::size()
+            .map(|(x, y)| (x as usize, y as usize - 2))
+            .unwrap();
+        Ok(Self {
+            bytes,
+            path: path.into(),
+            cursor: 0,
+            low_nibble: false,
+            in_ascii: false,
+            row_offset: 0,
+            dirty: false,
+            quit_times: QUIT_TIMES,
+            win_size,
+            editor_contents: EditorContents::new(),
+            status_message: StatusMessage::new(
+                "HELP: CTRL-S = Save | CTRL-Q = Quit | Tab = Hex/ASCII | Del = Delete byte".into(),
+            ),
+            theme: config.theme().unwrap_or_default(),
+        })
+    }
+
+    pub(crate) fn run(&mut self) -> crossterm::Result<()> {
+        let reader = Reader;
+        loop {
+            self.refresh_screen()?;
+            let key = reader.read_key()?;
+            if let KeyEvent {
+                code: KeyCode::Char('q'),
+                modifiers: KeyModifiers::CONTROL,
+            } = key
+            {
+                if self.dirty && self.quit_times > 0 {
+                    self.status_message.set_message(format!(
+                        "WARNING!!! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
+                        self.quit_times
+                    ));
+                    self.quit_times -= 1;
+                    continue;
+                }
+                return Ok(());
+            }
+            self.quit_times = QUIT_TIMES;
+            self.process_key(key);
+        }
+    }
+
+    fn process_key(&mut self, key: KeyEvent) {
+        let len = self.bytes.len();
+        match key {
+            KeyEvent {
+                code: KeyCode::Char('s'),
+                modifiers: KeyModifiers::CONTROL,
+            } => self.save(),
+            KeyEvent {
+                code: KeyCode::Tab, ..
+            } => {
+                self.in_ascii = !self.in_ascii;
+                self.low_nibble = false;
+            }
+            KeyEvent {
+                code: KeyCode::Left,
+                ..
+            } => self.move_to(self.cursor.saturating_sub(1)),
+            KeyEvent {
+                code: KeyCode::Right,
+                ..
+            } => self.move_to(self.cursor + 1),
+            KeyEvent {
+                code: KeyCode::Up, ..
+            } => self.move_to(self.cursor.saturating_sub(BYTES_PER_ROW)),
+            KeyEvent {
+                code: KeyCode::Down,
+                ..
+            } => self.move_to(self.cursor + BYTES_PER_ROW),
+            KeyEvent {
+                code: KeyCode::PageUp,
+                ..
+            } => self.move_to(self.cursor.saturating_sub(BYTES_PER_ROW * self.win_size.1)),
+            KeyEvent {
+                code: KeyCode::PageDown,
+                ..
+            } => self.move_to(self.cursor + BYTES_PER_ROW * self.win_size.1),
+            KeyEvent {
+                code: KeyCode::Home,
+                ..
+            } => self.move_to(self.cursor - self.cursor % BYTES_PER_ROW),
+            KeyEvent {
+                code: KeyCode::End, ..
+            } => self.move_to(self.cursor - self.cursor % BYTES_PER_ROW + BYTES_PER_ROW - 1),
+            KeyEvent {
+                code: KeyCode::Delete,
+                ..
+            } if self.cursor < len => {
+                self.bytes.remove(self.cursor);
+                self.low_nibble = false;
+                self.dirty = true;
+            }
+            KeyEvent {
+                code: KeyCode::Char(c),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            } => {
+                if self.in_ascii {
+                    if c.is_ascii() {
+                        self.write_byte(c as u8);
+                        self.move_to(self.cursor + 1);
+                    }
+                } else if let Some(digit) = c.to_digit(16) {
+                    let byte = self.bytes.get(self.cursor).copied().unwrap_or(0);
+                    let byte = if self.low_nibble {
+                        (byte & 0xf0) | digit as u8
+                    } else {
+                        (byte & 0x0f) | (digit as u8) << 4
+                    };
+                    self.write_byte(byte);
+                    if self.low_nibble {
+                        self.move_to(self.cursor + 1);
+                    } else {
+                        self.low_nibble = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn move_to(&mut self, cursor: usize) {
+        self.cursor = cmp::min(cursor, self.bytes.len());
+        self.low_nibble = false;
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        match self.bytes.get_mut(self.cursor) {
+            Some(current) => *current = byte,
+            None => self.bytes.push(byte),
+        }
+        self.dirty = true;
+    }
+
+    fn save(&mut self) {
+        match fs::write(&self.path, &self.bytes) {
+            Ok(()) => {
+                self.dirty = false;
+                self.status_message
+                    .set_message(format!("{} bytes written to disk", self.bytes.len()));
+            }
+            Err(err) => self
+                .status_message
+                .set_message(format!("Can't save! I/O error: {}", err)),
+        }
+    }
+
+    fn scroll(&mut self) {
+        let cursor_row = self.cursor / BYTES_PER_ROW;
+        self.row_offset = cmp::min(self.row_offset, cursor_row);
+        if cursor_row >= self.row_offset + self.win_size.1 {
+            self.row_offset = cursor_row - self.win_size.1 + 1;
+        }
+    }
+
+    fn refresh_screen(&mut self) -> crossterm::Result<()> {
+        self.scroll();
+        queue!(self.editor_contents, cursor::Hide, cursor::MoveTo(0, 0))?;
+        for i in 0..self.win_size.1 {
+            let start = (self.row_offset + i) * BYTES_PER_ROW;
+            if start < self.bytes.len() || start == self.cursor - self.cursor % BYTES_PER_ROW {
+                let end = cmp::min(start + BYTES_PER_ROW, self.bytes.len());
+                let line = hex_line(start, &self.bytes[start..end]);
+                self.editor_contents
+                    .push_str(&line[..cmp::min(line.len(), self.win_size.0)]);
+            } else {
+                self.editor_contents.push('~');
+            }
+            queue!(
+                self.editor_contents,
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+            self.editor_contents.push_str("\r\n");
+        }
+        self.draw_status_bar();
+        queue!(
+            self.editor_contents,
+            terminal::Clear(ClearType::UntilNewLine)
+        )?;
+        if let Some(msg) = self.status_message.message() {
+            self.editor_contents
+                .push_str(&msg[..cmp::min(self.win_size.0, msg.len())]);
+        }
+
+        let column = self.cursor % BYTES_PER_ROW;
+        let cursor_x = if self.in_ascii {
+            ASCII_COLUMN + column
+        } else {
+            HEX_COLUMN + column * 3 + column / 8 + self.low_nibble as usize
+        };
+        let cursor_y = self.cursor / BYTES_PER_ROW - self.row_offset;
+        queue!(
+            self.editor_contents,
+            cursor::MoveTo(cursor_x as u16, cursor_y as u16),
+            cursor::Show
+        )?;
+        self.editor_contents.flush()
+    }
+
+    fn draw_status_bar(&mut self) {
+        let info = format!(
+            "{} {} -- {} bytes [hex]",
+            self.path.display(),
+            if self.dirty { "(modified)" } else { "" },
+            self.bytes.len()
+        );
+        let line_info = format!(
+            "{} | 0x{:08x}",
+            if self.in_ascii { "ascii" } else { "hex" },
+            self.cursor
+        );
+        let width = self.win_size.0;
+        let mut status: String = info.chars().take(width).collect();
+        let status_len = status.chars().count();
+        if status_len + line_info.len() <= width {
+            status.push_str(&" ".repeat(width - status_len - line_info.len()));
+            status.push_str(&line_info);
+        }
+        self.editor_contents
+            .set_foreground(self.theme.status_bar_foreground);
+        self.editor_contents
+            .set_background(self.theme.status_bar_background);
+        self.editor_contents.push_str(&status);
+        self.editor_contents
+            .push_str(&style::Attribute::Reset.to_string());
+        self.editor_contents.push_str("\r\n");
+    }
+}
+
+fn hex_line(offset: usize, bytes: &[u8]) -> String {
+    let mut line = format!("{:08x}  ", offset);
+    for i in 0..BYTES_PER_ROW {
+        match bytes.get(i) {
+            Some(byte) => line.push_str(&format!("{:02x} ", byte)),
+            None => line.push_str("   "),
+        }
+        if i == BYTES_PER_ROW / 2 - 1 {
+            line.push(' ');
+        }
+    }
+    line.push('|');
+    line.extend(bytes.iter().map(|&byte| {
+        if byte.is_ascii_graphic() || byte == b' ' {
+            byte as char
+        } else {
+            '.'
+        }
+    }));
+    line.push('|');
+    line
+}
@@ -0,0 +1,246 @@
+use std::ops::Range;
+use std::{cmp, mem};
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::json::Json;
+use crate::lsp::{self, Request};
+use crate::Output;
+
+const INFO_HEIGHT: usize = 15;
+const INFO_WIDTH: usize = 80;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Hover,
+    Signature,
+}
+
+pub(crate) struct Info {
+    kind: Kind,
+    buffer: usize,
+    at: (usize, usize),
+    lines: Vec<String>,
+    marked: Option<(usize, Range<usize>)>,
+}
+
+fn hover_text(contents: &Json) -> String {
+    match contents {
+        Json::String(text) => text.clone(),
+        Json::Array(items) => items
+            .iter()
+            .map(hover_text)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        object => object
+            .get("value")
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .into(),
+    }
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for paragraph in text.lines() {
+        let paragraph = paragraph.trim_end();
+        if paragraph.starts_with("```")
+            || paragraph.is_empty() && lines.last().map_or(true, String::is_empty)
+        {
+            continue;
+        }
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(mem::take(&mut line));
+            } else if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    while lines.last().map_or(false, String::is_empty) {
+        lines.pop();
+    }
+    lines
+}
+
+fn parameter_range(label: &str, parameter: &Json) -> Option<Range<usize>> {
+    let (start, end) = match parameter.get("label")? {
+        Json::String(name) => {
+            // Past the name of the function, which may contain the name.
+            let from = label.find('(').unwrap_or(0);
+            let start = from + label[from..].find(name.as_str())?;
+            (start, start + name.len())
+        }
+        Json::Array(offsets) => {
+            let offset = |i: usize| {
+                let units = offsets.get(i)?.as_u64()? as usize;
+                Some(lsp::byte_index(label, units))
+            };
+            (offset(0)?, offset(1)?)
+        }
+        _ => return None,
+    };
+    let chars = |byte: usize| label[..byte].chars().count();
+    Some(chars(start)..chars(end))
+}
+
+impl Output {
+    pub(crate) fn hover(&mut self) {
+        if !self.server_provides("hoverProvider") {
+            return;
+        }
+        let cursor_controller = &self.buffer.cursor_controller;
+        let request = Request::Hover {
+            buffer: self.current_buffer,
+            at: (cursor_controller.cursor_x, cursor_controller.cursor_y),
+        };
+        self.request_at_cursor("textDocument/hover", Vec::new(), request);
+    }
+
+    pub(crate) fn receive_hover(&mut self, buffer: usize, at: (usize, usize), result: &Json) {
+        if !self.cursor_at(buffer, at) {
+            return;
+        }
+        let text = result.get("contents").map(hover_text).unwrap_or_default();
+        let width = cmp::min(INFO_WIDTH, self.win_size.0).saturating_sub(2);
+        let mut lines = wrap(&text, width);
+        if lines.is_empty() {
+            self.status_message
+                .set_message("Nothing to show for the symbol under the cursor".into());
+            return;
+        }
+        if lines.len() > INFO_HEIGHT {
+            lines.truncate(INFO_HEIGHT - 1);
+            lines.push("…".into());
+        }
+        self.info = Some(Info {
+            kind: Kind::Hover,
+            buffer,
+            at,
+            lines: lines.iter().map(|line| format!(" {} ", line)).collect(),
+            marked: None,
+        });
+    }
+
+    pub(crate) fn signature_typed(&mut self, c: char) {
+        if c == ')' {
+            if self.info.as_ref().map(|info| info.kind) == Some(Kind::Signature) {
+                self.info = None;
+            }
+            return;
+        }
+        let triggers = match self.server_triggers("signatureHelpProvider") {
+            Some(triggers) => triggers,
+            None => return,
+        };
+        let retriggers = self.server_retriggers("signatureHelpProvider");
+        let cursor_controller = &self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let showing = self
+            .info
+            .as_ref()
+            .filter(|info| info.kind == Kind::Signature)
+            .map(|info| info.at);
+        // A comma updates the signature of the call it is in, a parenthesis
+        // starts another one.
+        let at = match showing {
+            Some(at) if c != '(' && (triggers.contains(&c) || retriggers.contains(&c)) => at,
+            _ if triggers.contains(&c) => cursor,
+            _ => return,
+        };
+        let request = Request::SignatureHelp {
+            buffer: self.current_buffer,
+            at,
+        };
+        self.request_at_cursor("textDocument/signatureHelp", Vec::new(), request);
+    }
+
+    pub(crate) fn receive_signature(&mut self, buffer: usize, at: (usize, usize), result: &Json) {
+        let signatures = result
+            .get("signatures")
+            .and_then(Json::as_array)
+            .unwrap_or(&[]);
+        let active = result
+            .get("activeSignature")
+            .and_then(Json::as_u64)
+            .map_or(0, |active| active as usize);
+        let signature = match signatures.get(active).or_else(|| signatures.first()) {
+            Some(signature) if self.in_call(buffer, at) => signature,
+            _ => {
+                if self.info.as_ref().map(|info| info.kind) == Some(Kind::Signature) {
+                    self.info = None;
+                }
+                return;
+            }
+        };
+        let label = signature.get("label").and_then(Json::as_str).unwrap_or("");
+        let parameter = signature
+            .get("activeParameter")
+            .or_else(|| result.get("activeParameter"))
+            .and_then(Json::as_u64)
+            .and_then(|parameter| {
+                signature
+                    .get("parameters")
+                    .and_then(Json::as_array)?
+                    .get(parameter as usize)
+            });
+        let marked = parameter
+            .and_then(|parameter| parameter_range(label, parameter))
+            .map(|range| (0, range.start + 1..range.end + 1));
+        let mut line = format!(" {} ", label);
+        if signatures.len() > 1 {
+            line.push_str(&format!("({}/{}) ", active + 1, signatures.len()));
+        }
+        self.info = Some(Info {
+            kind: Kind::Signature,
+            buffer,
+            at,
+            lines: vec![line],
+            marked,
+        });
+    }
+
+    fn cursor_at(&self, buffer: usize, at: (usize, usize)) -> bool {
+        let cursor_controller = &self.buffer.cursor_controller;
+        buffer == self.current_buffer
+            && (cursor_controller.cursor_x, cursor_controller.cursor_y) == at
+    }
+
+    fn in_call(&self, buffer: usize, at: (usize, usize)) -> bool {
+        let cursor_controller = &self.buffer.cursor_controller;
+        buffer == self.current_buffer
+            && cursor_controller.cursor_y == at.1
+            && cursor_controller.cursor_x >= at.0
+    }
+
+    pub(crate) fn update_info(&mut self) {
+        let keep = match &self.info {
+            Some(info) if info.kind == Kind::Hover => self.cursor_at(info.buffer, info.at),
+            Some(info) => self.in_call(info.buffer, info.at),
+            None => return,
+        };
+        if !keep {
+            self.info = None;
+        }
+    }
+
+    pub(crate) fn info_key(&mut self, key: KeyEvent) -> bool {
+        if self.info.is_some() && key.code == KeyCode::Esc && key.modifiers.is_empty() {
+            self.info = None;
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn draw_info(&mut self) -> crossterm::Result<()> {
+        let info = match &self.info {
+            Some(info) => info,
+            None => return Ok(()),
+        };
+        let (lines, marked) = (info.lines.clone(), info.marked.clone());
+        self.draw_popup(&lines, marked, 1, true)
+    }
+}
@@ -0,0 +1,195 @@
+use std::cmp;
+
+use crate::config::Config;
+use crate::{EditorRows, Output};
+
+const DETECT_ROWS: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct Indent {
+    pub(crate) spaces: bool,
+    pub(crate) width: usize,
+}
+
+impl Indent {
+    pub(crate) fn unit(self) -> String {
+        if self.spaces {
+            " ".repeat(self.width)
+        } else {
+            "\t".into()
+        }
+    }
+
+    pub(crate) fn name(self) -> String {
+        if self.spaces {
+            format!("spaces:{}", self.width)
+        } else {
+            "tabs".into()
+        }
+    }
+}
+
+impl EditorRows {
+    pub(crate) fn set_indent(&mut self, config: &Config) {
+        self.indent = config.indent(self.file_type());
+        if config.detect_indent {
+            if let Some(indent) = self.detect_indent() {
+                self.indent = indent;
+            }
+        }
+    }
+
+    fn detect_indent(&self) -> Option<Indent> {
+        let (mut tabs, mut spaces) = (0, 0);
+        let mut steps = [0; 9];
+        let mut previous = 0;
+        for y in 0..cmp::min(self.number_of_rows(), DETECT_ROWS) {
+            let row = self.get_row(y);
+            if row.trim().is_empty() {
+                continue;
+            }
+            if row.starts_with('\t') {
+                tabs += 1;
+                previous = 0;
+                continue;
+            }
+            let width = row.len() - row.trim_start_matches(' ').len();
+            if width > 0 {
+                spaces += 1;
+            }
+            // Steps of one are mostly comment continuations like ` * `.
+            if let Some(step @ 2..=8) = width.checked_sub(previous) {
+                steps[step] += 1;
+            }
+            previous = width;
+        }
+        if tabs == 0 && spaces == 0 {
+            return None;
+        }
+        if tabs > spaces {
+            return Some(Indent {
+                spaces: false,
+                ..self.indent
+            });
+        }
+        let width = (2..steps.len())
+            .filter(|&step| steps[step] > 0)
+            .max_by_key(|&step| (steps[step], cmp::Reverse(step)))
+            .unwrap_or(self.indent.width);
+        Some(Indent {
+            spaces: true,
+            width,
+        })
+    }
+}
+
+fn leading_whitespace(text: &str) -> &str {
+    &text[..text.len() - text.trim_start().len()]
+}
+
+impl Output {
+    pub(crate) fn insert_tab(&mut self) {
+        let indent = self.buffer.editor_rows.indent;
+        if !indent.spaces {
+            self.insert_char('\t');
+            return;
+        }
+        let cursor_controller = &self.buffer.cursor_controller;
+        let editor_rows = &self.buffer.editor_rows;
+        let column = match editor_rows.row_contents.get(cursor_controller.cursor_y) {
+            Some(row) => row.render_x(cursor_controller.cursor_x),
+            None => 0,
+        };
+        self.insert_text(&" ".repeat(indent.width - column % indent.width));
+    }
+
+    pub(crate) fn retab(&mut self) {
+        if self.reject_edit() {
+            return;
+        }
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.load_all();
+        editor_rows.history.commit();
+        let (indent, tab_stop) = (editor_rows.indent, editor_rows.tab_stop);
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let mut changed = 0;
+        for y in 0..editor_rows.number_of_rows() {
+            let row = editor_rows.get_editor_row(y);
+            let content = &row.row_content;
+            let len = content.len() - content.trim_start_matches(&[' ', '\t'][..]).len();
+            let column = row.render_x(len);
+            let wanted = if indent.spaces {
+                " ".repeat(column)
+            } else {
+                "\t".repeat(column / tab_stop) + &" ".repeat(column % tab_stop)
+            };
+            if wanted == content[..len] {
+                continue;
+            }
+            editor_rows.replace_range(y, 0, len, &wanted);
+            changed += 1;
+            let shift = |x: &mut usize| {
+                *x = if *x >= len {
+                    *x - len + wanted.len()
+                } else {
+                    cmp::min(*x, wanted.len())
+                }
+            };
+            if cursor_controller.cursor_y == y {
+                shift(&mut cursor_controller.cursor_x);
+            }
+            if let Some((x, anchor_y)) = &mut cursor_controller.anchor {
+                if *anchor_y == y {
+                    shift(x);
+                }
+            }
+        }
+        editor_rows.history.commit();
+        if changed > 0 {
+            self.buffer.dirty += 1;
+        }
+        self.status_message
+            .set_message(format!("Reindented {} lines", changed));
+    }
+
+    pub(crate) fn split_indented(&mut self) {
+        let (x, y) = (
+            self.buffer.cursor_controller.cursor_x,
+            self.buffer.cursor_controller.cursor_y,
+        );
+        let indent_after = self
+            .config
+            .indent_after(self.buffer.editor_rows.file_type());
+        let row = self.buffer.editor_rows.get_row(y);
+        let (before, after) = row.split_at(x);
+        let indent = leading_whitespace(before).to_string();
+        let deeper = before
+            .trim_end()
+            .chars()
+            .next_back()
+            .map_or(false, |c| indent_after.contains(c));
+        let closes = deeper && after.trim_start().starts_with(&[')', ']', '}'][..]);
+        let carried = leading_whitespace(after).len();
+        let inner = if deeper {
+            indent.clone() + &self.buffer.editor_rows.indent.unit()
+        } else {
+            indent.clone()
+        };
+
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.split_row(x, y);
+        editor_rows.replace_range(y + 1, 0, carried, &inner);
+        if closes {
+            editor_rows.split_row(inner.len(), y + 1);
+            editor_rows.replace_range(y + 2, 0, 0, &indent);
+        }
+        // The old row drops the whitespace it now ends with.
+        let trailing = editor_rows.get_row(y).len() - editor_rows.get_row(y).trim_end().len();
+        if trailing > 0 {
+            let end = editor_rows.get_row(y).len();
+            editor_rows.replace_range(y, end - trailing, end, "");
+        }
+        self.buffer.cursor_controller.cursor_x = inner.len();
+        self.buffer.cursor_controller.cursor_y = y + 1;
+    }
+}
@@ -0,0 +1,28 @@
+use unicode_width::UnicodeWidthChar;
+
+use crate::Row;
+
+pub(crate) const LINE_END: char = '¬';
+
+impl Row {
+    pub(crate) fn invisibles(&self) -> Vec<Option<char>> {
+        let mut shown = Vec::with_capacity(self.render.len());
+        let mut render_x = 0;
+        for c in self.row_content.chars() {
+            match c {
+                '\t' => {
+                    let spaces = self.tab_stop - render_x % self.tab_stop;
+                    shown.push(Some('→'));
+                    shown.extend((1..spaces).map(|_| Some(' ')));
+                    render_x += spaces;
+                    continue;
+                }
+                ' ' => shown.push(Some('·')),
+                c if c.is_whitespace() && c.width() == Some(1) => shown.push(Some('␣')),
+                _ => shown.push(None),
+            }
+            render_x += c.width().unwrap_or(0);
+        }
+        shown
+    }
+}
@@ -0,0 +1,108 @@
+use std::cmp;
+
+use crate::Output;
+
+const MAX_JUMPS: usize = 100;
+
+#[derive(Default)]
+pub(crate) struct Jumps {
+    back: Vec<(usize, (usize, usize))>,
+}
+
+#[derive(Default)]
+pub(crate) struct JumpList {
+    positions: Vec<(usize, usize)>,
+    /// `positions.len()` when not going back through them.
+    current: usize,
+}
+
+impl Output {
+    pub(crate) fn push_jump(&mut self) {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let jump = (
+            self.current_buffer,
+            (cursor_controller.cursor_x, cursor_controller.cursor_y),
+        );
+        self.record_jump(jump.1);
+        let back = &mut self.jumps.back;
+        if back.last() != Some(&jump) {
+            if back.len() == MAX_JUMPS {
+                back.remove(0);
+            }
+            back.push(jump);
+        }
+    }
+
+    pub(crate) fn jump_back(&mut self) {
+        let (buffer, (x, y)) = match self.jumps.back.pop() {
+            Some(jump) => jump,
+            None => {
+                self.status_message
+                    .set_message("No earlier position".into());
+                return;
+            }
+        };
+        if buffer != self.current_buffer {
+            self.switch_buffer(buffer);
+        }
+        self.move_cursor_to((x, y));
+    }
+
+    pub(crate) fn record_jump(&mut self, from: (usize, usize)) {
+        let list = &mut self.buffer.jump_list;
+        list.positions.truncate(list.current);
+        if list.positions.last() != Some(&from) {
+            if list.positions.len() == MAX_JUMPS {
+                list.positions.remove(0);
+            }
+            list.positions.push(from);
+        }
+        list.current = list.positions.len();
+    }
+
+    pub(crate) fn step_jump_list(&mut self, older: bool) {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let list = &mut self.buffer.jump_list;
+        if older && list.current == 0 || !older && list.current + 1 >= list.positions.len() {
+            self.status_message.set_message(
+                if older {
+                    "At the oldest jump"
+                } else {
+                    "At the newest jump"
+                }
+                .into(),
+            );
+            return;
+        }
+        if older && list.current == list.positions.len() {
+            // To come forward to again.
+            list.positions.push(cursor);
+        }
+        if older {
+            list.current -= 1;
+        } else {
+            list.current += 1;
+        }
+        let position = list.positions[list.current];
+        self.move_cursor_to(position);
+    }
+
+    fn move_cursor_to(&mut self, (x, y): (usize, usize)) {
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.load_rows(y);
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        cursor_controller.cursor_y = cmp::min(y, editor_rows.number_of_rows());
+        let row = editor_rows
+            .row_contents
+            .get(cursor_controller.cursor_y)
+            .map_or("", |row| row.row_content.as_str());
+        let mut x = cmp::min(x, row.len());
+        while !row.is_char_boundary(x) {
+            x -= 1;
+        }
+        cursor_controller.cursor_x = x;
+        cursor_controller.anchor = None;
+        cursor_controller.block = None;
+    }
+}
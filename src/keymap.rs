@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    Quit,
+    Save,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    LineStart,
+    LineEnd,
+    SelectUp,
+    SelectDown,
+    SelectLeft,
+    SelectRight,
+    SelectLineStart,
+    SelectLineEnd,
+    SelectBlockUp,
+    SelectBlockDown,
+    SelectBlockLeft,
+    SelectBlockRight,
+    MoveWordLeft,
+    MoveWordRight,
+    PreviousParagraph,
+    NextParagraph,
+    BlockStart,
+    BlockEnd,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    ScrollLineTop,
+    ScrollLineCenter,
+    ScrollLineBottom,
+    GotoLine,
+    MatchingBracket,
+    Find,
+    FindNext,
+    FindPrevious,
+    Replace,
+    ReplaceAll,
+    Undo,
+    Redo,
+    Backspace,
+    Delete,
+    DeletePreviousWord,
+    DeleteNextWord,
+    Newline,
+    Dedent,
+    ToggleComment,
+    DuplicateLines,
+    MoveLinesUp,
+    MoveLinesDown,
+    JoinLines,
+    JoinLinesWithoutSpace,
+    LineNumbers,
+    SoftWrap,
+    Invisibles,
+    LineEnding,
+    ReadOnly,
+    Copy,
+    Cut,
+    Paste,
+    PastePrevious,
+    Open,
+    Grep,
+    Make,
+    NextError,
+    PreviousError,
+    GotoDefinition,
+    FindReferences,
+    JumpBack,
+    JumpOlder,
+    JumpNewer,
+    Hover,
+    RecordMacro,
+    PlayMacro,
+    RepeatEdit,
+    SetMark,
+    GotoMark,
+    SpellSuggest,
+    AddWord,
+    NextHunk,
+    PreviousHunk,
+    NextConflict,
+    PreviousConflict,
+    NextBuffer,
+    PreviousBuffer,
+    ListBuffers,
+    Window,
+    CommandLine,
+    Help,
+}
+
+const ACTIONS: [(&str, Action); 89] = [
+    ("quit", Action::Quit),
+    ("save", Action::Save),
+    ("move-up", Action::MoveUp),
+    ("move-down", Action::MoveDown),
+    ("move-left", Action::MoveLeft),
+    ("move-right", Action::MoveRight),
+    ("line-start", Action::LineStart),
+    ("line-end", Action::LineEnd),
+    ("select-up", Action::SelectUp),
+    ("select-down", Action::SelectDown),
+    ("select-left", Action::SelectLeft),
+    ("select-right", Action::SelectRight),
+    ("select-line-start", Action::SelectLineStart),
+    ("select-line-end", Action::SelectLineEnd),
+    ("select-block-up", Action::SelectBlockUp),
+    ("select-block-down", Action::SelectBlockDown),
+    ("select-block-left", Action::SelectBlockLeft),
+    ("select-block-right", Action::SelectBlockRight),
+    ("move-word-left", Action::MoveWordLeft),
+    ("move-word-right", Action::MoveWordRight),
+    ("previous-paragraph", Action::PreviousParagraph),
+    ("next-paragraph", Action::NextParagraph),
+    ("block-start", Action::BlockStart),
+    ("block-end", Action::BlockEnd),
+    ("page-up", Action::PageUp),
+    ("page-down", Action::PageDown),
+    ("half-page-up", Action::HalfPageUp),
+    ("half-page-down", Action::HalfPageDown),
+    ("scroll-line-top", Action::ScrollLineTop),
+    ("scroll-line-center", Action::ScrollLineCenter),
+    ("scroll-line-bottom", Action::ScrollLineBottom),
+    ("goto-line", Action::GotoLine),
+    ("matching-bracket", Action::MatchingBracket),
+    ("find", Action::Find),
+    ("find-next", Action::FindNext),
+    ("find-previous", Action::FindPrevious),
+    ("replace", Action::Replace),
+    ("replace-all", Action::ReplaceAll),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("backspace", Action::Backspace),
+    ("delete", Action::Delete),
+    ("delete-previous-word", Action::DeletePreviousWord),
+    ("delete-next-word", Action::DeleteNextWord),
+    ("newline", Action::Newline),
+    ("dedent", Action::Dedent),
+    ("toggle-comment", Action::ToggleComment),
+    ("duplicate-lines", Action::DuplicateLines),
+    ("move-lines-up", Action::MoveLinesUp),
+    ("move-lines-down", Action::MoveLinesDown),
+    ("join-lines", Action::JoinLines),
+    ("join-lines-without-space", Action::JoinLinesWithoutSpace),
+    ("line-numbers", Action::LineNumbers),
+    ("soft-wrap", Action::SoftWrap),
+    ("invisibles", Action::Invisibles),
+    ("line-ending", Action::LineEnding),
+    ("read-only", Action::ReadOnly),
+    ("copy", Action::Copy),
+    ("cut", Action::Cut),
+    ("paste", Action::Paste),
+    ("paste-previous", Action::PastePrevious),
+    ("open", Action::Open),
+    ("grep", Action::Grep),
+    ("make", Action::Make),
+    ("next-error", Action::NextError),
+    ("previous-error", Action::PreviousError),
+    ("goto-definition", Action::GotoDefinition),
+    ("find-references", Action::FindReferences),
+    ("jump-back", Action::JumpBack),
+    ("jump-older", Action::JumpOlder),
+    ("jump-newer", Action::JumpNewer),
+    ("hover", Action::Hover),
+    ("record-macro", Action::RecordMacro),
+    ("play-macro", Action::PlayMacro),
+    ("repeat-edit", Action::RepeatEdit),
+    ("set-mark", Action::SetMark),
+    ("goto-mark", Action::GotoMark),
+    ("spell-suggest", Action::SpellSuggest),
+    ("add-word", Action::AddWord),
+    ("next-hunk", Action::NextHunk),
+    ("previous-hunk", Action::PreviousHunk),
+    ("next-conflict", Action::NextConflict),
+    ("previous-conflict", Action::PreviousConflict),
+    ("next-buffer", Action::NextBuffer),
+    ("previous-buffer", Action::PreviousBuffer),
+    ("list-buffers", Action::ListBuffers),
+    ("window", Action::Window),
+    ("command-line", Action::CommandLine),
+    ("help", Action::Help),
+];
+
+const DEFAULT_BINDINGS: [(&str, Action); 87] = [
+    ("ctrl-q", Action::Quit),
+    ("ctrl-s", Action::Save),
+    ("up", Action::MoveUp),
+    ("down", Action::MoveDown),
+    ("left", Action::MoveLeft),
+    ("right", Action::MoveRight),
+    ("home", Action::LineStart),
+    ("end", Action::LineEnd),
+    ("shift-up", Action::SelectUp),
+    ("shift-down", Action::SelectDown),
+    ("shift-left", Action::SelectLeft),
+    ("shift-right", Action::SelectRight),
+    ("shift-home", Action::SelectLineStart),
+    ("shift-end", Action::SelectLineEnd),
+    ("alt-shift-up", Action::SelectBlockUp),
+    ("alt-shift-down", Action::SelectBlockDown),
+    ("alt-shift-left", Action::SelectBlockLeft),
+    ("alt-shift-right", Action::SelectBlockRight),
+    ("ctrl-left", Action::MoveWordLeft),
+    ("ctrl-right", Action::MoveWordRight),
+    ("ctrl-up", Action::PreviousParagraph),
+    ("ctrl-down", Action::NextParagraph),
+    ("alt-home", Action::BlockStart),
+    ("alt-end", Action::BlockEnd),
+    ("pageup", Action::PageUp),
+    ("pagedown", Action::PageDown),
+    ("ctrl-u", Action::HalfPageUp),
+    ("ctrl-d", Action::HalfPageDown),
+    ("ctrl-l", Action::ScrollLineCenter),
+    ("ctrl-g", Action::GotoLine),
+    ("alt-m", Action::MatchingBracket),
+    ("ctrl-f", Action::Find),
+    ("ctrl-n", Action::FindNext),
+    ("ctrl-p", Action::FindPrevious),
+    ("ctrl-r", Action::Replace),
+    // Alt-R toggles regex search in the prompt.
+    ("alt-%", Action::ReplaceAll),
+    ("ctrl-z", Action::Undo),
+    ("ctrl-y", Action::Redo),
+    ("backspace", Action::Backspace),
+    ("delete", Action::Delete),
+    // Most terminals send Ctrl-Backspace as Ctrl-H.
+    ("ctrl-backspace", Action::DeletePreviousWord),
+    ("ctrl-h", Action::DeletePreviousWord),
+    ("ctrl-delete", Action::DeleteNextWord),
+    ("enter", Action::Newline),
+    ("shift-backtab", Action::Dedent),
+    // Terminals send Ctrl-/ as Ctrl-7.
+    ("ctrl-/", Action::ToggleComment),
+    ("ctrl-7", Action::ToggleComment),
+    ("alt-d", Action::DuplicateLines),
+    ("alt-up", Action::MoveLinesUp),
+    ("alt-down", Action::MoveLinesDown),
+    ("alt-j", Action::JoinLines),
+    ("alt-l", Action::LineNumbers),
+    ("alt-w", Action::SoftWrap),
+    ("alt-i", Action::Invisibles),
+    ("alt-e", Action::LineEnding),
+    ("alt-o", Action::ReadOnly),
+    ("ctrl-c", Action::Copy),
+    ("ctrl-x", Action::Cut),
+    ("ctrl-v", Action::Paste),
+    ("alt-y", Action::PastePrevious),
+    ("ctrl-o", Action::Open),
+    ("alt-g", Action::Grep),
+    ("ctrl-b", Action::Make),
+    ("alt-.", Action::NextError),
+    ("alt-,", Action::PreviousError),
+    ("f12", Action::GotoDefinition),
+    ("shift-f12", Action::FindReferences),
+    ("ctrl-t", Action::JumpBack),
+    ("alt--", Action::JumpOlder),
+    ("alt-=", Action::JumpNewer),
+    ("alt-k", Action::Hover),
+    ("f3", Action::RecordMacro),
+    ("f4", Action::PlayMacro),
+    ("alt-z", Action::RepeatEdit),
+    ("f2", Action::SetMark),
+    ("shift-f2", Action::GotoMark),
+    ("alt-s", Action::SpellSuggest),
+    ("alt-a", Action::AddWord),
+    ("alt-n", Action::NextHunk),
+    ("alt-p", Action::PreviousHunk),
+    ("alt-c", Action::NextConflict),
+    ("alt-right", Action::NextBuffer),
+    ("alt-left", Action::PreviousBuffer),
+    ("alt-b", Action::ListBuffers),
+    ("ctrl-w", Action::Window),
+    ("alt-x", Action::CommandLine),
+    ("f1", Action::Help),
+];
+
+impl Action {
+    pub(crate) fn moves_cursor(self) -> bool {
+        matches!(
+            self,
+            Action::MoveUp
+                | Action::MoveDown
+                | Action::MoveLeft
+                | Action::MoveRight
+                | Action::LineStart
+                | Action::LineEnd
+                | Action::MoveWordLeft
+                | Action::MoveWordRight
+                | Action::PreviousParagraph
+                | Action::NextParagraph
+                | Action::BlockStart
+                | Action::BlockEnd
+                | Action::MatchingBracket
+                | Action::PageUp
+                | Action::PageDown
+                | Action::HalfPageUp
+                | Action::HalfPageDown
+        )
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|&(_, action)| action)
+    }
+}
+
+pub(crate) struct Keymap {
+    bindings: HashMap<KeyEvent, Action>,
+}
+
+impl Keymap {
+    pub(crate) fn new(overrides: &[(KeyEvent, Action)]) -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|&(key, action)| (parse_key(key).unwrap(), action))
+            .chain(overrides.iter().copied())
+            .collect();
+        Self { bindings }
+    }
+
+    pub(crate) fn get(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings.get(key).copied()
+    }
+
+    pub(crate) fn bindings(&self) -> Vec<(&'static str, Vec<String>)> {
+        ACTIONS
+            .iter()
+            .map(|&(name, action)| {
+                let mut keys: Vec<String> = self
+                    .bindings
+                    .iter()
+                    .filter(|&(_, &bound)| bound == action)
+                    .map(|(key, _)| key_name(key))
+                    .collect();
+                keys.sort();
+                (name, keys)
+            })
+            .collect()
+    }
+}
+
+fn key_name(key: &KeyEvent) -> String {
+    let mut name = String::new();
+    for (modifier, prefix) in [
+        (KeyModifiers::CONTROL, "ctrl-"),
+        (KeyModifiers::ALT, "alt-"),
+        (KeyModifiers::SHIFT, "shift-"),
+    ] {
+        if key.modifiers.contains(modifier) {
+            name.push_str(prefix);
+        }
+    }
+    match key.code {
+        KeyCode::Up => name.push_str("up"),
+        KeyCode::Down => name.push_str("down"),
+        KeyCode::Left => name.push_str("left"),
+        KeyCode::Right => name.push_str("right"),
+        KeyCode::Home => name.push_str("home"),
+        KeyCode::End => name.push_str("end"),
+        KeyCode::PageUp => name.push_str("pageup"),
+        KeyCode::PageDown => name.push_str("pagedown"),
+        KeyCode::Enter => name.push_str("enter"),
+        KeyCode::Tab => name.push_str("tab"),
+        KeyCode::BackTab => name.push_str("backtab"),
+        KeyCode::Backspace => name.push_str("backspace"),
+        KeyCode::Delete => name.push_str("delete"),
+        KeyCode::Insert => name.push_str("insert"),
+        KeyCode::Esc => name.push_str("esc"),
+        KeyCode::F(number) => name.push_str(&format!("f{}", number)),
+        KeyCode::Char(c) => name.push(c),
+        _ => name.push('?'),
+    }
+    name
+}
+
+pub(crate) fn parse_key(key: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = key;
+    loop {
+        let (modifier, tail) = match rest.split_once('-') {
+            Some((modifier, tail)) if !tail.is_empty() => (modifier, tail),
+            _ => break,
+        };
+        modifiers |= match modifier {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        rest = tail;
+    }
+    let code = match rest {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "esc" => KeyCode::Esc,
+        _ => match rest
+            .strip_prefix('f')
+            .and_then(|number| number.parse().ok())
+        {
+            Some(number @ 1..=12) => KeyCode::F(number),
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return None,
+                }
+            }
+        },
+    };
+    Some(KeyEvent { code, modifiers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_key_names() {
+        let key = |code, modifiers| Some(KeyEvent { code, modifiers });
+        assert_eq!(
+            parse_key("ctrl-s"),
+            key(KeyCode::Char('s'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parse_key("ctrl-alt-pagedown"),
+            key(KeyCode::PageDown, KeyModifiers::CONTROL | KeyModifiers::ALT)
+        );
+        assert_eq!(
+            parse_key("alt--"),
+            key(KeyCode::Char('-'), KeyModifiers::ALT)
+        );
+        assert_eq!(parse_key("f12"), key(KeyCode::F(12), KeyModifiers::NONE));
+        assert_eq!(parse_key("é"), key(KeyCode::Char('é'), KeyModifiers::NONE));
+        for bad in ["", "f13", "meta-x", "ctrl-", "ab"] {
+            assert_eq!(parse_key(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn key_names_round_trip() {
+        for name in ["ctrl-s", "alt-%", "shift-tab", "ctrl-alt-left", "f5", "esc"] {
+            assert_eq!(key_name(&parse_key(name).unwrap()), name);
+        }
+    }
+}
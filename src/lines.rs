@@ -0,0 +1,145 @@
+use std::cmp;
+
+use crate::Output;
+
+impl Output {
+    pub(crate) fn join_lines(&mut self, space: bool) {
+        let (first, last) = match self.selected_rows() {
+            Some((first, last)) if first == last => (first, last + 1),
+            Some(rows) => rows,
+            None => return,
+        };
+        self.clear_selection();
+        self.join_rows(first, last, space);
+    }
+
+    pub(crate) fn join_rows(&mut self, first: usize, last: usize, space: bool) {
+        let rows = self.buffer.editor_rows.number_of_rows();
+        let last = cmp::min(last, rows.saturating_sub(1));
+        if first >= last || self.reject_edit() {
+            return;
+        }
+        let editor_rows = &mut self.buffer.editor_rows;
+        let mut x = 0;
+        for _ in first..last {
+            x = editor_rows.get_row(first).len();
+            let mut separator = "";
+            if space {
+                let next = editor_rows.get_row(first + 1);
+                let indent = next.len() - next.trim_start().len();
+                let joined = &next[indent..];
+                let ends_blank = editor_rows
+                    .get_row(first)
+                    .chars()
+                    .next_back()
+                    .map_or(true, char::is_whitespace);
+                if !(ends_blank || joined.is_empty() || joined.starts_with(')')) {
+                    separator = " ";
+                }
+                if indent > 0 {
+                    editor_rows.replace_range(first + 1, 0, indent, "");
+                }
+            }
+            editor_rows.join_adjacent_rows(first + 1);
+            if !separator.is_empty() {
+                editor_rows.replace_range(first, x, x, separator);
+            }
+        }
+        self.buffer.cursor_controller.cursor_x = x;
+        self.buffer.cursor_controller.cursor_y = first;
+        self.buffer.dirty += 1;
+    }
+
+    pub(crate) fn trim_trailing_whitespace(&mut self) {
+        if self.reject_edit() {
+            return;
+        }
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.load_all();
+        editor_rows.history.commit();
+        let mut trimmed = false;
+        for y in 0..editor_rows.number_of_rows() {
+            let row = editor_rows.get_row(y);
+            let (end, len) = (row.trim_end().len(), row.len());
+            if end < len {
+                editor_rows.replace_range(y, end, len, "");
+                trimmed = true;
+            }
+        }
+        editor_rows.history.commit();
+        if !trimmed {
+            return;
+        }
+        let clamp = |(x, y): &mut (usize, usize)| {
+            if *y < editor_rows.number_of_rows() {
+                *x = cmp::min(*x, editor_rows.get_row(*y).len());
+            }
+        };
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let mut cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        clamp(&mut cursor);
+        cursor_controller.cursor_x = cursor.0;
+        if let Some(anchor) = &mut cursor_controller.anchor {
+            clamp(anchor);
+        }
+        self.buffer.dirty += 1;
+    }
+
+    pub(crate) fn duplicate_lines(&mut self) {
+        let (first, last) = match self.selected_rows() {
+            Some(rows) => rows,
+            None => return,
+        };
+        if self.reject_edit() {
+            return;
+        }
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.history.commit();
+        for y in first..=last {
+            let content = editor_rows.get_row(y).to_string();
+            editor_rows.insert_row(last + 1 + y - first, content);
+        }
+        editor_rows.history.commit();
+        self.shift_lines(last - first + 1, true);
+        self.buffer.dirty += 1;
+    }
+
+    pub(crate) fn move_lines(&mut self, down: bool) {
+        let (first, last) = match self.selected_rows() {
+            Some(rows) => rows,
+            None => return,
+        };
+        let rows = self.buffer.editor_rows.number_of_rows();
+        if (down && last + 1 >= rows) || (!down && first == 0) || self.reject_edit() {
+            return;
+        }
+        let (from, to) = if down {
+            (last + 1, first)
+        } else {
+            (first - 1, last)
+        };
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.history.commit();
+        let content = editor_rows.get_row(from).to_string();
+        editor_rows.delete_rows(from, from);
+        editor_rows.insert_row(to, content);
+        editor_rows.history.commit();
+        self.shift_lines(1, down);
+        self.buffer.dirty += 1;
+    }
+
+    fn shift_lines(&mut self, by: usize, down: bool) {
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let shift = |y: &mut usize| {
+            if down {
+                *y += by
+            } else {
+                *y -= by
+            }
+        };
+        shift(&mut cursor_controller.cursor_y);
+        if let Some((_, y)) = &mut cursor_controller.anchor {
+            shift(y);
+        }
+    }
+}
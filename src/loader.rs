@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::encoding::{Encoding, LineEnding};
+
+// Lines are only read in order, without an index to seek to one: what needs
+// the whole file, like search and saving, reads the rest of it first.
+const LAZY_THRESHOLD: u64 = 16 * 1024 * 1024;
+const BOMS: [&[u8]; 3] = [&[0xef, 0xbb, 0xbf], &[0xff, 0xfe], &[0xfe, 0xff]];
+
+pub(crate) struct Loader {
+    reader: BufReader<File>,
+    crlf: usize,
+    lf: usize,
+    encoding: Encoding,
+    non_ascii: bool,
+    lossy: bool,
+}
+
+impl Loader {
+    pub(crate) fn open(path: &Path) -> io::Result<Option<Self>> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() < LAZY_THRESHOLD {
+            return Ok(None);
+        }
+        let mut reader = BufReader::new(file);
+        let start = reader.fill_buf()?;
+        if BOMS.iter().any(|bom| start.starts_with(bom)) {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            reader,
+            crlf: 0,
+            lf: 0,
+            encoding: Encoding::Utf8,
+            non_ascii: false,
+            lossy: false,
+        }))
+    }
+
+    pub(crate) fn read_lines(&mut self, count: usize) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        while lines.len() < count {
+            line.clear();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            if line.ends_with(b"\n") {
+                line.pop();
+                if line.ends_with(b"\r") {
+                    line.pop();
+                    self.crlf += 1;
+                } else {
+                    self.lf += 1;
+                }
+            }
+            lines.push(self.decode(&line));
+        }
+        Ok(lines)
+    }
+
+    /// UTF-8 until a line is not, then latin-1 if everything before was ASCII.
+    fn decode(&mut self, line: &[u8]) -> String {
+        if self.encoding == Encoding::Latin1 {
+            return line.iter().map(|&byte| char::from(byte)).collect();
+        }
+        match std::str::from_utf8(line) {
+            Ok(text) => {
+                self.non_ascii |= !text.is_ascii();
+                text.into()
+            }
+            Err(_) if !self.non_ascii => {
+                self.encoding = Encoding::Latin1;
+                self.decode(line)
+            }
+            Err(_) => {
+                self.lossy = true;
+                String::from_utf8_lossy(line).into_owned()
+            }
+        }
+    }
+
+    pub(crate) fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub(crate) fn lossy(&self) -> bool {
+        self.lossy
+    }
+
+    pub(crate) fn line_ending(&self) -> (LineEnding, bool) {
+        let line_ending = if self.crlf > self.lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        };
+        (line_ending, self.crlf > 0 && self.lf > 0)
+    }
+}
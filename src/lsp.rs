@@ -0,0 +1,701 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use std::{env, fs, iter, mem, thread};
+
+use crossterm::style::Color;
+
+use crate::json::Json;
+use crate::theme::Theme;
+use crate::{Output, Row};
+
+const REQUEST_PATIENCE: Duration = Duration::from_secs(2);
+
+#[derive(PartialEq)]
+pub(crate) enum Request {
+    Completion {
+        buffer: usize,
+        start: (usize, usize),
+    },
+    Definition,
+    References,
+    Hover {
+        buffer: usize,
+        at: (usize, usize),
+    },
+    SignatureHelp {
+        buffer: usize,
+        at: (usize, usize),
+    },
+}
+
+pub(crate) struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Json>,
+    next_id: usize,
+    capabilities: Json,
+    /// Held back until the server answered `initialize`.
+    queued: Option<Vec<Json>>,
+}
+
+impl Client {
+    fn start(command: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "empty command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            while let Some(message) = read_message(&mut stdout) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut client = Self {
+            child,
+            stdin,
+            messages,
+            next_id: 1,
+            capabilities: Json::Null,
+            queued: Some(Vec::new()),
+        };
+        let params = Json::object(vec![
+            ("processId", (process::id() as usize).into()),
+            ("rootUri", uri(&env::current_dir()?).into()),
+            ("capabilities", capabilities()),
+        ]);
+        client.write(&Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", 0.into()),
+            ("method", "initialize".into()),
+            ("params", params),
+        ]))?;
+        Ok(client)
+    }
+
+    fn write(&mut self, message: &Json) -> io::Result<()> {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.stdin.flush()
+    }
+
+    fn send(&mut self, message: Json) {
+        match &mut self.queued {
+            Some(queued) => queued.push(message),
+            None => {
+                self.write(&message).ok();
+            }
+        }
+    }
+
+    pub(crate) fn notify(&mut self, method: &str, params: Json) {
+        self.send(Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ]));
+    }
+
+    fn request(&mut self, method: &str, params: Json) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", id.into()),
+            ("method", method.into()),
+            ("params", params),
+        ]));
+        id
+    }
+
+    fn characters(&self, provider: &str, key: &str) -> Option<Vec<char>> {
+        let provider = self.capabilities.get(provider)?;
+        if provider == &Json::Bool(false) || provider == &Json::Null {
+            return None;
+        }
+        Some(
+            provider
+                .get(key)
+                .and_then(Json::as_array)
+                .unwrap_or(&[])
+                .iter()
+                .filter_map(Json::as_str)
+                .flat_map(str::chars)
+                .collect(),
+        )
+    }
+
+    fn poll(&mut self) -> Vec<Json> {
+        let mut received = Vec::new();
+        while let Ok(message) = self.messages.try_recv() {
+            let id = message.get("id").cloned();
+            match (id, message.get("method")) {
+                (Some(id), None) if self.queued.is_some() && id.as_u64() == Some(0) => {
+                    if let Some(capabilities) = message
+                        .get("result")
+                        .and_then(|result| result.get("capabilities"))
+                    {
+                        self.capabilities = capabilities.clone();
+                    }
+                    self.write(&Json::object(vec![
+                        ("jsonrpc", "2.0".into()),
+                        ("method", "initialized".into()),
+                        ("params", Json::object(Vec::new())),
+                    ]))
+                    .ok();
+                    for queued in self.queued.take().unwrap() {
+                        self.write(&queued).ok();
+                    }
+                }
+                (Some(id), Some(method)) => {
+                    // Nothing is configured, so every setting asked for is
+                    // left at the server's default.
+                    let result = match method.as_str() {
+                        Some("workspace/configuration") => Json::Array(
+                            message
+                                .get("params")
+                                .and_then(|params| params.get("items"))
+                                .and_then(Json::as_array)
+                                .map_or(Vec::new(), |items| vec![Json::Null; items.len()]),
+                        ),
+                        _ => Json::Null,
+                    };
+                    self.write(&Json::object(vec![
+                        ("jsonrpc", "2.0".into()),
+                        ("id", id),
+                        ("result", result),
+                    ]))
+                    .ok();
+                }
+                _ => received.push(message),
+            }
+        }
+        received
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.queued = None;
+        self.notify("exit", Json::Null);
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object(vec![(
+        "textDocument",
+        Json::object(vec![
+            ("synchronization", Json::object(Vec::new())),
+            ("publishDiagnostics", Json::object(Vec::new())),
+            ("completion", Json::object(Vec::new())),
+            ("definition", Json::object(Vec::new())),
+            ("references", Json::object(Vec::new())),
+            ("hover", Json::object(Vec::new())),
+            ("signatureHelp", Json::object(Vec::new())),
+        ]),
+    )])
+}
+
+fn read_message(reader: &mut impl BufRead) -> Option<Json> {
+    loop {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().ok();
+                }
+            }
+        }
+        let mut body = vec![0; length?];
+        reader.read_exact(&mut body).ok()?;
+        if let Some(message) = Json::parse(&String::from_utf8_lossy(&body)) {
+            return Some(message);
+        }
+    }
+}
+
+pub(crate) fn uri(path: &Path) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'/' | b'-' | b'.' | b'_' | b'~' => uri.push(byte as char),
+            byte if byte.is_ascii_alphanumeric() => uri.push(byte as char),
+            byte => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+pub(crate) fn path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = encoded
+            .get(i + 1..i + 3)
+            .filter(|_| encoded[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Language servers count columns in UTF-16 code units.
+pub(crate) fn byte_index(text: &str, units: usize) -> usize {
+    let mut counted = 0;
+    for (at, c) in text.char_indices() {
+        if counted >= units {
+            return at;
+        }
+        counted += c.len_utf16();
+    }
+    text.len()
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub(crate) enum Severity {
+    Error = 1,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    pub(crate) fn symbol(self) -> char {
+        match self {
+            Severity::Error => 'E',
+            Severity::Warning => 'W',
+            Severity::Information => 'I',
+            Severity::Hint => 'H',
+        }
+    }
+
+    pub(crate) fn color(self, theme: &Theme) -> Color {
+        match self {
+            Severity::Error => theme.diagnostic_error,
+            Severity::Warning => theme.diagnostic_warning,
+            Severity::Information | Severity::Hint => theme.diagnostic_info,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "info",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+pub(crate) struct Diagnostic {
+    start: (usize, usize),
+    end: (usize, usize),
+    severity: Severity,
+    message: String,
+}
+
+impl Diagnostic {
+    fn parse(diagnostic: &Json) -> Option<Self> {
+        let range = diagnostic.get("range")?;
+        let position = |name: &str| {
+            let position = range.get(name)?;
+            Some((
+                position.get("line")?.as_u64()? as usize,
+                position.get("character")?.as_u64()? as usize,
+            ))
+        };
+        let severity = match diagnostic.get("severity").and_then(Json::as_u64) {
+            Some(2) => Severity::Warning,
+            Some(3) => Severity::Information,
+            Some(4) => Severity::Hint,
+            _ => Severity::Error,
+        };
+        Some(Self {
+            start: position("start")?,
+            end: position("end")?,
+            severity,
+            message: diagnostic.get("message")?.as_str()?.into(),
+        })
+    }
+
+    fn range_in(&self, y: usize, row: &str) -> Option<(usize, usize)> {
+        if y < self.start.0 || y > self.end.0 {
+            return None;
+        }
+        let from = if y == self.start.0 {
+            byte_index(row, self.start.1)
+        } else {
+            0
+        };
+        let mut to = if y == self.end.0 {
+            byte_index(row, self.end.1)
+        } else {
+            row.len()
+        };
+        if to <= from {
+            to = row[from..]
+                .chars()
+                .next()
+                .map_or(from, |c| from + c.len_utf8());
+        }
+        Some((from, to))
+    }
+}
+
+pub(crate) struct Document {
+    server: String,
+    uri: String,
+    version: usize,
+    pub(crate) changes: Option<u64>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+    pub(crate) fn severity(&self, y: usize) -> Option<Severity> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.start.0 == y)
+            .map(|diagnostic| diagnostic.severity)
+            .fold(None, |worst, severity| match worst {
+                Some(worst) if worst <= severity => Some(worst),
+                _ => Some(severity),
+            })
+    }
+
+    pub(crate) fn ranges(&self, y: usize, row: &Row) -> Vec<(usize, usize)> {
+        self.diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.range_in(y, &row.row_content))
+            .collect()
+    }
+
+    fn at(&self, (x, y): (usize, usize), row: &str) -> Option<usize> {
+        let on_line = |diagnostic: &&Diagnostic| diagnostic.range_in(y, row).is_some();
+        self.diagnostics
+            .iter()
+            .position(|diagnostic| {
+                on_line(&diagnostic)
+                    && diagnostic
+                        .range_in(y, row)
+                        .map_or(false, |(from, to)| from <= x && x < to)
+            })
+            .or_else(|| self.diagnostics.iter().position(|it| on_line(&it)))
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct LanguageServers {
+    clients: HashMap<String, Option<Client>>,
+    shown: Option<(usize, usize, usize)>,
+    pending: Vec<Sent>,
+}
+
+struct Sent {
+    server: String,
+    id: usize,
+    request: Request,
+    at: Instant,
+}
+
+impl LanguageServers {
+    pub(crate) fn awaits(&self, request: &Request) -> bool {
+        self.pending.iter().any(|sent| sent.request == *request)
+    }
+
+    pub(crate) fn expecting(&self) -> bool {
+        self.pending
+            .iter()
+            .any(|sent| sent.at.elapsed() < REQUEST_PATIENCE)
+    }
+}
+
+impl Output {
+    pub(crate) fn update_language_servers(&mut self) -> crossterm::Result<()> {
+        self.sync_documents();
+        let clients = &mut self.language_servers.clients;
+        let mut received = Vec::new();
+        for (command, client) in clients {
+            if let Some(client) = client {
+                for message in client.poll() {
+                    received.push((command.clone(), message));
+                }
+            }
+        }
+        for (server, message) in received {
+            let method = message.get("method").and_then(Json::as_str);
+            if method == Some("textDocument/publishDiagnostics") {
+                if let Some(params) = message.get("params") {
+                    self.publish_diagnostics(params);
+                }
+            }
+            let id = match message.get("id").and_then(Json::as_u64) {
+                Some(id) if method.is_none() => id as usize,
+                _ => continue,
+            };
+            let pending = &mut self.language_servers.pending;
+            if let Some(i) = pending
+                .iter()
+                .position(|sent| sent.server == server && sent.id == id)
+            {
+                let sent = pending.remove(i);
+                let result = message.get("result").unwrap_or(&Json::Null);
+                match sent.request {
+                    Request::Completion { buffer, start } => {
+                        self.receive_completion(buffer, start, result)
+                    }
+                    Request::Hover { buffer, at } => self.receive_hover(buffer, at, result),
+                    Request::SignatureHelp { buffer, at } => {
+                        self.receive_signature(buffer, at, result)
+                    }
+                    request => self.receive_locations(request, result)?,
+                }
+            }
+        }
+        self.show_diagnostic();
+        Ok(())
+    }
+
+    fn sync_documents(&mut self) {
+        let config = &self.config;
+        let clients = &mut self.language_servers.clients;
+        let status_message = &mut self.status_message;
+        for buffer in iter::once(&mut self.buffer).chain(self.buffers.iter_mut()) {
+            let editor_rows = &mut buffer.editor_rows;
+            let file_type = match editor_rows.file_type() {
+                Some(file_type) if editor_rows.loader.is_none() => file_type.to_string(),
+                _ => continue,
+            };
+            let (command, file) = match (config.language_server(&file_type), &editor_rows.filename)
+            {
+                (Some(command), Some(file)) => (command, file.clone()),
+                _ => continue,
+            };
+            let client = clients.entry(command.into()).or_insert_with(|| {
+                Client::start(command)
+                    .map_err(|err| {
+                        status_message.set_message(format!("Can't start {}: {}", command, err))
+                    })
+                    .ok()
+            });
+            let client = match client {
+                Some(client) => client,
+                None => continue,
+            };
+            let changes = editor_rows.changes;
+            match &mut editor_rows.document {
+                Some(document) if document.server == command => {
+                    if document.changes == Some(changes) {
+                        continue;
+                    }
+                    document.version += 1;
+                    document.changes = Some(changes);
+                    let (uri, version) = (document.uri.clone(), document.version);
+                    let text = editor_rows.text();
+                    client.notify(
+                        "textDocument/didChange",
+                        Json::object(vec![
+                            (
+                                "textDocument",
+                                Json::object(vec![
+                                    ("uri", uri.into()),
+                                    ("version", version.into()),
+                                ]),
+                            ),
+                            (
+                                "contentChanges",
+                                Json::Array(vec![Json::object(vec![("text", text.into())])]),
+                            ),
+                        ]),
+                    );
+                }
+                _ => {
+                    let uri = uri(&file);
+                    let text = editor_rows.text();
+                    client.notify(
+                        "textDocument/didOpen",
+                        Json::object(vec![(
+                            "textDocument",
+                            Json::object(vec![
+                                ("uri", uri.clone().into()),
+                                ("languageId", file_type.into()),
+                                ("version", 1.into()),
+                                ("text", text.into()),
+                            ]),
+                        )]),
+                    );
+                    editor_rows.document = Some(Document {
+                        server: command.into(),
+                        uri,
+                        version: 1,
+                        changes: Some(changes),
+                        diagnostics: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn current_server(&mut self) -> Option<(&mut Client, &Document)> {
+        let document = self.buffer.editor_rows.document.as_ref()?;
+        let client = self
+            .language_servers
+            .clients
+            .get_mut(&document.server)?
+            .as_mut()?;
+        Some((client, document))
+    }
+
+    pub(crate) fn server_triggers(&mut self, provider: &str) -> Option<Vec<char>> {
+        self.current_server()?
+            .0
+            .characters(provider, "triggerCharacters")
+    }
+
+    pub(crate) fn server_provides(&mut self, provider: &str) -> bool {
+        if self.server_triggers(provider).is_some() {
+            return true;
+        }
+        let message = if self.buffer.editor_rows.document.is_some() {
+            "The language server can't tell"
+        } else {
+            "No language server for this file"
+        };
+        self.status_message.set_message(message.into());
+        false
+    }
+
+    pub(crate) fn server_retriggers(&mut self, provider: &str) -> Vec<char> {
+        self.current_server()
+            .and_then(|(client, _)| client.characters(provider, "retriggerCharacters"))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn request_at_cursor(
+        &mut self,
+        method: &str,
+        extra: Vec<(&str, Json)>,
+        request: Request,
+    ) -> bool {
+        // The server has to know what was just typed.
+        self.sync_documents();
+        let (x, y) = (
+            self.buffer.cursor_controller.cursor_x,
+            self.buffer.cursor_controller.cursor_y,
+        );
+        let editor_rows = &self.buffer.editor_rows;
+        let character = if y < editor_rows.number_of_rows() {
+            editor_rows.get_row(y)[..x].encode_utf16().count()
+        } else {
+            0
+        };
+        let (client, document) = match self.current_server() {
+            Some(server) => server,
+            None => return false,
+        };
+        let mut params = vec![
+            (
+                "textDocument",
+                Json::object(vec![("uri", document.uri.as_str().into())]),
+            ),
+            (
+                "position",
+                Json::object(vec![("line", y.into()), ("character", character.into())]),
+            ),
+        ];
+        params.extend(extra);
+        let params = Json::object(params);
+        let server = document.server.clone();
+        let id = client.request(method, params);
+        // Only the answer to the latest request of a kind matters.
+        let pending = &mut self.language_servers.pending;
+        pending.retain(|sent| mem::discriminant(&sent.request) != mem::discriminant(&request));
+        pending.push(Sent {
+            server,
+            id,
+            request,
+            at: Instant::now(),
+        });
+        true
+    }
+
+    fn publish_diagnostics(&mut self, params: &Json) {
+        let uri = params.get("uri").and_then(Json::as_str);
+        let diagnostics: Vec<Diagnostic> = params
+            .get("diagnostics")
+            .and_then(Json::as_array)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(Diagnostic::parse)
+            .collect();
+        let document = iter::once(&mut self.buffer)
+            .chain(self.buffers.iter_mut())
+            .filter_map(|buffer| buffer.editor_rows.document.as_mut())
+            .find(|document| Some(document.uri.as_str()) == uri);
+        if let Some(document) = document {
+            document.diagnostics = diagnostics;
+            self.language_servers.shown = None;
+        }
+    }
+
+    fn show_diagnostic(&mut self) {
+        let (x, y) = (
+            self.buffer.cursor_controller.cursor_x,
+            self.buffer.cursor_controller.cursor_y,
+        );
+        let editor_rows = &self.buffer.editor_rows;
+        let found = match &editor_rows.document {
+            Some(document) if y < editor_rows.number_of_rows() => document
+                .at((x, y), editor_rows.get_row(y))
+                .map(|index| (index, &document.diagnostics[index])),
+            _ => None,
+        };
+        let (index, diagnostic) = match found {
+            Some(found) => found,
+            None => {
+                self.language_servers.shown = None;
+                return;
+            }
+        };
+        let shown = Some((self.current_buffer, y, index));
+        if self.language_servers.shown != shown {
+            self.language_servers.shown = shown;
+            let message = format!("{}: {}", diagnostic.severity.name(), diagnostic.message);
+            let message = message.lines().next().unwrap_or("").to_string();
+            self.status_message.set_message(message);
+        }
+    }
+}
@@ -1,22 +1,121 @@
 use std::cmp::Ordering;
 use std::io::{stdout, ErrorKind, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
-use std::{cmp, env, fs, io};
+use std::{cmp, env, fs, io, mem, process};
 
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::style::Color;
 use crossterm::terminal::ClearType;
 use crossterm::{cursor, event, execute, queue, style, terminal};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use backup::Backup;
+use browse::Browser;
+use cli::Invocation;
+use clipboard::Clipboard;
+use command::Commands;
+use compare::Comparison;
+use completion::Completion;
+use config::Config;
+use conflict::Conflicts;
+use encoding::{Encoding, LineEnding};
+use git::Gutter;
+use hex::HexEditor;
+use hover::Info;
+use indent::Indent;
+use jumps::{JumpList, Jumps};
+use keymap::{Action, Keymap};
+use loader::Loader;
+use lsp::{Document, LanguageServers};
+use marks::Marks;
+use modal::{Mode, Pending};
+use occurrences::Occurrences;
+use quickfix::Quickfix;
+use recorder::Repeat;
+use register::{Paste, Register, Registers};
+use reload::DiskState;
+use rope::Rope;
+use search::{Pattern, SearchDirection, SearchIndex};
+use spell::Spelling;
+use syntax::{HighlightType, RowState, SyntaxHighlight};
+use theme::Theme;
+use undo::{Edit, History};
+use view::Viewer;
+use welcome::{Choice, Welcome};
+use window::{Direction, Layout, Rect, Window};
+
+mod atomic;
+mod autosave;
+mod backup;
+mod block;
+mod bracket;
+mod browse;
+mod cli;
+mod clipboard;
+mod command;
+mod comment;
+mod compare;
+mod completion;
+mod config;
+mod conflict;
+mod definition;
+mod diff;
+mod encoding;
+mod git;
+mod grep;
+mod help;
+mod hex;
+mod hover;
+mod indent;
+mod invisibles;
+mod json;
+mod jumps;
+mod keymap;
+mod lines;
+mod loader;
+mod lsp;
+mod marks;
+mod modal;
+mod motion;
+mod occurrences;
+mod popup;
+mod quickfix;
+mod recorder;
+mod register;
+mod reload;
+mod rope;
+mod scrollbar;
+mod search;
+mod shell;
+mod spell;
+mod status;
+mod sudo;
+mod swap;
+mod syntax;
+mod theme;
+mod undo;
+mod view;
+mod welcome;
+mod window;
+mod word;
 
 const VERSION: &str = "0.0.1";
 const TAB_STOP: usize = 8;
 const QUIT_TIMES: u8 = 3;
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+const LOAD_BATCH: usize = 4096;
+const SCROLL_LINES: usize = 3;
 
 struct CleanUp;
 
 impl Drop for CleanUp {
     fn drop(&mut self) {
         terminal::disable_raw_mode().expect("Could not turn Raw Mode off.");
+        execute!(stdout(), event::DisableMouseCapture).expect("Error");
         Output::clear_screen().expect("Error");
     }
 }
@@ -26,37 +125,130 @@ struct Reader;
 impl Reader {
     fn read_key(&self) -> crossterm::Result<KeyEvent> {
         loop {
-            if event::poll(Duration::from_millis(500))? {
-                if let Event::Key(event) = event::read()? {
-                    return Ok(event);
-                }
+            if let Some(event) = self.poll_key()? {
+                return Ok(event);
             }
         }
     }
+
+    fn poll_key(&self) -> crossterm::Result<Option<KeyEvent>> {
+        match self.poll_event(Duration::from_millis(500))? {
+            Some(Event::Key(event)) => Ok(Some(event)),
+            _ => Ok(None),
+        }
+    }
 }
 
 struct Editor {
     reader: Reader,
     output: Output,
+    commands: Commands,
+    keymap: Keymap,
+    pending: Pending,
+    registers: Registers,
+    last_paste: Option<Paste>,
+    repeat: Repeat,
+    clipboard: Clipboard,
+    deferred: Option<Event>,
     quit_times: u8,
+    last_input: Instant,
+    last_autosave: Instant,
 }
 
 impl Editor {
-    fn new() -> Self {
+    fn new(config: io::Result<Config>, files: Vec<PathBuf>, read_only: bool) -> Self {
+        let output = Output::new(config, files, read_only);
         Self {
             reader: Reader,
-            output: Output::new(),
+            keymap: Keymap::new(&output.config.keys),
+            clipboard: Clipboard::new(output.config.clipboard),
+            output,
+            commands: Commands::new(),
+            pending: Pending::default(),
+            registers: Registers::default(),
+            last_paste: None,
+            repeat: Repeat::default(),
+            deferred: None,
             quit_times: QUIT_TIMES,
+            last_input: Instant::now(),
+            last_autosave: Instant::now(),
         }
     }
 
     fn process_keypress(&mut self) -> crossterm::Result<bool> {
-        match self.reader.read_key()? {
-            KeyEvent {
-                code: KeyCode::Char('q'),
-                modifiers: event::KeyModifiers::CONTROL,
-            } => {
-                if self.output.dirty > 0 && self.quit_times > 0 {
+        // Before autosaving, not to write over a change made on disk.
+        self.output.check_disk()?;
+        self.output.offer_recovery()?;
+        self.autosave();
+        self.update_swap();
+        let event = match self.deferred.take() {
+            Some(event) => Some(event),
+            None => {
+                // Not to keep a language server's answer waiting for a key.
+                let timeout = if self.output.language_servers.expecting() {
+                    Duration::from_millis(20)
+                } else {
+                    Duration::from_millis(500)
+                };
+                let timeout = self
+                    .output
+                    .occurrence_delay()
+                    .map_or(timeout, |delay| cmp::min(delay, timeout));
+                self.reader.poll_event(timeout)?
+            }
+        };
+        if matches!(event, Some(Event::Key(_) | Event::Mouse(_))) {
+            self.last_input = Instant::now();
+        }
+        let key = match event {
+            Some(Event::Key(key)) => key,
+            Some(Event::Mouse(event)) => {
+                self.output.process_mouse(event);
+                self.output.buffer.editor_rows.history.commit();
+                return Ok(true);
+            }
+            Some(Event::Resize(columns, rows)) => {
+                self.output.resize(columns, rows);
+                return Ok(true);
+            }
+            _ => return Ok(true),
+        };
+        if self.keymap.get(&key) != Some(Action::PastePrevious) {
+            self.last_paste = None;
+        }
+        if self.output.completion_key(key) || self.output.info_key(key) {
+            self.output.buffer.editor_rows.history.commit();
+            return Ok(true);
+        }
+        if let Some(keep_running) = self.process_modal_key(key)? {
+            self.output.buffer.editor_rows.history.commit();
+            self.quit_times = QUIT_TIMES;
+            return Ok(keep_running);
+        }
+        if let Some(text) = self.read_paste(key)? {
+            self.output.buffer.editor_rows.history.commit();
+            if !self.output.type_in_block(&text) {
+                self.output.delete_selection();
+                self.output.insert_text(&text);
+            }
+            self.output.buffer.editor_rows.history.commit();
+            self.quit_times = QUIT_TIMES;
+            return Ok(true);
+        }
+        let action = self.keymap.get(&key);
+        let extends_edit = match action {
+            Some(action) => matches!(action, Action::Backspace | Action::Delete),
+            None => matches!(
+                key,
+                KeyEvent {
+                    code: KeyCode::Char(..) | KeyCode::Tab,
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                }
+            ),
+        };
+        match action {
+            Some(Action::Quit) => {
+                if self.output.is_dirty() && self.quit_times > 0 {
                     self.output.status_message.set_message(format!(
                         "WARNING!!! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
                         self.quit_times
@@ -66,104 +258,386 @@ impl Editor {
                 }
                 return Ok(false);
             }
-            KeyEvent {
-                code:
-                    direction
-                    @
-                    (KeyCode::Up
-                    | KeyCode::Down
-                    | KeyCode::Left
-                    | KeyCode::Right
-                    | KeyCode::End
-                    | KeyCode::Home),
-                modifiers: event::KeyModifiers::NONE,
-            } => self.output.move_cursor(direction),
-            KeyEvent {
-                code: val @ (KeyCode::PageUp | KeyCode::PageDown),
-                modifiers: event::KeyModifiers::NONE,
-            } => {
-                if matches!(val, KeyCode::PageUp) {
-                    self.output.cursor_controller.cursor_y =
-                        self.output.cursor_controller.row_offset
-                } else {
-                    self.output.cursor_controller.cursor_y = cmp::min(
-                        self.output.win_size.1 + self.output.cursor_controller.row_offset - 1,
-                        self.output.editor_rows.number_of_rows(),
-                    );
-                }
-                (0..self.output.win_size.1).for_each(|_| {
-                    self.output.move_cursor(if matches!(val, KeyCode::PageUp) {
-                        KeyCode::Up
-                    } else {
-                        KeyCode::Down
-                    });
-                })
+            Some(action) => {
+                if !self.run_action(action)? {
+                    return Ok(false);
+                }
             }
-            KeyEvent {
-                code: KeyCode::Char('s'),
-                modifiers: event::KeyModifiers::CONTROL,
-            } => {
-                if matches!(self.output.editor_rows.filename, None) {
-                    let prompt = prompt!(&mut self.output, "Save as: {}").map(|it| it.into());
-                    if let None = prompt {
+            None => {
+                if let KeyEvent {
+                    code: code @ (KeyCode::Char(..) | KeyCode::Tab),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                } = key
+                {
+                    let ch = match code {
+                        KeyCode::Tab => '\t',
+                        KeyCode::Char(ch) => ch,
+                        _ => unreachable!(),
+                    };
+                    if !self.output.type_in_block(ch.encode_utf8(&mut [0; 4])) {
+                        if code == KeyCode::Tab && self.output.selection().is_some() {
+                            self.output.indent_lines(false);
+                        } else if code == KeyCode::Tab {
+                            self.output.insert_tab();
+                        } else {
+                            self.output.delete_selection();
+                            if !self.output.type_closing(ch) {
+                                self.output.insert_char(ch)
+                            }
+                            self.output.complete_typed(ch);
+                            self.output.signature_typed(ch);
+                        }
+                    }
+                }
+            }
+        }
+        if !extends_edit {
+            self.output.buffer.editor_rows.history.commit();
+        }
+        self.quit_times = QUIT_TIMES;
+        Ok(true)
+    }
+
+    /// Text keys already waiting behind `key` are read as one paste.
+    fn read_paste(&mut self, key: KeyEvent) -> crossterm::Result<Option<String>> {
+        let first = match pasted_char(key) {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let mapped = self
+            .keymap
+            .get(&key)
+            .map_or(false, |action| action != Action::Newline);
+        if mapped || !self.reader.ready()? {
+            return Ok(None);
+        }
+        let mut text = first.to_string();
+        while self.deferred.is_none() && self.reader.ready()? {
+            match self.reader.poll_event(Duration::ZERO)? {
+                Some(Event::Key(key)) => match pasted_char(key) {
+                    Some(c) => text.push(c),
+                    None => self.deferred = Some(Event::Key(key)),
+                },
+                Some(event) => self.deferred = Some(event),
+                None => break,
+            }
+        }
+        Ok(Some(text))
+    }
+
+    fn copy(&mut self, cut: bool) {
+        if cut && self.output.reject_edit() {
+            return;
+        }
+        let selection = self.output.selection();
+        if let Some(text) = self.output.block_text() {
+            if cut {
+                self.output.delete_selection();
+            }
+            return self.store_copy(text, false);
+        }
+        let editor_rows = &mut self.output.buffer.editor_rows;
+        let cursor_controller = &mut self.output.buffer.cursor_controller;
+        let (text, linewise) = match selection {
+            Some((start, end)) => {
+                let text = editor_rows.text_range(start, end);
+                if cut {
+                    editor_rows.delete_range(start, end);
+                    cursor_controller.cursor_x = start.0;
+                    cursor_controller.cursor_y = start.1;
+                }
+                (text, false)
+            }
+            None => {
+                let y = cursor_controller.cursor_y;
+                if y >= editor_rows.number_of_rows() {
+                    return;
+                }
+                let text = format!("{}\n", editor_rows.get_row(y));
+                if cut {
+                    editor_rows.delete_rows(y, y);
+                    cursor_controller.cursor_x = 0;
+                }
+                (text, true)
+            }
+        };
+        if cut {
+            self.output.buffer.dirty += 1;
+            self.output.clear_selection();
+        }
+        self.store_copy(text, linewise);
+    }
+
+    fn store_copy(&mut self, text: String, linewise: bool) {
+        if self.output.mode.map_or(false, Mode::is_visual) {
+            self.output.clear_selection();
+        }
+        self.registers.store(
+            None,
+            Register {
+                text: text.clone(),
+                linewise,
+            },
+        );
+        if let Err(err) = self.clipboard.copy(text, linewise) {
+            self.output
+                .status_message
+                .set_message(format!("Can't reach the clipboard: {}", err));
+        }
+    }
+
+    fn paste_clipboard(&mut self) {
+        if self.output.reject_edit() {
+            return;
+        }
+        let (text, linewise) = match self.clipboard.paste() {
+            Some(paste) => paste,
+            None => {
+                self.output
+                    .status_message
+                    .set_message("Clipboard is empty".into());
+                return;
+            }
+        };
+        self.output.delete_selection();
+        let at = self.cursor();
+        let newest = self
+            .registers
+            .get(None)
+            .map_or(false, |newest| newest.text == text);
+        let end = self.put(&Register { text, linewise }, at, if newest { 1 } else { 0 });
+        self.set_cursor(end);
+    }
+
+    fn run_action(&mut self, action: Action) -> crossterm::Result<bool> {
+        if action.moves_cursor() && !self.output.mode.map_or(false, Mode::is_visual) {
+            self.output.clear_selection();
+        }
+        match action {
+            Action::Quit => return Ok(false),
+            Action::Save => self.output.save()?,
+            Action::MoveUp => self.output.move_cursor(KeyCode::Up),
+            Action::MoveDown => self.output.move_cursor(KeyCode::Down),
+            Action::MoveLeft => self.output.move_cursor(KeyCode::Left),
+            Action::MoveRight => self.output.move_cursor(KeyCode::Right),
+            Action::LineStart => self.output.move_cursor(KeyCode::Home),
+            Action::LineEnd => self.output.move_cursor(KeyCode::End),
+            Action::MoveWordLeft => self.output.move_word(false),
+            Action::MoveWordRight => self.output.move_word(true),
+            Action::PreviousParagraph => self.output.jump(motion::previous_paragraph),
+            Action::NextParagraph => self.output.jump(motion::next_paragraph),
+            Action::BlockStart => self.output.jump(motion::block_start),
+            Action::BlockEnd => self.output.jump(motion::block_end),
+            Action::SelectUp => self.output.select(KeyCode::Up),
+            Action::SelectDown => self.output.select(KeyCode::Down),
+            Action::SelectLeft => self.output.select(KeyCode::Left),
+            Action::SelectRight => self.output.select(KeyCode::Right),
+            Action::SelectLineStart => self.output.select(KeyCode::Home),
+            Action::SelectLineEnd => self.output.select(KeyCode::End),
+            Action::SelectBlockUp => self.output.select_block(KeyCode::Up),
+            Action::SelectBlockDown => self.output.select_block(KeyCode::Down),
+            Action::SelectBlockLeft => self.output.select_block(KeyCode::Left),
+            Action::SelectBlockRight => self.output.select_block(KeyCode::Right),
+            Action::PageUp | Action::PageDown => {
+                let rows = self.output.buffer.cursor_controller.screen_rows;
+                self.output
+                    .scroll_page(cmp::max(rows, 1), action == Action::PageDown)
+            }
+            Action::HalfPageUp | Action::HalfPageDown => {
+                let rows = self.output.buffer.cursor_controller.screen_rows / 2;
+                self.output
+                    .scroll_page(cmp::max(rows, 1), action == Action::HalfPageDown)
+            }
+            Action::ScrollLineTop => self.output.scroll_cursor_to('t'),
+            Action::ScrollLineCenter => self.output.scroll_cursor_to('z'),
+            Action::ScrollLineBottom => self.output.scroll_cursor_to('b'),
+            Action::MatchingBracket => self.output.jump_to_bracket(),
+            Action::GotoLine => {
+                if let Some(line) = prompt!(&mut self.output, "Go to line: {}") {
+                    if !self.output.jump_to_line(line.trim()) {
                         self.output
                             .status_message
-                            .set_message("Save aborted".into());
-                        return Ok(true);
+                            .set_message(format!("Not a line number: {}", line));
                     }
-                    self.output.editor_rows.filename = prompt;
                 }
-                self.output.editor_rows.save().map(|len| {
-                    self.output
-                        .status_message
-                        .set_message(format!("{} bytes written to disk", len));
-                    self.output.dirty = 0;
-                })?;
             }
-            KeyEvent {
-                code: key @ (KeyCode::Backspace | KeyCode::Delete),
-                modifiers: event::KeyModifiers::NONE,
-            } => {
-                if matches!(key, KeyCode::Delete) {
-                    self.output.move_cursor(KeyCode::Right)
+            Action::Find => self.output.find()?,
+            Action::FindNext => self.output.find_again(SearchDirection::Forward),
+            Action::FindPrevious => self.output.find_again(SearchDirection::Backward),
+            Action::Replace => self.output.replace()?,
+            Action::ReplaceAll => self.output.replace_all()?,
+            Action::Undo => self.output.undo(),
+            Action::Redo => self.output.redo(),
+            Action::Backspace | Action::Delete
+                if self.output.delete_in_block(action == Action::Delete)
+                    || self.output.delete_selection() => {}
+            Action::Backspace if self.output.delete_pair() => {}
+            Action::Backspace => self.output.delete_char(),
+            Action::Delete => {
+                if !self
+                    .output
+                    .buffer
+                    .cursor_controller
+                    .is_at_end(&self.output.buffer.editor_rows)
+                {
+                    self.output.move_cursor(KeyCode::Right);
+                    self.output.delete_char()
                 }
-                self.output.delete_char()
             }
-            KeyEvent {
-                code: KeyCode::Enter,
-                modifiers: KeyModifiers::NONE,
-            } => self.output.insert_newline(),
-            KeyEvent {
-                code: code @ (KeyCode::Char(..) | KeyCode::Tab),
-                modifiers: event::KeyModifiers::NONE | event::KeyModifiers::SHIFT,
-            } => self.output.insert_char(match code {
-                KeyCode::Tab => '\t',
-                KeyCode::Char(ch) => ch,
-                _ => unreachable!(),
-            }),
-            _ => {}
+            Action::DeletePreviousWord | Action::DeleteNextWord
+                if self.output.delete_selection() => {}
+            Action::DeletePreviousWord => self.output.delete_word(false),
+            Action::DeleteNextWord => self.output.delete_word(true),
+            Action::Newline => {
+                self.output.delete_selection();
+                self.output.insert_newline()
+            }
+            Action::Dedent => self.output.indent_lines(true),
+            Action::ToggleComment => self.output.toggle_comment(),
+            Action::DuplicateLines => self.output.duplicate_lines(),
+            Action::MoveLinesUp => self.output.move_lines(false),
+            Action::MoveLinesDown => self.output.move_lines(true),
+            Action::JoinLines => self.output.join_lines(true),
+            Action::JoinLinesWithoutSpace => self.output.join_lines(false),
+            Action::LineNumbers => {
+                let line_numbers = &mut self.output.buffer.cursor_controller.line_numbers;
+                *line_numbers = line_numbers.next();
+            }
+            Action::SoftWrap => {
+                self.output.buffer.editor_rows.soft_wrap = !self.output.buffer.editor_rows.soft_wrap
+            }
+            Action::Invisibles => {
+                let editor_rows = &mut self.output.buffer.editor_rows;
+                editor_rows.show_invisibles = !editor_rows.show_invisibles
+            }
+            Action::LineEnding => self.output.toggle_line_ending(),
+            Action::Copy => self.copy(false),
+            Action::Cut => self.copy(true),
+            Action::Paste => self.paste_clipboard(),
+            Action::PastePrevious => self.paste_previous(),
+            Action::ReadOnly => {
+                let editor_rows = &mut self.output.buffer.editor_rows;
+                editor_rows.read_only = !editor_rows.read_only;
+                self.output.status_message.set_message(
+                    if editor_rows.read_only {
+                        "Buffer is now read-only"
+                    } else {
+                        "Buffer is now writable"
+                    }
+                    .into(),
+                );
+            }
+            Action::Open => {
+                if let Some(file) = prompt!(&mut self.output, "Open: {}") {
+                    self.output.open_buffer(file.into())
+                }
+            }
+            Action::Grep => self.output.live_grep("")?,
+            Action::Make => self.output.make("")?,
+            Action::NextError => self.output.step_error(true),
+            Action::PreviousError => self.output.step_error(false),
+            Action::GotoDefinition => self.output.goto_definition(),
+            Action::FindReferences => self.output.find_references(),
+            Action::JumpBack => self.output.jump_back(),
+            Action::Hover => self.output.hover(),
+            Action::RecordMacro => self.record_macro()?,
+            Action::PlayMacro => self.ask_macro()?,
+            Action::RepeatEdit => self.repeat_edit(1),
+            Action::SetMark => self.ask_mark(true)?,
+            Action::GotoMark => self.ask_mark(false)?,
+            Action::JumpOlder => self.output.step_jump_list(true),
+            Action::JumpNewer => self.output.step_jump_list(false),
+            Action::SpellSuggest => self.output.suggest_spelling(),
+            Action::AddWord => self.output.add_word(),
+            Action::NextHunk => self.output.goto_hunk(true),
+            Action::PreviousHunk => self.output.goto_hunk(false),
+            Action::NextConflict => self.output.goto_conflict(true),
+            Action::PreviousConflict => self.output.goto_conflict(false),
+            Action::NextBuffer => self.output.cycle_buffer(1),
+            Action::PreviousBuffer => self.output.cycle_buffer(self.output.buffers.len()),
+            Action::ListBuffers => self.output.list_buffers(),
+            Action::Window => match self.reader.read_key()?.code {
+                KeyCode::Char('s') => self.output.split_window(false),
+                KeyCode::Char('v') => self.output.split_window(true),
+                KeyCode::Char('c' | 'q') => self.output.close_window(),
+                KeyCode::Char('w') => self.output.cycle_window(),
+                KeyCode::Char('h') | KeyCode::Left => self.output.move_to_window(Direction::Left),
+                KeyCode::Char('l') | KeyCode::Right => self.output.move_to_window(Direction::Right),
+                KeyCode::Char('k') | KeyCode::Up => self.output.move_to_window(Direction::Up),
+                KeyCode::Char('j') | KeyCode::Down => self.output.move_to_window(Direction::Down),
+                _ => {}
+            },
+            Action::CommandLine => return self.run_command(),
+            Action::Help => self.show_help(),
         }
-        self.quit_times = QUIT_TIMES;
         Ok(true)
     }
 
+    fn run_command(&mut self) -> crossterm::Result<bool> {
+        let line = match prompt!(&mut self.output, ":{}") {
+            Some(line) => line,
+            None => return Ok(true),
+        };
+        let line = line.trim();
+        if self.output.jump_to_line(line) {
+            return Ok(true);
+        }
+        let (name, args) = match line.strip_prefix('!') {
+            Some(command) => ("!", command.trim()),
+            None => line
+                .split_once(' ')
+                .map_or((line, ""), |(name, args)| (name, args.trim())),
+        };
+        match self.commands.find(name) {
+            Some(run) => run(self, args),
+            None => {
+                self.output
+                    .status_message
+                    .set_message(format!("Unknown command: {}", name));
+                Ok(true)
+            }
+        }
+    }
+
     fn run(&mut self) -> crossterm::Result<bool> {
         self.output.refresh_screen()?;
-        self.process_keypress()
+        let keep_running = self.process_keypress()?;
+        self.track_edit();
+        Ok(keep_running)
     }
 }
 
 struct EditorContents {
     content: String,
+    truecolor: bool,
 }
 
 impl EditorContents {
     fn new() -> Self {
         Self {
             content: String::new(),
+            truecolor: env::var("COLORTERM")
+                .map_or(false, |value| value == "truecolor" || value == "24bit"),
+        }
+    }
+
+    fn convert_color(&self, color: Color) -> Color {
+        match color {
+            Color::Rgb { r, g, b } if !self.truecolor => Color::AnsiValue(rgb_to_ansi(r, g, b)),
+            color => color,
         }
     }
 
+    fn set_foreground(&mut self, color: Color) {
+        let color = self.convert_color(color);
+        queue!(self, style::SetForegroundColor(color)).unwrap();
+    }
+
+    fn set_background(&mut self, color: Color) {
+        let color = self.convert_color(color);
+        queue!(self, style::SetBackgroundColor(color)).unwrap();
+    }
+
     fn push(&mut self, c: char) {
         self.content.push(c)
     }
@@ -192,36 +666,233 @@ impl io::Write for EditorContents {
     }
 }
 
+/// The start of `text` that fits in `width` screen columns.
+fn fit_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (at, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            return &text[..at];
+        }
+    }
+    text
+}
+
+fn rgb_to_ansi(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |value: u8| {
+        (0..LEVELS.len())
+            .min_by_key(|&index| (LEVELS[index] as i32 - value as i32).abs())
+            .unwrap()
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        [(r, r2), (g, g2), (b, b2)]
+            .iter()
+            .map(|&(a, b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let grey_index = ((r as u32 + g as u32 + b as u32) / 3).saturating_sub(3) / 10;
+    let grey_index = cmp::min(grey_index, 23) as u8;
+    let grey_level = 8 + 10 * grey_index;
+    if distance((grey_level, grey_level, grey_level)) < distance(cube) {
+        232 + grey_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
 struct EditorRows {
-    row_contents: Vec<Row>,
+    row_contents: Rope<Row>,
     filename: Option<PathBuf>,
+    history: History,
+    syntax: Option<Box<dyn SyntaxHighlight>>,
+    tab_stop: usize,
+    persistent_undo: bool,
+    backup: Backup,
+    read_only: bool,
+    soft_wrap: bool,
+    show_invisibles: bool,
+    indent: Indent,
+    encoding: Encoding,
+    line_ending: LineEnding,
+    mixed_line_endings: bool,
+    loader: Option<Loader>,
+    disk_state: DiskState,
+    git: Option<Gutter>,
+    conflicts: Conflicts,
+    document: Option<Document>,
+    marks: Marks,
+    spell: bool,
+    highlight_occurrences: bool,
+    color_column: Option<usize>,
+    branch: Option<String>,
+    /// Counts the edits applied, to tell when the rows changed.
+    changes: u64,
 }
 
 impl EditorRows {
-    fn new() -> Self {
-        let mut arg = env::args();
-
-        match arg.nth(1) {
-            None => Self {
-                row_contents: Vec::new(),
-                filename: None,
-            },
-            Some(file) => Self::from_file(file.into()),
+    fn new(file: Option<PathBuf>, config: &Config) -> Self {
+        match file {
+            None => Self::empty(config),
+            Some(file) => Self::from_file(file, config).expect("Unable to read file"),
         }
     }
 
-    fn from_file(file: PathBuf) -> Self {
-        let file_content = fs::read_to_string(&file).expect("Unable to read file");
+    fn empty(config: &Config) -> Self {
         Self {
-            row_contents: file_content
-                .lines()
-                .map(|it| {
-                    let mut row = Row::new(it.into(), String::new());
-                    Self::render_row(&mut row);
-                    row
-                })
-                .collect(),
-            filename: Some(file),
+            row_contents: Rope::new(),
+            filename: None,
+            history: History::new(),
+            syntax: None,
+            tab_stop: config.tab_stop,
+            persistent_undo: config.persistent_undo,
+            backup: config.backup.clone(),
+            read_only: config.read_only,
+            soft_wrap: false,
+            show_invisibles: false,
+            indent: config.indent(None),
+            encoding: Encoding::Utf8,
+            line_ending: LineEnding::Lf,
+            mixed_line_endings: false,
+            loader: None,
+            disk_state: None,
+            git: None,
+            conflicts: Conflicts::default(),
+            document: None,
+            marks: Marks::default(),
+            spell: config.spell,
+            highlight_occurrences: config.highlight_occurrences,
+            color_column: config.color_column(None),
+            branch: None,
+            changes: 0,
+        }
+    }
+
+    fn from_file(file: PathBuf, config: &Config) -> io::Result<Self> {
+        if let Ok(Some(loader)) = Loader::open(&file) {
+            return Ok(Self::from_loader(file, loader, config));
+        }
+        let bytes = match fs::read(&file) {
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            result => result?,
+        };
+        let (file_content, encoding) = encoding::decode(&bytes);
+        let (line_ending, mixed_line_endings) = LineEnding::detect(&file_content);
+        let tab_stop = config.tab_stop;
+        let row_contents: Rope<Row> = file_content
+            .lines()
+            .map(|it| {
+                let mut row = Row::new(it.into(), String::new());
+                Self::render_row(&mut row, tab_stop);
+                row
+            })
+            .collect();
+        let content_hash =
+            undo::content_hash(row_contents.iter().map(|it| it.row_content.as_str()));
+        let history = if config.persistent_undo {
+            History::load(&file, content_hash)
+        } else {
+            None
+        };
+        let mut editor_rows = Self {
+            history: history.unwrap_or_else(History::new),
+            row_contents,
+            encoding,
+            line_ending,
+            mixed_line_endings,
+            ..Self::empty(config)
+        };
+        editor_rows.git = Gutter::load(&file);
+        editor_rows.branch = git::branch(&file);
+        editor_rows.marks = Marks::load(&file);
+        editor_rows.set_filename(file);
+        editor_rows.set_indent(config);
+        editor_rows.color_column = config.color_column(editor_rows.file_type());
+        Ok(editor_rows)
+    }
+
+    fn from_loader(file: PathBuf, loader: Loader, config: &Config) -> Self {
+        let mut editor_rows = Self {
+            loader: Some(loader),
+            ..Self::empty(config)
+        };
+        editor_rows.load(LOAD_BATCH);
+        if let Some(loader) = &editor_rows.loader {
+            editor_rows.line_ending = loader.line_ending().0;
+        }
+        editor_rows.git = Gutter::load(&file);
+        editor_rows.branch = git::branch(&file);
+        editor_rows.marks = Marks::load(&file);
+        editor_rows.set_filename(file);
+        editor_rows.set_indent(config);
+        editor_rows.color_column = config.color_column(editor_rows.file_type());
+        editor_rows
+    }
+
+    fn load_rows(&mut self, up_to: usize) {
+        if up_to >= self.number_of_rows() {
+            self.load(up_to - self.number_of_rows() + LOAD_BATCH);
+        }
+    }
+
+    fn load_all(&mut self) {
+        self.load(usize::MAX);
+    }
+
+    fn load(&mut self, count: usize) {
+        let loader = match &mut self.loader {
+            Some(loader) => loader,
+            None => return,
+        };
+        let lines = loader.read_lines(count).unwrap_or_default();
+        self.mixed_line_endings |= loader.line_ending().1;
+        self.encoding = loader.encoding();
+        // Saving would write the replaced bytes over the original ones.
+        self.read_only |= loader.lossy();
+        if lines.len() < count {
+            self.loader = None;
+        }
+        if lines.is_empty() {
+            return;
+        }
+        let start = self.number_of_rows();
+        for line in lines {
+            let y = self.row_contents.len();
+            let mut row = Row::new(line.clone(), String::new());
+            Self::render_row(&mut row, self.tab_stop);
+            self.row_contents.insert(y, row);
+            if let Some(syntax) = &mut self.syntax {
+                syntax.edit(&Edit::InsertRow { y, content: line }, &self.row_contents);
+            }
+        }
+        self.update_syntax(start);
+    }
+
+    fn set_filename(&mut self, file: PathBuf) {
+        self.syntax = syntax::select(
+            &file,
+            self.row_contents.get(0).map(|row| row.row_content.as_str()),
+        );
+        self.filename = Some(file);
+        self.remember_disk_state();
+        self.update_syntax(0);
+    }
+
+    fn file_type(&self) -> Option<&str> {
+        self.syntax.as_ref().map(|syntax| syntax.file_type())
+    }
+
+    fn has_signs(&self) -> bool {
+        self.git.is_some() || self.document.is_some()
+    }
+
+    fn update_syntax(&mut self, at: usize) {
+        if let Some(syntax) = &mut self.syntax {
+            if at < self.row_contents.len() {
+                syntax.update_syntax(at, &mut self.row_contents)
+            }
         }
     }
 
@@ -237,271 +908,1801 @@ impl EditorRows {
         &self.row_contents[at]
     }
 
-    fn get_render(&self, at: usize) -> &String {
-        &self.row_contents[at].render
-    }
-
-    fn render_row(row: &mut Row) {
-        let mut index = 0;
+    fn render_row(row: &mut Row, tab_stop: usize) {
+        let mut render_x = 0;
         let capacity = row
             .row_content
             .chars()
-            .fold(0, |acc, next| acc + if next == '\t' { TAB_STOP } else { 1 });
+            .fold(0, |acc, next| acc + if next == '\t' { tab_stop } else { 1 });
         row.render = String::with_capacity(capacity);
+        row.tab_stop = tab_stop;
         row.row_content.chars().for_each(|c| {
-            index += 1;
             if c == '\t' {
-                row.render.push(' ');
-                while index % TAB_STOP != 0 {
-                    row.render.push(' ');
-                    index += 1
-                }
+                let spaces = tab_stop - render_x % tab_stop;
+                (0..spaces).for_each(|_| row.render.push(' '));
+                render_x += spaces;
             } else {
-                row.render.push(c)
+                row.render.push(c);
+                render_x += c.width().unwrap_or(0);
             }
         })
     }
 
     fn insert_row(&mut self, at: usize, contents: String) {
-        let mut new_row = Row::new(contents, String::new());
-        EditorRows::render_row(&mut new_row);
-        self.row_contents.insert(at, new_row);
+        self.edit(Edit::InsertRow {
+            y: at,
+            content: contents,
+        });
     }
 
     fn get_editor_row_mut(&mut self, at: usize) -> &mut Row {
         &mut self.row_contents[at]
     }
 
-    fn save(&self) -> io::Result<usize> {
-        match &self.filename {
-            None => Err(io::Error::new(ErrorKind::Other, "No file name specified")),
-            Some(name) => {
-                let mut file = fs::OpenOptions::new().write(true).open(name)?;
-                let contents: String = self
-                    .row_contents
-                    .iter()
-                    .map(|it| it.row_content.as_str())
-                    .collect::<Vec<&str>>()
-                    .join("\n");
-                file.set_len(contents.len() as u64)?;
-                file.write_all(contents.as_bytes())?;
+    fn insert_char(&mut self, at_x: usize, at_y: usize, ch: char) {
+        if at_y == self.number_of_rows() {
+            self.insert_row(at_y, String::new());
+        }
+        self.edit(Edit::InsertChar {
+            x: at_x,
+            y: at_y,
+            ch,
+        });
+    }
+
+    fn delete_char(&mut self, at_x: usize, at_y: usize) {
+        let end = self.get_editor_row(at_y).next_boundary(at_x);
+        let mut chars = self.get_row(at_y)[at_x..end].chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => self.edit(Edit::DeleteChar {
+                x: at_x,
+                y: at_y,
+                ch,
+            }),
+            (Some(_), Some(_)) => self.replace_range(at_y, at_x, end, ""),
+            _ => {}
+        }
+    }
+
+    fn save_with(
+        &mut self,
+        write: fn(&Path, &[u8]) -> io::Result<()>,
+        backup: bool,
+    ) -> io::Result<usize> {
+        self.load_all();
+        match &self.filename {
+            None => Err(io::Error::new(ErrorKind::Other, "No file name specified")),
+            Some(name) => {
+                let contents: String = self
+                    .row_contents
+                    .iter()
+                    .flat_map(|it| [it.row_content.as_str(), self.line_ending.as_str()])
+                    .collect();
+                let bytes = self.encoding.encode(&contents)?;
+                if backup {
+                    self.backup.write(name)?;
+                }
+                write(name, &bytes)?;
+                self.disk_state = reload::disk_state(name);
+                let content_hash =
+                    undo::content_hash(self.row_contents.iter().map(|it| it.row_content.as_str()));
+                self.history.commit();
+                if self.persistent_undo {
+                    self.history.save(name, content_hash).ok();
+                }
+                self.marks.save(name).ok();
+                self.mixed_line_endings = false;
+
+                Ok(bytes.len())
+            }
+        }
+    }
+
+    fn replace_range(&mut self, at_y: usize, start: usize, end: usize, text: &str) {
+        self.edit(Edit::Replace {
+            y: at_y,
+            start,
+            old: self.get_row(at_y)[start..end].into(),
+            new: text.into(),
+        });
+    }
+
+    fn split_row(&mut self, at_x: usize, at_y: usize) {
+        self.edit(Edit::SplitRow { x: at_x, y: at_y });
+    }
+
+    fn join_adjacent_rows(&mut self, at: usize) {
+        self.edit(Edit::JoinRows {
+            x: self.get_row(at - 1).len(),
+            y: at - 1,
+        });
+    }
+
+    fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        if start.1 == end.1 {
+            return self.get_row(start.1)[start.0..end.0].into();
+        }
+        let mut text = String::from(&self.get_row(start.1)[start.0..]);
+        for y in start.1 + 1..end.1 {
+            text.push('\n');
+            text.push_str(self.get_row(y));
+        }
+        text.push('\n');
+        text.push_str(&self.get_row(end.1)[..end.0]);
+        text
+    }
+
+    fn delete_range(&mut self, start: (usize, usize), end: (usize, usize)) -> String {
+        let text = self.text_range(start, end);
+        if start == end {
+            return text;
+        }
+        if start.1 == end.1 {
+            self.replace_range(start.1, start.0, end.0, "");
+            return text;
+        }
+        self.replace_range(end.1, 0, end.0, "");
+        for y in (start.1 + 1..end.1).rev() {
+            let content = self.get_row(y).into();
+            self.edit(Edit::DeleteRow { y, content });
+        }
+        self.replace_range(start.1, start.0, self.get_row(start.1).len(), "");
+        self.join_adjacent_rows(start.1 + 1);
+        text
+    }
+
+    fn insert_text(&mut self, x: usize, y: usize, text: &str) -> (usize, usize) {
+        if y == self.number_of_rows() {
+            self.insert_row(y, String::new());
+        }
+        let (mut x, mut y) = (x, y);
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.split_row(x, y);
+                x = 0;
+                y += 1;
+            }
+            if !line.is_empty() {
+                self.replace_range(y, x, x, line);
+                x += line.len();
+            }
+        }
+        (x, y)
+    }
+
+    fn delete_rows(&mut self, from: usize, to: usize) {
+        for y in (from..cmp::min(to + 1, self.number_of_rows())).rev() {
+            let content = self.get_row(y).into();
+            self.edit(Edit::DeleteRow { y, content });
+        }
+    }
+
+    fn edit(&mut self, edit: Edit) {
+        self.apply(&edit);
+        self.history.record(edit);
+    }
+
+    fn apply(&mut self, edit: &Edit) {
+        self.changes += 1;
+        self.marks.adjust(edit);
+        self.apply_to_rows(edit);
+        if let Some(syntax) = &mut self.syntax {
+            syntax.edit(edit, &self.row_contents);
+        }
+        self.update_syntax(edit.cursor_before().1);
+    }
+
+    fn apply_to_rows(&mut self, edit: &Edit) {
+        let tab_stop = self.tab_stop;
+        match edit {
+            Edit::InsertChar { x, y, ch } => self.get_editor_row_mut(*y).insert_char(*x, *ch),
+            Edit::DeleteChar { x, y, .. } => self.get_editor_row_mut(*y).delete_char(*x),
+            Edit::InsertRow { y, content } => {
+                let mut new_row = Row::new(content.clone(), String::new());
+                Self::render_row(&mut new_row, tab_stop);
+                self.row_contents.insert(*y, new_row);
+            }
+            Edit::DeleteRow { y, .. } => {
+                self.row_contents.remove(*y);
+            }
+            Edit::SplitRow { x, y } => {
+                let current_row = self.get_editor_row_mut(*y);
+                let new_row_content = current_row.row_content[*x..].into();
+                current_row.row_content.truncate(*x);
+                Self::render_row(current_row, tab_stop);
+                self.apply_to_rows(&Edit::InsertRow {
+                    y: y + 1,
+                    content: new_row_content,
+                });
+            }
+            Edit::JoinRows { y, .. } => {
+                let next_row = self.row_contents.remove(y + 1);
+                let current_row = self.get_editor_row_mut(*y);
+                current_row.row_content.push_str(&next_row.row_content);
+                Self::render_row(current_row, tab_stop);
+            }
+            Edit::Replace { y, start, old, new } => {
+                let row = self.get_editor_row_mut(*y);
+                row.row_content
+                    .replace_range(*start..start + old.len(), new);
+                Self::render_row(row, tab_stop);
+            }
+        }
+    }
+
+    fn undo(&mut self) -> Option<(usize, usize)> {
+        let edits = self.history.take_undo()?;
+        edits
+            .iter()
+            .rev()
+            .for_each(|edit| self.apply(&edit.inverse()));
+        let cursor = edits.first().map(Edit::cursor_before);
+        self.history.push_redo(edits);
+        cursor
+    }
+
+    fn redo(&mut self) -> Option<(usize, usize)> {
+        let edits = self.history.take_redo()?;
+        edits.iter().for_each(|edit| self.apply(edit));
+        let cursor = edits.last().map(Edit::cursor_after);
+        self.history.push_undo(edits);
+        cursor
+    }
+}
+
+struct Buffer {
+    editor_rows: EditorRows,
+    cursor_controller: CursorController,
+    dirty: u64,
+    /// What `dirty` was when the swap file was last written, 0 for none.
+    swapped: u64,
+    /// Whether recovery from a swap file left behind was offered yet.
+    swap_checked: bool,
+    jump_list: JumpList,
+}
+
+impl Buffer {
+    fn new(editor_rows: EditorRows, win_size: (usize, usize), config: &Config) -> Self {
+        Self {
+            editor_rows,
+            cursor_controller: CursorController::new(win_size, config),
+            dirty: 0,
+            swapped: 0,
+            swap_checked: false,
+            jump_list: JumpList::default(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.editor_rows
+            .filename
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or("[No name]")
+    }
+}
+
+struct Output {
+    win_size: (usize, usize),
+    buffer: Buffer,
+    buffers: Vec<Buffer>,
+    current_buffer: usize,
+    /// The first `arguments` buffers are the files named on the command line.
+    arguments: usize,
+    argument: usize,
+    windows: Vec<Window>,
+    current_window: usize,
+    layout: Layout,
+    editor_contents: EditorContents,
+    status_message: StatusMessage,
+    search_index: SearchIndex,
+    theme: Theme,
+    config: Config,
+    mode: Option<Mode>,
+    comparison: Option<Comparison>,
+    quickfix: Quickfix,
+    completion: Option<Completion>,
+    info: Option<Info>,
+    jumps: Jumps,
+    language_servers: LanguageServers,
+    spelling: Spelling,
+    occurrences: Occurrences,
+}
+
+impl Output {
+    fn new(config: io::Result<Config>, files: Vec<PathBuf>, read_only: bool) -> Self {
+        let win_size = terminal::size()
+            .map(|(x, y)| (x as usize, y as usize - 2))
+            .unwrap();
+        let (config, message) = match config {
+            Ok(config) => (
+                config,
+                "HELP: CTRL-S = Save | CTRL-Q = Quit | CTRL-F = Find | CTRL-N/P = Next/Prev match"
+                    .into(),
+            ),
+            Err(err) => (Config::default(), format!("Can't load config: {}", err)),
+        };
+        let config = Config {
+            read_only,
+            ..config
+        };
+        let (theme, message) = match config.theme() {
+            Ok(theme) => (theme, message),
+            Err(err) => (Theme::default(), format!("Can't load theme: {}", err)),
+        };
+        let arguments = cmp::max(files.len(), 1);
+        let mut buffers = files.into_iter().map(|file| {
+            welcome::remember(&file).ok();
+            Buffer::new(EditorRows::new(Some(file), &config), win_size, &config)
+        });
+        let buffer = buffers
+            .next()
+            .unwrap_or_else(|| Buffer::new(EditorRows::new(None, &config), win_size, &config));
+
+        Self {
+            win_size,
+            buffer,
+            buffers: buffers.collect(),
+            current_buffer: 0,
+            arguments,
+            argument: 0,
+            windows: vec![Window {
+                buffer: 0,
+                cursor_controller: CursorController::new(win_size, &config),
+            }],
+            current_window: 0,
+            layout: Layout::Window(0),
+            editor_contents: EditorContents::new(),
+            status_message: StatusMessage::new(message),
+            search_index: SearchIndex::new(),
+            theme,
+            mode: config.modal.then(|| Mode::Normal),
+            config,
+            comparison: None,
+            quickfix: Quickfix::default(),
+            completion: None,
+            info: None,
+            jumps: Jumps::default(),
+            language_servers: LanguageServers::default(),
+            spelling: Spelling::default(),
+            occurrences: Occurrences::default(),
+        }
+    }
+
+    fn clear_screen() -> crossterm::Result<()> {
+        execute!(stdout(), terminal::Clear(ClearType::All))?;
+        execute!(stdout(), cursor::MoveTo(0, 0))
+    }
+
+    fn draw_rows(&mut self, window: usize, rect: Rect) {
+        let screen_columns = rect.width - self.scrollbar_width(rect.width);
+        let cursor_controller = self.window_cursor(window);
+        let buffer_index = self.window_buffer(window);
+        let buffer = match self.buffer_slot(buffer_index) {
+            Some(slot) => &self.buffers[slot],
+            None => &self.buffer,
+        };
+        let screen_lines = cursor_controller.screen_lines(&buffer.editor_rows);
+        let inclusive = self.mode.map_or(false, Mode::is_visual);
+        let brackets = bracket::bracket_pair(
+            &buffer.editor_rows,
+            (cursor_controller.cursor_x, cursor_controller.cursor_y),
+        );
+        let current = window == self.current_window;
+        let cursor_line = (current && self.config.cursor_line).then(|| cursor_controller.cursor_y);
+        let cursor_column = (current && self.config.cursor_column)
+            .then(|| cursor_controller.screen_position(&buffer.editor_rows).0);
+        for (i, (file_row, segment, column_offset)) in screen_lines.into_iter().enumerate() {
+            queue!(
+                self.editor_contents,
+                cursor::MoveTo(rect.x as u16, (rect.y + i) as u16)
+            )
+            .unwrap();
+            let mut width = 0;
+            let mut line_background = None;
+            // The cells past the end of the text that are colored, rather than
+            // left blank.
+            let mut colored_cells = Vec::new();
+            if file_row >= buffer.editor_rows.number_of_rows() {
+                self.editor_contents.push('~');
+                width = 1;
+            } else {
+                let gutter_width = cursor_controller.gutter_width;
+                if gutter_width > 0 && segment > 0 {
+                    self.editor_contents.push_str(&" ".repeat(gutter_width));
+                } else if gutter_width > 0 {
+                    let editor_rows = &buffer.editor_rows;
+                    if editor_rows.has_signs() {
+                        let severity = editor_rows
+                            .document
+                            .as_ref()
+                            .and_then(|document| document.severity(file_row));
+                        let sign = match severity {
+                            Some(severity) => {
+                                Some((severity.symbol(), severity.color(&self.theme)))
+                            }
+                            None => editor_rows
+                                .git
+                                .as_ref()
+                                .and_then(|gutter| gutter.sign(file_row))
+                                .map(|sign| (sign.symbol(), sign.color(&self.theme))),
+                        };
+                        match sign {
+                            Some((symbol, color)) => {
+                                self.editor_contents.set_foreground(color);
+                                self.editor_contents.push(symbol);
+                            }
+                            None => self.editor_contents.push(' '),
+                        }
+                    }
+                    self.editor_contents.set_foreground(self.theme.line_number);
+                    let cursor_y = cursor_controller.cursor_y;
+                    let width =
+                        (gutter_width - buffer.editor_rows.has_signs() as usize).saturating_sub(1);
+                    let number = match cursor_controller.line_numbers {
+                        LineNumbers::Off => String::new(),
+                        LineNumbers::Relative if file_row == cursor_y => {
+                            format!("{:<width$} ", file_row + 1, width = width)
+                        }
+                        LineNumbers::Relative => format!(
+                            "{:>width$} ",
+                            cmp::max(file_row, cursor_y) - cmp::min(file_row, cursor_y),
+                            width = width
+                        ),
+                        _ => format!("{:>width$} ", file_row + 1, width = width),
+                    };
+                    self.editor_contents.push_str(&number);
+                    self.editor_contents.set_foreground(Color::Reset);
+                }
+                width += gutter_width;
+                let row = buffer.editor_rows.get_editor_row(file_row);
+                let mut matches: Vec<(usize, usize)> = match &self.search_index.highlight {
+                    Some(pattern) => pattern
+                        .ranges(&row.row_content)
+                        .into_iter()
+                        .map(|(from, to)| (row.render_index(from), row.render_index(to)))
+                        .collect(),
+                    None => Vec::new(),
+                };
+                if let Some((from, to)) = cursor_controller.selection_in(file_row, row, inclusive) {
+                    matches.push((row.render_index(from), row.render_index(to)));
+                }
+                let bracket_columns: Vec<usize> = brackets
+                    .iter()
+                    .flat_map(|&(one, other)| [one, other])
+                    .filter(|&(_, y)| y == file_row)
+                    .map(|(x, _)| row.render_index(x))
+                    .collect();
+                // Not while typing at the end of the cursor line.
+                let text_end = row.row_content.trim_end().len();
+                let trailing = if file_row == cursor_controller.cursor_y
+                    && cursor_controller.cursor_x > text_end
+                {
+                    usize::MAX
+                } else {
+                    row.render_index(text_end)
+                };
+                if cursor_line == Some(file_row) {
+                    line_background = Some(self.theme.cursor_line);
+                }
+                if let Some(part) = buffer.editor_rows.conflict_part(file_row) {
+                    line_background = Some(part.color(&self.theme));
+                }
+                let occurrences = if current && buffer_index == self.current_buffer {
+                    self.occurrences_in(file_row, row)
+                } else {
+                    Vec::new()
+                };
+                let mut changed_columns = Vec::new();
+                if let Some(comparison) = &self.comparison {
+                    match comparison.mark(buffer_index, file_row) {
+                        Some(mark) if mark.only => line_background = Some(self.theme.diff_added),
+                        Some(mark) => {
+                            line_background = Some(self.theme.diff_changed);
+                            changed_columns = mark
+                                .ranges
+                                .iter()
+                                .map(|range| {
+                                    (row.render_index(range.start), row.render_index(range.end))
+                                })
+                                .collect();
+                        }
+                        None => {}
+                    }
+                }
+                let mut underlines = match &buffer.editor_rows.document {
+                    Some(document) => document.ranges(file_row, row),
+                    None => Vec::new(),
+                };
+                if buffer.editor_rows.spell {
+                    let prose = matches!(buffer.editor_rows.file_type(), None | Some("markdown"));
+                    underlines.extend(self.spelling.misspelled(row, prose));
+                }
+                let underlines: Vec<(usize, usize)> = underlines
+                    .into_iter()
+                    .map(|(from, to)| (row.render_index(from), row.render_index(to)))
+                    .collect();
+                let invisibles = if buffer.editor_rows.show_invisibles {
+                    row.invisibles()
+                } else {
+                    Vec::new()
+                };
+                let mut highlighted = false;
+                let mut underlined = false;
+                let normal = self.theme.syntax_color(HighlightType::Normal);
+                let mut current_color = Color::Reset;
+                let last_column = column_offset + cursor_controller.text_columns();
+                let cursor_column = cursor_column.map(|x| x - gutter_width + column_offset);
+                let guide = buffer.editor_rows.color_column.map(|column| column - 1);
+                let tint = self.config.tint_past_column;
+                for (x, color) in [
+                    (cursor_column, self.theme.cursor_column),
+                    (guide, self.theme.color_column),
+                ] {
+                    if let Some(x) = x.filter(|x| (column_offset..last_column).contains(x)) {
+                        colored_cells.push((gutter_width + x - column_offset, color));
+                    }
+                }
+                let mut render_x = 0;
+                row.render
+                    .chars()
+                    .enumerate()
+                    .filter_map(|(column, c)| {
+                        let start = render_x;
+                        render_x += c.width().unwrap_or(0);
+                        (start >= column_offset && render_x <= last_column)
+                            .then(|| (column, c, start..cmp::max(render_x, start + 1)))
+                    })
+                    .for_each(|(column, c, cells)| {
+                        let in_match = matches
+                            .iter()
+                            .any(|&(from, to)| from <= column && column < to);
+                        if in_match != highlighted {
+                            highlighted = in_match;
+                            self.editor_contents.push_str(
+                                &if in_match {
+                                    style::Attribute::Reverse
+                                } else {
+                                    style::Attribute::NoReverse
+                                }
+                                .to_string(),
+                            );
+                        }
+                        let in_underline = underlines
+                            .iter()
+                            .any(|&(from, to)| from <= column && column < to);
+                        if in_underline != underlined {
+                            underlined = in_underline;
+                            self.editor_contents.push_str(
+                                &if in_underline {
+                                    style::Attribute::Underlined
+                                } else {
+                                    style::Attribute::NoUnderline
+                                }
+                                .to_string(),
+                            );
+                        }
+                        let shown = invisibles.get(column).copied().flatten();
+                        let color = match shown {
+                            Some(_) => self.theme.invisible,
+                            None => row
+                                .highlight
+                                .get(column)
+                                .map_or(normal, |&highlight| self.theme.syntax_color(highlight)),
+                        };
+                        let c = shown.unwrap_or(c);
+                        if color != current_color {
+                            current_color = color;
+                            self.editor_contents.set_foreground(color);
+                        }
+                        width += c.width().unwrap_or(0);
+                        let background = if bracket_columns.contains(&column) {
+                            Some(self.theme.matching_bracket)
+                        } else if column >= trailing {
+                            Some(self.theme.trailing_whitespace)
+                        } else if changed_columns
+                            .iter()
+                            .any(|&(from, to)| from <= column && column < to)
+                        {
+                            Some(self.theme.diff_text)
+                        } else if occurrences
+                            .iter()
+                            .any(|&(from, to)| from <= column && column < to)
+                        {
+                            Some(self.theme.occurrence)
+                        } else if cursor_column.map_or(false, |x| cells.contains(&x)) {
+                            Some(self.theme.cursor_column)
+                        } else if guide
+                            .map_or(false, |x| cells.contains(&x) || tint && cells.start > x)
+                        {
+                            Some(self.theme.color_column)
+                        } else {
+                            line_background
+                        };
+                        match background {
+                            Some(background) => {
+                                self.editor_contents.set_background(background);
+                                self.editor_contents.push(c);
+                                self.editor_contents.set_background(Color::Reset);
+                            }
+                            None => self.editor_contents.push(c),
+                        }
+                    });
+                if highlighted {
+                    self.editor_contents
+                        .push_str(&style::Attribute::NoReverse.to_string());
+                }
+                if underlined {
+                    self.editor_contents
+                        .push_str(&style::Attribute::NoUnderline.to_string());
+                }
+                let row_width = row.render_x(row.row_content.len());
+                if buffer.editor_rows.show_invisibles
+                    && (column_offset..last_column).contains(&row_width)
+                {
+                    current_color = self.theme.invisible;
+                    self.editor_contents.set_foreground(current_color);
+                    self.editor_contents.push(invisibles::LINE_END);
+                    width += 1;
+                }
+                if current_color != Color::Reset {
+                    self.editor_contents.set_foreground(Color::Reset);
+                }
+            }
+            if let Some(background) = line_background {
+                self.editor_contents.set_background(background);
+            }
+            colored_cells.sort_by_key(|&(x, _)| x);
+            for (x, color) in colored_cells {
+                if x < width || x >= screen_columns {
+                    continue;
+                }
+                self.editor_contents.push_str(&" ".repeat(x - width));
+                self.editor_contents.set_background(color);
+                self.editor_contents.push(' ');
+                self.editor_contents
+                    .set_background(line_background.unwrap_or(Color::Reset));
+                width = x + 1;
+            }
+            self.editor_contents
+                .push_str(&" ".repeat(screen_columns.saturating_sub(width)));
+            if line_background.is_some() {
+                self.editor_contents.set_background(Color::Reset);
+            }
+        }
+    }
+
+    fn draw_status_bar(&mut self, window: usize, rect: Rect) {
+        queue!(
+            self.editor_contents,
+            cursor::MoveTo(rect.x as u16, (rect.y + rect.height - 1) as u16)
+        )
+        .unwrap();
+        self.editor_contents
+            .set_foreground(self.theme.status_bar_foreground);
+        self.editor_contents
+            .set_background(self.theme.status_bar_background);
+
+        let format = self
+            .config
+            .status_format
+            .as_deref()
+            .unwrap_or(status::DEFAULT_FORMAT);
+        let (info, line_info) = status::expand(format, |name| self.status_segment(window, name));
+        let info = fit_width(&info, rect.width);
+        let info_len = info.width();
+        self.editor_contents.push_str(info);
+        for i in info_len..rect.width {
+            if rect.width - i == line_info.width() {
+                self.editor_contents.push_str(&line_info);
+                break;
+            } else {
+                self.editor_contents.push(' ');
+            }
+        }
+
+        self.editor_contents
+            .push_str(&style::Attribute::Reset.to_string());
+    }
+
+    fn draw_separators(&mut self, separators: &[Rect]) {
+        self.editor_contents
+            .set_foreground(self.theme.status_bar_foreground);
+        self.editor_contents
+            .set_background(self.theme.status_bar_background);
+        for separator in separators {
+            for y in separator.y..separator.y + separator.height {
+                queue!(
+                    self.editor_contents,
+                    cursor::MoveTo(separator.x as u16, y as u16)
+                )
+                .unwrap();
+                self.editor_contents.push('│');
+            }
+        }
+        self.editor_contents
+            .push_str(&style::Attribute::Reset.to_string());
+    }
+
+    fn draw_message_bar(&mut self) {
+        let y = self.win_size.1 as u16 + 1;
+        queue!(
+            self.editor_contents,
+            cursor::MoveTo(0, y),
+            terminal::Clear(ClearType::UntilNewLine),
+        )
+        .unwrap();
+        if let Some(msg) = self.status_message.message() {
+            self.editor_contents
+                .push_str(fit_width(msg, self.win_size.0));
+        }
+    }
+
+    fn refresh_screen(&mut self) -> crossterm::Result<()> {
+        self.buffer.editor_rows.update_gutter();
+        self.buffer.editor_rows.update_conflicts();
+        for buffer in &mut self.buffers {
+            buffer.editor_rows.update_gutter();
+            buffer.editor_rows.update_conflicts();
+        }
+        self.update_comparison();
+        self.update_language_servers()?;
+        self.update_completion();
+        self.update_info();
+        self.update_spelling();
+        self.update_occurrences();
+        let (mut windows, separators) = self.window_rects();
+        windows.retain(|(_, rect)| rect.height > 0);
+        for &(window, rect) in &windows {
+            self.scroll_window(window, rect);
+        }
+        self.sync_comparison();
+        queue!(self.editor_contents, cursor::Hide)?;
+
+        for &(window, rect) in &windows {
+            self.draw_rows(window, rect);
+            self.draw_scrollbar(window, rect);
+            self.draw_status_bar(window, rect);
+        }
+        self.draw_separators(&separators);
+        self.draw_message_bar();
+        self.draw_info()?;
+        self.draw_completion()?;
+
+        let rect = self.window_rect(self.current_window);
+        let (cursor_x, cursor_y) = self
+            .buffer
+            .cursor_controller
+            .screen_position(&self.buffer.editor_rows);
+        queue!(
+            self.editor_contents,
+            cursor::MoveTo((rect.x + cursor_x) as u16, (rect.y + cursor_y) as u16),
+            cursor::Show
+        )?;
+
+        self.editor_contents.flush()
+    }
+
+    fn window_rects(&self) -> (Vec<(usize, Rect)>, Vec<Rect>) {
+        let area = Rect {
+            x: 0,
+            y: 0,
+            width: self.win_size.0,
+            height: self.win_size.1 + 1,
+        };
+        let mut windows = Vec::with_capacity(self.windows.len());
+        let mut separators = Vec::new();
+        self.layout.arrange(area, &mut windows, &mut separators);
+        (windows, separators)
+    }
+
+    fn window_rect(&self, window: usize) -> Rect {
+        let (windows, _) = self.window_rects();
+        windows
+            .into_iter()
+            .find(|&(id, _)| id == window)
+            .map(|(_, rect)| rect)
+            .unwrap()
+    }
+
+    fn window_buffer(&self, window: usize) -> usize {
+        if window == self.current_window {
+            self.current_buffer
+        } else {
+            self.windows[window].buffer
+        }
+    }
+
+    fn window_cursor(&self, window: usize) -> CursorController {
+        if window == self.current_window {
+            self.buffer.cursor_controller
+        } else {
+            self.windows[window].cursor_controller
+        }
+    }
+
+    /// Where buffer `index` is kept in `buffers`, `None` for the current one.
+    fn buffer_slot(&self, index: usize) -> Option<usize> {
+        match index.cmp(&self.current_buffer) {
+            Ordering::Less => Some(index),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(index - 1),
+        }
+    }
+
+    fn scroll_window(&mut self, window: usize, rect: Rect) {
+        let slot = self.buffer_slot(self.window_buffer(window));
+        let scrollbar_width = self.scrollbar_width(rect.width);
+        let cursor_controller = if window == self.current_window {
+            &mut self.buffer.cursor_controller
+        } else {
+            &mut self.windows[window].cursor_controller
+        };
+        let editor_rows = match slot {
+            Some(slot) => &mut self.buffers[slot].editor_rows,
+            None => &mut self.buffer.editor_rows,
+        };
+        cursor_controller.screen_columns = rect.width - scrollbar_width;
+        cursor_controller.screen_rows = rect.height - 1;
+        editor_rows.load_rows(
+            cmp::max(cursor_controller.cursor_y, cursor_controller.row_offset)
+                + 2 * cursor_controller.screen_rows,
+        );
+        cursor_controller.clamp(editor_rows);
+        cursor_controller.scroll(editor_rows);
+    }
+
+    fn focus_window(&mut self, window: usize) {
+        if window == self.current_window {
+            return;
+        }
+        let current = &mut self.windows[self.current_window];
+        current.buffer = self.current_buffer;
+        current.cursor_controller = self.buffer.cursor_controller;
+        self.switch_buffer(self.windows[window].buffer);
+        self.buffer.cursor_controller = self.windows[window].cursor_controller;
+        self.current_window = window;
+    }
+
+    fn split_window(&mut self, vertical: bool) {
+        let rect = self.window_rect(self.current_window);
+        if (vertical && rect.width < 3) || (!vertical && rect.height < 4) {
+            self.status_message
+                .set_message("Not enough room to split the window".into());
+            return;
+        }
+        let window = self.windows.len();
+        self.windows.push(Window {
+            buffer: self.current_buffer,
+            cursor_controller: self.buffer.cursor_controller,
+        });
+        self.layout.split(self.current_window, window, vertical);
+        self.focus_window(window);
+    }
+
+    fn close_window(&mut self) {
+        if self.windows.len() == 1 {
+            self.status_message
+                .set_message("Can't close the last window".into());
+            return;
+        }
+        let closed = self.current_window;
+        self.cycle_window();
+        self.windows.remove(closed);
+        self.layout.close(closed);
+        if self.current_window > closed {
+            self.current_window -= 1;
+        }
+    }
+
+    fn cycle_window(&mut self) {
+        let (windows, _) = self.window_rects();
+        let position = windows
+            .iter()
+            .position(|&(id, _)| id == self.current_window)
+            .unwrap();
+        self.focus_window(windows[(position + 1) % windows.len()].0);
+    }
+
+    fn move_to_window(&mut self, direction: Direction) {
+        let (windows, _) = self.window_rects();
+        let rect = self.window_rect(self.current_window);
+        let (x, y) = self
+            .buffer
+            .cursor_controller
+            .screen_position(&self.buffer.editor_rows);
+        if let Some(window) = window::neighbour(&windows, rect, (rect.x + x, rect.y + y), direction)
+        {
+            self.focus_window(window)
+        }
+    }
+
+    fn resize(&mut self, columns: u16, rows: u16) {
+        self.win_size = (columns as usize, (rows as usize).saturating_sub(2));
+    }
+
+    /// The end is exclusive, except in visual mode.
+    fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let anchor = cursor_controller.anchor?;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let (start, end) = if (anchor.1, anchor.0) <= (cursor.1, cursor.0) {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+        let editor_rows = &self.buffer.editor_rows;
+        let rows = editor_rows.number_of_rows();
+        if rows == 0 {
+            return None;
+        }
+        // The anchor stays put while its row is edited from elsewhere.
+        let clamp = |(x, y): (usize, usize)| {
+            if y >= rows {
+                return (x, y);
+            }
+            (editor_rows.get_editor_row(y).clamp_x(x), y)
+        };
+        let (start, end) = (clamp(start), clamp(end));
+        let last = (editor_rows.get_row(rows - 1).len(), rows - 1);
+        let start = if start.1 < rows { start } else { last };
+        let end = if end.1 >= rows {
+            last
+        } else if self.mode == Some(Mode::Visual) && end.0 < editor_rows.get_row(end.1).len() {
+            (
+                editor_rows.get_editor_row(end.1).next_boundary(end.0),
+                end.1,
+            )
+        } else {
+            end
+        };
+        Some((start, end))
+    }
+
+    fn clear_selection(&mut self) {
+        self.buffer.cursor_controller.anchor = None;
+        self.buffer.cursor_controller.block = None;
+        if self.mode.map_or(false, Mode::is_visual) {
+            self.mode = Some(Mode::Normal);
+        }
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        if self.delete_block() {
+            self.clear_selection();
+            return true;
+        }
+        let (start, end) = match self.selection() {
+            Some(selection) => selection,
+            None => return false,
+        };
+        if self.reject_edit() {
+            return true;
+        }
+        self.buffer.editor_rows.delete_range(start, end);
+        self.buffer.cursor_controller.cursor_x = start.0;
+        self.buffer.cursor_controller.cursor_y = start.1;
+        self.buffer.dirty += 1;
+        self.clear_selection();
+        true
+    }
+
+    fn select(&mut self, direction: KeyCode) {
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        cursor_controller.block = None;
+        if cursor_controller.anchor.is_none() {
+            cursor_controller.anchor =
+                Some((cursor_controller.cursor_x, cursor_controller.cursor_y));
+        }
+        self.move_cursor(direction);
+    }
+
+    fn selected_rows(&self) -> Option<(usize, usize)> {
+        let rows = self.buffer.editor_rows.number_of_rows();
+        match self.selection() {
+            Some((start, end)) => Some((start.1, end.1)),
+            None if self.buffer.cursor_controller.cursor_y < rows => {
+                let y = self.buffer.cursor_controller.cursor_y;
+                Some((y, y))
+            }
+            None => None,
+        }
+    }
+
+    fn indent_lines(&mut self, dedent: bool) {
+        let (first, last) = match self.selected_rows() {
+            Some(rows) => rows,
+            None => return,
+        };
+        if self.reject_edit() {
+            return;
+        }
+        let indent = self.buffer.editor_rows.indent;
+        let unit = indent.unit();
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        for y in first..=last {
+            let editor_rows = &mut self.buffer.editor_rows;
+            let row = editor_rows.get_row(y);
+            let shift = |x: &mut usize, width: usize| {
+                *x = if dedent {
+                    x.saturating_sub(width)
+                } else {
+                    *x + width
+                }
+            };
+            let width = if dedent {
+                let spaces = row.len() - row.trim_start_matches(' ').len();
+                if row.starts_with('\t') {
+                    1
+                } else {
+                    cmp::min(spaces, indent.width)
+                }
+            } else if row.trim().is_empty() {
+                0
+            } else {
+                editor_rows.replace_range(y, 0, 0, &unit);
+                unit.len()
+            };
+            if width == 0 {
+                continue;
+            }
+            if dedent {
+                editor_rows.replace_range(y, 0, width, "");
+            }
+            if cursor_controller.cursor_y == y {
+                shift(&mut cursor_controller.cursor_x, width);
+            }
+            if let Some((x, anchor_y)) = &mut cursor_controller.anchor {
+                if *anchor_y == y {
+                    shift(x, width);
+                }
+            }
+        }
+        self.buffer.dirty += 1;
+    }
+
+    fn process_mouse(&mut self, event: MouseEvent) {
+        let (column, row) = (event.column as usize, event.row as usize);
+        let (windows, _) = self.window_rects();
+        let (window, rect) = match windows.into_iter().find(|(_, rect)| {
+            (rect.x..rect.x + rect.width).contains(&column)
+                && (rect.y..rect.y + rect.height).contains(&row)
+        }) {
+            Some(found) => found,
+            None => return,
+        };
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.focus_window(window);
+                self.clear_selection();
+                self.place_cursor(rect, (column, row));
+            }
+            MouseEventKind::Drag(MouseButton::Left) if window == self.current_window => {
+                let cursor_controller = &mut self.buffer.cursor_controller;
+                if cursor_controller.anchor.is_none() {
+                    cursor_controller.anchor =
+                        Some((cursor_controller.cursor_x, cursor_controller.cursor_y));
+                    if self.mode.is_some() {
+                        self.mode = Some(Mode::Visual);
+                    }
+                }
+                self.place_cursor(rect, (column, row));
+            }
+            MouseEventKind::ScrollDown => {
+                self.focus_window(window);
+                self.scroll_lines(true);
+            }
+            MouseEventKind::ScrollUp => {
+                self.focus_window(window);
+                self.scroll_lines(false);
+            }
+            _ => {}
+        }
+    }
+
+    fn place_cursor(&mut self, rect: Rect, (column, row): (usize, usize)) {
+        let editor_rows = &self.buffer.editor_rows;
+        let cursor_controller = &self.buffer.cursor_controller;
+        let lines = cursor_controller.screen_lines(editor_rows);
+        let (file_row, _, render_start) = match lines.get(row - rect.y) {
+            Some(&line) => line,
+            _ => return,
+        };
+        let rows = editor_rows.number_of_rows();
+        let y = cmp::min(file_row, rows.saturating_sub(1));
+        let x = match editor_rows.row_contents.get(y) {
+            Some(row) if y == file_row => row.cursor_x(
+                render_start + (column - rect.x).saturating_sub(cursor_controller.gutter_width),
+            ),
+            Some(row) => row.row_content.len(),
+            None => 0,
+        };
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        cursor_controller.cursor_x = x;
+        cursor_controller.cursor_y = y;
+    }
+
+    fn scroll_lines(&mut self, down: bool) {
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let screen_rows = cursor_controller.screen_rows;
+        self.buffer
+            .editor_rows
+            .load_rows(cursor_controller.row_offset + SCROLL_LINES + screen_rows);
+        let rows = self.buffer.editor_rows.number_of_rows();
+        let margin = cmp::min(
+            cursor_controller.scroll_margin,
+            screen_rows.saturating_sub(1) / 2,
+        );
+        if down {
+            cursor_controller.row_offset = cmp::min(
+                cursor_controller.row_offset + SCROLL_LINES,
+                rows.saturating_sub(1),
+            );
+            cursor_controller.cursor_y = cmp::max(
+                cursor_controller.cursor_y,
+                cmp::min(cursor_controller.row_offset + margin, rows),
+            );
+        } else {
+            cursor_controller.row_offset =
+                cursor_controller.row_offset.saturating_sub(SCROLL_LINES);
+            let bottom = (cursor_controller.row_offset + screen_rows).saturating_sub(margin + 1);
+            cursor_controller.cursor_y = cmp::min(cursor_controller.cursor_y, bottom);
+        }
+        cursor_controller.clamp(&self.buffer.editor_rows);
+    }
+
+    fn scroll_page(&mut self, rows: usize, down: bool) {
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let screen_rows = cursor_controller.screen_rows;
+        let column = cursor_controller.preferred_column(&self.buffer.editor_rows);
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.load_rows(cursor_controller.row_offset + rows + screen_rows);
+        let number_of_rows = editor_rows.number_of_rows();
+        if down {
+            let last_offset = (number_of_rows + 1).saturating_sub(screen_rows);
+            cursor_controller.row_offset = cmp::max(
+                cursor_controller.row_offset,
+                cmp::min(cursor_controller.row_offset + rows, last_offset),
+            );
+            cursor_controller.cursor_y =
+                cmp::min(cursor_controller.cursor_y + rows, number_of_rows);
+        } else {
+            cursor_controller.row_offset = cursor_controller.row_offset.saturating_sub(rows);
+            cursor_controller.cursor_y = cursor_controller.cursor_y.saturating_sub(rows);
+        }
+        cursor_controller.cursor_x = if cursor_controller.cursor_y < number_of_rows {
+            editor_rows
+                .get_editor_row(cursor_controller.cursor_y)
+                .grapheme_index(column)
+        } else {
+            0
+        };
+        cursor_controller.keep_column(column, editor_rows);
+    }
+
+    pub(crate) fn scroll_cursor_to(&mut self, place: char) {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let editor_rows = &self.buffer.editor_rows;
+        let screen_rows = cmp::max(cursor_controller.screen_rows, 1);
+        let height = |y: usize| {
+            if editor_rows.soft_wrap && y < editor_rows.number_of_rows() {
+                cursor_controller.row_height(editor_rows.get_editor_row(y))
+            } else {
+                1
+            }
+        };
+        let mut row_offset = cursor_controller.cursor_y;
+        // The screen lines to fill with the rows above the cursor line.
+        let room = screen_rows.saturating_sub(height(row_offset));
+        let above = match place {
+            't' => 0,
+            'z' => room / 2,
+            _ => room,
+        };
+        let mut used = 0;
+        while row_offset > 0 && used + height(row_offset - 1) <= above {
+            row_offset -= 1;
+            used += height(row_offset);
+        }
+        self.buffer.cursor_controller.row_offset = row_offset;
+    }
+
+    fn move_cursor(&mut self, direction: KeyCode) {
+        self.buffer
+            .cursor_controller
+            .move_cursor(direction, &self.buffer.editor_rows);
+    }
+
+    fn find_callback(output: &mut Output, keyword: &str, key_event: KeyEvent) {
+        match key_event {
+            KeyEvent {
+                code: KeyCode::Esc | KeyCode::Enter,
+                ..
+            } => {
+                output.search_index.reset();
+                output.search_index.highlight = None;
+                return;
+            }
+            KeyEvent {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::ALT,
+            } => {
+                output.search_index.regex = !output.search_index.regex;
+                output.search_index.reset();
+            }
+            KeyEvent {
+                code: KeyCode::Up | KeyCode::Left,
+                ..
+            } => output.search_index.direction = SearchDirection::Backward,
+            KeyEvent {
+                code: KeyCode::Down | KeyCode::Right,
+                ..
+            } => output.search_index.direction = SearchDirection::Forward,
+            _ => output.search_index.reset(),
+        }
+        let pattern = match Pattern::new(keyword, output.search_index.regex) {
+            Some(pattern) => pattern,
+            None => {
+                output.search_index.highlight = None;
+                return;
+            }
+        };
+        let found = match output.search_index.last_match {
+            None => search::find(&output.buffer.editor_rows, &pattern, 0),
+            Some(last_match) => search::find_next(
+                &output.buffer.editor_rows,
+                &pattern,
+                last_match,
+                output.search_index.direction,
+            ),
+        };
+        if let Some(position) = found {
+            output.search_index.last_match = Some(position);
+            output.jump_to_match(position);
+        }
+        output.search_index.highlight = Some(pattern);
+    }
+
+    fn jump_to_match(&mut self, (x, y): (usize, usize)) {
+        self.buffer.cursor_controller.cursor_y = y;
+        self.buffer.cursor_controller.cursor_x = x;
+        self.buffer.cursor_controller.row_offset = self.buffer.editor_rows.number_of_rows();
+    }
+
+    fn find_again(&mut self, direction: SearchDirection) {
+        self.buffer.editor_rows.load_all();
+        let pattern = match &self.search_index.pattern {
+            Some(pattern) => pattern,
+            None => {
+                self.status_message.set_message("No previous search".into());
+                return;
+            }
+        };
+        let cursor = (
+            self.buffer.cursor_controller.cursor_x,
+            self.buffer.cursor_controller.cursor_y,
+        );
+        match search::find_next(&self.buffer.editor_rows, pattern, cursor, direction) {
+            Some(position) => {
+                self.record_jump(cursor);
+                self.jump_to_match(position)
+            }
+            None => self.status_message.set_message("Pattern not found".into()),
+        }
+    }
+
+    fn find(&mut self) -> crossterm::Result<()> {
+        self.buffer.editor_rows.load_all();
+        let cursor_controller = self.buffer.cursor_controller;
+        match prompt!(
+            self,
+            "Search: {} (Use ESC / Arrows / Enter, Alt-R = Toggle regex)",
+            callback = Output::find_callback
+        ) {
+            None => self.buffer.cursor_controller = cursor_controller,
+            Some(keyword) => {
+                self.record_jump((cursor_controller.cursor_x, cursor_controller.cursor_y));
+                self.search_index.pattern = Pattern::new(&keyword, self.search_index.regex)
+            }
+        }
+        Ok(())
+    }
+
+    fn prompt_replacement(&mut self) -> crossterm::Result<Option<(Pattern, String)>> {
+        self.buffer.editor_rows.load_all();
+        let keyword = match prompt!(self, "Replace: {} (Use ESC to cancel)") {
+            Some(keyword) => keyword,
+            None => return Ok(None),
+        };
+        let pattern = match Pattern::new(&keyword, self.search_index.regex) {
+            Some(pattern) => pattern,
+            None => {
+                self.status_message
+                    .set_message(format!("Invalid pattern: {}", keyword));
+                return Ok(None);
+            }
+        };
+        Ok(prompt!(self, "Replace with: {} (Use ESC to cancel)")
+            .map(|replacement| (pattern, replacement)))
+    }
+
+    fn replace_all(&mut self) -> crossterm::Result<()> {
+        if self.reject_edit() {
+            return Ok(());
+        }
+        if let Some((pattern, replacement)) = self.prompt_replacement()? {
+            let replaced =
+                search::replace_all(&mut self.buffer.editor_rows, &pattern, &replacement);
+            if replaced > 0 {
+                self.buffer.dirty += 1;
+                let row_len = self
+                    .buffer
+                    .editor_rows
+                    .row_contents
+                    .get(self.buffer.cursor_controller.cursor_y)
+                    .map_or(0, |row| row.row_content.len());
+                self.buffer.cursor_controller.cursor_x =
+                    cmp::min(self.buffer.cursor_controller.cursor_x, row_len);
+            }
+            self.status_message
+                .set_message(format!("Replaced {} occurrence(s)", replaced));
+        }
+        Ok(())
+    }
+
+    fn replace(&mut self) -> crossterm::Result<()> {
+        if self.reject_edit() {
+            return Ok(());
+        }
+        let (pattern, replacement) = match self.prompt_replacement()? {
+            Some(replace) => replace,
+            None => return Ok(()),
+        };
+        let cursor_controller = self.buffer.cursor_controller;
+        let (mut x, mut y) = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let mut replace_all = false;
+        let mut replaced = 0;
+        let mut last_replacement = None;
+        // Past the end, going round from the top up to where the cursor was.
+        let mut wrapped = None;
+        self.search_index.highlight = Some(pattern.clone());
+        loop {
+            if y >= self.buffer.editor_rows.number_of_rows() && wrapped.is_none() {
+                wrapped = Some((cursor_controller.cursor_x, cursor_controller.cursor_y));
+                x = 0;
+                y = 0;
+            }
+            if wrapped.map_or(false, |(_, stop_y)| y > stop_y)
+                || y >= self.buffer.editor_rows.number_of_rows()
+            {
+                break;
+            }
+            let row = self.buffer.editor_rows.get_row(y);
+            let found = if x <= row.len() {
+                pattern.find_at(row, x)
+            } else {
+                None
+            };
+            let (start, end) = match found {
+                Some(found) => found,
+                None => {
+                    x = 0;
+                    y += 1;
+                    continue;
+                }
+            };
+            if wrapped.map_or(false, |(stop_x, stop_y)| (y, start) >= (stop_y, stop_x)) {
+                break;
+            }
+            let step = if start == end {
+                row[end..].chars().next().map_or(1, char::len_utf8)
+            } else {
+                0
+            };
+            if !replace_all {
+                self.jump_to_match((start, y));
+                self.status_message
+                    .set_message("Replace this match? (y/n/a/q)".into());
+                self.refresh_screen()?;
+                match Reader.read_key()?.code {
+                    KeyCode::Char('y') => {}
+                    KeyCode::Char('a') => replace_all = true,
+                    KeyCode::Char('n') => {
+                        x = end + step;
+                        continue;
+                    }
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    _ => continue,
+                }
+            }
+            let text = pattern.expand(self.buffer.editor_rows.get_row(y), start, &replacement);
+            self.buffer.editor_rows.replace_range(y, start, end, &text);
+            if let Some((stop_x, stop_y)) = &mut wrapped {
+                if *stop_y == y {
+                    *stop_x = (*stop_x + text.len()).saturating_sub(end - start);
+                }
+            }
+            x = start + text.len() + step;
+            last_replacement = Some((start, y));
+            replaced += 1;
+            self.buffer.dirty += 1;
+        }
+        self.search_index.highlight = None;
+        self.buffer.cursor_controller = cursor_controller;
+        if let Some(position) = last_replacement {
+            self.jump_to_match(position)
+        }
+        self.status_message
+            .set_message(format!("Replaced {} occurrence(s)", replaced));
+        Ok(())
+    }
+
+    fn reject_edit(&mut self) -> bool {
+        if self.buffer.editor_rows.read_only {
+            self.status_message
+                .set_message("Buffer is read-only (:set noreadonly to edit)".into());
+        }
+        self.buffer.editor_rows.read_only
+    }
+
+    fn undo(&mut self) {
+        if self.reject_edit() {
+            return;
+        }
+        match self.buffer.editor_rows.undo() {
+            Some(cursor) => self.restore_cursor(cursor),
+            None => self.status_message.set_message("Nothing to undo".into()),
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.reject_edit() {
+            return;
+        }
+        match self.buffer.editor_rows.redo() {
+            Some(cursor) => self.restore_cursor(cursor),
+            None => self.status_message.set_message("Nothing to redo".into()),
+        }
+    }
+
+    fn restore_cursor(&mut self, (x, y): (usize, usize)) {
+        self.clear_selection();
+        self.buffer.cursor_controller.cursor_x = x;
+        self.buffer.cursor_controller.cursor_y = y;
+        self.buffer.dirty += 1;
+    }
 
-                Ok(contents.as_bytes().len())
+    fn save(&mut self) -> crossterm::Result<()> {
+        if self.reject_edit() {
+            return Ok(());
+        }
+        if matches!(self.buffer.editor_rows.filename, None) {
+            match prompt!(self, "Save as: {}") {
+                Some(file) => self.buffer.editor_rows.set_filename(file.into()),
+                None => {
+                    self.status_message.set_message("Save aborted".into());
+                    return Ok(());
+                }
             }
         }
+        match self.write_buffer() {
+            Ok(len) => self.saved(len),
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => return self.save_denied(),
+            Err(err) => self
+                .status_message
+                .set_message(format!("Can't save! I/O error: {}", err)),
+        }
+        Ok(())
     }
 
-    fn join_adjacent_rows(&mut self, at: usize) {
-        let current_row = self.row_contents.remove(at);
-        let previous_row = self.get_editor_row_mut(at - 1);
-        previous_row.row_content.push_str(&current_row.row_content);
-        Self::render_row(previous_row);
+    fn write_buffer(&mut self) -> io::Result<usize> {
+        self.write_buffer_with(atomic::write, true)
     }
-}
-
-struct Output {
-    win_size: (usize, usize),
-    editor_rows: EditorRows,
-    editor_contents: EditorContents,
-    cursor_controller: CursorController,
-    status_message: StatusMessage,
-    dirty: u64,
-}
-
-impl Output {
-    fn new() -> Self {
-        let win_size = terminal::size()
-            .map(|(x, y)| (x as usize, y as usize - 2))
-            .unwrap();
 
-        Self {
-            win_size,
-            editor_rows: EditorRows::new(),
-            editor_contents: EditorContents::new(),
-            cursor_controller: CursorController::new(win_size),
-            status_message: StatusMessage::new("HELP: CTRL-S = Save | CTRL-Q = Quit".into()),
-            dirty: 0,
+    fn write_buffer_with(
+        &mut self,
+        write: fn(&Path, &[u8]) -> io::Result<()>,
+        backup: bool,
+    ) -> io::Result<usize> {
+        if self.buffer.editor_rows.changed_on_disk() {
+            return Err(io::Error::new(ErrorKind::Other, "the file changed on disk"));
         }
+        if self.config.trim_trailing_whitespace {
+            self.trim_trailing_whitespace();
+        }
+        self.buffer.editor_rows.save_with(write, backup)
     }
 
-    fn clear_screen() -> crossterm::Result<()> {
-        execute!(stdout(), terminal::Clear(ClearType::All))?;
-        execute!(stdout(), cursor::MoveTo(0, 0))
+    fn saved(&mut self, len: usize) {
+        self.status_message
+            .set_message(format!("{} bytes written to disk", len));
+        self.buffer.dirty = 0;
     }
 
-    fn draw_rows(&mut self) {
-        let screen_rows = self.win_size.1;
-        let screen_columns = self.win_size.0;
-        for i in 0..screen_rows {
-            let file_row = i + self.cursor_controller.row_offset;
-            if file_row >= self.editor_rows.number_of_rows() {
-                if self.editor_rows.number_of_rows() == 0 && i == screen_rows / 3 {
-                    let mut welcome = format!("Pound Editor --- Version {}", VERSION);
-                    if welcome.len() > screen_columns {
-                        welcome.truncate(screen_columns)
-                    }
-                    let mut padding = (screen_columns - welcome.len()) / 2;
-                    if padding != 0 {
-                        self.editor_contents.push('~');
-                        padding -= 1
-                    }
-                    (0..padding).for_each(|_| self.editor_contents.push(' '));
-                    self.editor_contents.push_str(&welcome);
-                } else {
-                    self.editor_contents.push('~');
-                }
-            } else {
-                let row = self.editor_rows.get_render(file_row);
-                let column_offset = self.cursor_controller.column_offset;
-                let len = cmp::min(row.len().saturating_sub(column_offset), screen_columns);
-                let start = if len == 0 { 0 } else { column_offset };
-                self.editor_contents.push_str(&row[start..start + len]);
-            }
-            queue!(
-                self.editor_contents,
-                terminal::Clear(ClearType::UntilNewLine)
-            )
-            .unwrap();
-            self.editor_contents.push_str("\r\n");
+    fn goto_line(&mut self, number: usize) {
+        self.buffer.editor_rows.load_rows(number);
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        cursor_controller.cursor_y = cmp::min(
+            number.saturating_sub(1),
+            self.buffer.editor_rows.number_of_rows(),
+        );
+        cursor_controller.cursor_x = 0;
+    }
+
+    fn goto_position(&mut self, line: usize, column: usize) {
+        self.goto_line(line);
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let y = cursor_controller.cursor_y;
+        if y < self.buffer.editor_rows.number_of_rows() {
+            let row = self.buffer.editor_rows.get_row(y);
+            cursor_controller.cursor_x = row
+                .char_indices()
+                .nth(column.saturating_sub(1))
+                .map_or(row.len(), |(x, _)| x);
         }
+        cursor_controller.row_offset = y.saturating_sub(cursor_controller.screen_rows / 2);
     }
 
-    fn draw_status_bar(&mut self) {
-        self.editor_contents
-            .push_str(&style::Attribute::Reverse.to_string());
-
-        let info = format!(
-            "{} {} -- {} lines",
-            self.editor_rows
-                .filename
-                .as_ref()
-                .and_then(|path| path.file_name())
-                .and_then(|name| name.to_str())
-                .unwrap_or("[No name]"),
-            if self.dirty > 0 { "(modified)" } else { "" },
-            self.editor_rows.number_of_rows()
-        );
-        let info_len = cmp::min(info.len(), self.win_size.0);
+    fn jump_to_line(&mut self, line: &str) -> bool {
+        let number = if line == "$" {
+            self.buffer.editor_rows.load_all();
+            self.buffer.editor_rows.number_of_rows()
+        } else if let Some(percent) = line.strip_suffix('%') {
+            let percent = match percent.parse::<usize>() {
+                Ok(percent) => cmp::min(percent, 100),
+                Err(_) => return false,
+            };
+            self.buffer.editor_rows.load_all();
+            (self.buffer.editor_rows.number_of_rows() * percent + 99) / 100
+        } else {
+            match line.parse() {
+                Ok(number) => number,
+                Err(_) => return false,
+            }
+        };
+        let cursor_controller = &self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        self.record_jump(cursor);
+        self.goto_position(cmp::max(number, 1), 1);
+        true
+    }
 
-        let line_info = format!(
-            "{}/{}",
-            self.cursor_controller.cursor_y + 1,
-            self.editor_rows.number_of_rows(),
-        );
+    fn is_dirty(&self) -> bool {
+        self.buffer.dirty > 0 || self.buffers.iter().any(|buffer| buffer.dirty > 0)
+    }
 
-        self.editor_contents.push_str(&info[..info_len]);
-        for i in info_len..self.win_size.0 {
-            if self.win_size.0 - i == line_info.len() {
-                self.editor_contents.push_str(&line_info);
-                break;
+    fn visit(&mut self, file: &Path) -> bool {
+        let wanted = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        let edits = |buffer: &Buffer| {
+            buffer.editor_rows.filename.as_ref().map_or(false, |name| {
+                fs::canonicalize(name).map_or(false, |name| name == wanted)
+            })
+        };
+        if edits(&self.buffer) {
+            return true;
+        }
+        if let Some(slot) = self.buffers.iter().position(edits) {
+            self.switch_buffer(if slot < self.current_buffer {
+                slot
             } else {
-                self.editor_contents.push(' ');
-            }
+                slot + 1
+            });
+            return true;
         }
-
-        self.editor_contents
-            .push_str(&style::Attribute::Reset.to_string());
-        self.editor_contents.push_str("\r\n");
+        self.open_buffer(file.into());
+        edits(&self.buffer)
     }
 
-    fn draw_message_bar(&mut self) {
-        queue!(
-            self.editor_contents,
-            terminal::Clear(ClearType::UntilNewLine),
-        )
-        .unwrap();
-        if let Some(msg) = self.status_message.message() {
-            self.editor_contents
-                .push_str(&msg[..cmp::min(self.win_size.0, msg.len())]);
+    fn open_buffer(&mut self, file: PathBuf) {
+        welcome::remember(&file).ok();
+        match EditorRows::from_file(file, &self.config) {
+            Ok(editor_rows) => {
+                self.buffers
+                    .push(Buffer::new(editor_rows, self.win_size, &self.config));
+                self.switch_buffer(self.buffers.len());
+            }
+            Err(err) => self
+                .status_message
+                .set_message(format!("Can't open file: {}", err)),
         }
     }
 
-    fn refresh_screen(&mut self) -> crossterm::Result<()> {
-        self.cursor_controller.scroll(&self.editor_rows);
-        queue!(self.editor_contents, cursor::Hide, cursor::MoveTo(0, 0))?;
+    fn switch_buffer(&mut self, index: usize) {
+        let slot = match self.buffer_slot(index) {
+            Some(slot) if slot < self.buffers.len() => slot,
+            _ => return,
+        };
+        let next = self.buffers.remove(slot);
+        let previous = mem::replace(&mut self.buffer, next);
+        let previous_index = mem::replace(&mut self.current_buffer, index);
+        self.buffers
+            .insert(self.buffer_slot(previous_index).unwrap(), previous);
+    }
 
-        self.draw_rows();
-        self.draw_status_bar();
-        self.draw_message_bar();
+    fn step_argument(&mut self, forward: bool) {
+        let argument = if forward {
+            Some(self.argument + 1).filter(|&argument| argument < self.arguments)
+        } else {
+            self.argument.checked_sub(1)
+        };
+        match argument {
+            Some(argument) => {
+                self.argument = argument;
+                self.switch_buffer(argument);
+                self.status_message.set_message(format!(
+                    "{} ({} of {})",
+                    self.buffer.name(),
+                    argument + 1,
+                    self.arguments
+                ));
+            }
+            None if forward => self
+                .status_message
+                .set_message("Already at the last file".into()),
+            None => self
+                .status_message
+                .set_message("Already at the first file".into()),
+        }
+    }
 
-        let cursor_x =
-            (self.cursor_controller.render_x - self.cursor_controller.column_offset) as u16;
-        let cursor_y = (self.cursor_controller.cursor_y - self.cursor_controller.row_offset) as u16;
-        queue!(
-            self.editor_contents,
-            cursor::MoveTo(cursor_x, cursor_y),
-            cursor::Show
-        )?;
+    fn cycle_buffer(&mut self, step: usize) {
+        let count = self.buffers.len() + 1;
+        self.switch_buffer((self.current_buffer + step) % count);
+    }
 
-        self.editor_contents.flush()
+    fn list_buffers(&mut self) {
+        let mut names: Vec<String> = self
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| {
+                let index = if index < self.current_buffer {
+                    index
+                } else {
+                    index + 1
+                };
+                format!(
+                    "{}:{}{}",
+                    index + 1,
+                    buffer.name(),
+                    if buffer.dirty > 0 { "+" } else { "" }
+                )
+            })
+            .collect();
+        names.insert(
+            self.current_buffer,
+            format!(
+                "[{}:{}{}]",
+                self.current_buffer + 1,
+                self.buffer.name(),
+                if self.buffer.dirty > 0 { "+" } else { "" }
+            ),
+        );
+        self.status_message.set_message(names.join(" "));
     }
 
-    fn move_cursor(&mut self, direction: KeyCode) {
-        self.cursor_controller
-            .move_cursor(direction, &self.editor_rows);
+    fn toggle_line_ending(&mut self) {
+        if self.reject_edit() {
+            return;
+        }
+        let line_ending = self.buffer.editor_rows.line_ending.toggle();
+        self.buffer.editor_rows.line_ending = line_ending;
+        self.buffer.editor_rows.mixed_line_endings = false;
+        self.status_message.set_message(format!(
+            "Line endings set to {}",
+            line_ending.name().to_uppercase()
+        ));
+        self.buffer.dirty += 1;
     }
 
     fn insert_char(&mut self, ch: char) {
-        if self.cursor_controller.cursor_y == self.editor_rows.number_of_rows() {
-            self.editor_rows
-                .insert_row(self.editor_rows.number_of_rows(), String::new());
-            self.dirty += 1;
+        if self.reject_edit() {
+            return;
         }
-        self.editor_rows
-            .get_editor_row_mut(self.cursor_controller.cursor_y)
-            .insert_char(self.cursor_controller.cursor_x, ch);
-        self.cursor_controller.cursor_x += 1;
-        self.dirty += 1;
+        self.buffer.editor_rows.insert_char(
+            self.buffer.cursor_controller.cursor_x,
+            self.buffer.cursor_controller.cursor_y,
+            ch,
+        );
+        self.buffer.cursor_controller.cursor_x += ch.len_utf8();
+        self.buffer.dirty += 1;
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        if self.reject_edit() {
+            return;
+        }
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let (x, y) = self.buffer.editor_rows.insert_text(
+            cursor_controller.cursor_x,
+            cursor_controller.cursor_y,
+            text,
+        );
+        cursor_controller.cursor_x = x;
+        cursor_controller.cursor_y = y;
+        self.buffer.dirty += 1;
     }
 
     fn delete_char(&mut self) {
-        if self.cursor_controller.cursor_y == self.editor_rows.number_of_rows() {
+        if self.reject_edit() {
             return;
         }
-        if self.cursor_controller.cursor_y == 0 && self.cursor_controller.cursor_x == 0 {
+        if self.buffer.cursor_controller.cursor_y == self.buffer.editor_rows.number_of_rows() {
             return;
         }
-        let row = self
-            .editor_rows
-            .get_editor_row_mut(self.cursor_controller.cursor_y);
-        if self.cursor_controller.cursor_x > 0 {
-            row.delete_char(self.cursor_controller.cursor_x - 1);
-            self.cursor_controller.cursor_x -= 1;
+        if self.buffer.cursor_controller.cursor_y == 0
+            && self.buffer.cursor_controller.cursor_x == 0
+        {
+            return;
+        }
+        if self.buffer.cursor_controller.cursor_x > 0 {
+            let at_x = self
+                .buffer
+                .editor_rows
+                .get_editor_row(self.buffer.cursor_controller.cursor_y)
+                .previous_boundary(self.buffer.cursor_controller.cursor_x);
+            self.buffer
+                .editor_rows
+                .delete_char(at_x, self.buffer.cursor_controller.cursor_y);
+            self.buffer.cursor_controller.cursor_x = at_x;
         } else {
             let previous_row_content = self
+                .buffer
                 .editor_rows
-                .get_row(self.cursor_controller.cursor_y - 1);
-            self.cursor_controller.cursor_x = previous_row_content.len();
-            self.editor_rows
-                .join_adjacent_rows(self.cursor_controller.cursor_y);
-            self.cursor_controller.cursor_y -= 1;
+                .get_row(self.buffer.cursor_controller.cursor_y - 1);
+            self.buffer.cursor_controller.cursor_x = previous_row_content.len();
+            self.buffer
+                .editor_rows
+                .join_adjacent_rows(self.buffer.cursor_controller.cursor_y);
+            self.buffer.cursor_controller.cursor_y -= 1;
         }
-        self.dirty += 1;
+        self.buffer.dirty += 1;
     }
 
     fn insert_newline(&mut self) {
-        if self.cursor_controller.cursor_x == 0 {
-            self.editor_rows
-                .insert_row(self.cursor_controller.cursor_y, String::new())
-        } else {
-            let current_row = self
+        if self.reject_edit() {
+            return;
+        }
+        if self.buffer.cursor_controller.cursor_x == 0 {
+            self.buffer
                 .editor_rows
-                .get_editor_row_mut(self.cursor_controller.cursor_y);
-            let new_row_content = current_row.row_content[self.cursor_controller.cursor_x..].into();
-            current_row
-                .row_content
-                .truncate(self.cursor_controller.cursor_x);
-            EditorRows::render_row(current_row);
-            self.editor_rows
-                .insert_row(self.cursor_controller.cursor_y + 1, new_row_content);
+                .insert_row(self.buffer.cursor_controller.cursor_y, String::new())
+        } else if self.config.auto_indent {
+            self.split_indented();
+            self.buffer.dirty += 1;
+            return;
+        } else {
+            self.buffer.editor_rows.split_row(
+                self.buffer.cursor_controller.cursor_x,
+                self.buffer.cursor_controller.cursor_y,
+            );
         }
-        self.cursor_controller.cursor_x = 0;
-        self.cursor_controller.cursor_y += 1;
+        self.buffer.cursor_controller.cursor_x = 0;
+        self.buffer.cursor_controller.cursor_y += 1;
+
+        self.buffer.dirty += 1;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LineNumbers {
+    Off,
+    Absolute,
+    Relative,
+}
 
-        self.dirty += 1;
+impl LineNumbers {
+    fn next(self) -> Self {
+        match self {
+            LineNumbers::Off => LineNumbers::Absolute,
+            LineNumbers::Absolute => LineNumbers::Relative,
+            LineNumbers::Relative => LineNumbers::Off,
+        }
     }
 }
 
+#[derive(Clone, Copy)]
 struct CursorController {
     cursor_x: usize,
     cursor_y: usize,
@@ -510,10 +2711,18 @@ struct CursorController {
     row_offset: usize,
     column_offset: usize,
     render_x: usize,
+    line_numbers: LineNumbers,
+    gutter_width: usize,
+    scroll_margin: usize,
+    anchor: Option<(usize, usize)>,
+    /// The screen columns of the anchor and the cursor when selecting a block.
+    block: Option<(usize, usize)>,
+    /// The column, position and `changes` the last move up or down left the cursor at.
+    preferred_x: Option<(usize, usize, usize, u64)>,
 }
 
 impl CursorController {
-    fn new(win_size: (usize, usize)) -> CursorController {
+    fn new(win_size: (usize, usize), config: &Config) -> CursorController {
         Self {
             cursor_x: 0,
             cursor_y: 0,
@@ -522,19 +2731,28 @@ impl CursorController {
             row_offset: 0,
             column_offset: 0,
             render_x: 0,
+            line_numbers: config.line_numbers,
+            gutter_width: 0,
+            scroll_margin: config.scroll_margin,
+            anchor: None,
+            block: None,
+            preferred_x: None,
         }
     }
 
     fn move_cursor(&mut self, direction: KeyCode, editor_rows: &EditorRows) {
         let numbers_of_rows = editor_rows.number_of_rows();
+        let column = self.preferred_column(editor_rows);
         match direction {
             KeyCode::Up => {
                 self.cursor_y = self.cursor_y.saturating_sub(1);
             }
             KeyCode::Left => {
                 if self.cursor_x != 0 {
-                    self.cursor_x -= 1;
-                } else {
+                    self.cursor_x = editor_rows
+                        .get_editor_row(self.cursor_y)
+                        .previous_boundary(self.cursor_x);
+                } else if self.cursor_y > 0 {
                     self.cursor_y -= 1;
                     self.cursor_x = editor_rows.get_row(self.cursor_y).len();
                 }
@@ -547,7 +2765,11 @@ impl CursorController {
             KeyCode::Right => {
                 if self.cursor_y < numbers_of_rows {
                     match self.cursor_x.cmp(&editor_rows.get_row(self.cursor_y).len()) {
-                        Ordering::Less => self.cursor_x += 1,
+                        Ordering::Less => {
+                            self.cursor_x = editor_rows
+                                .get_editor_row(self.cursor_y)
+                                .next_boundary(self.cursor_x)
+                        }
                         Ordering::Equal => {
                             self.cursor_y += 1;
                             self.cursor_x = 0;
@@ -556,8 +2778,19 @@ impl CursorController {
                     }
                 }
             }
+            // To the first non-blank, or to the start when already there.
             KeyCode::Home => {
-                self.cursor_x = 0;
+                let row = if self.cursor_y < numbers_of_rows {
+                    editor_rows.get_row(self.cursor_y)
+                } else {
+                    ""
+                };
+                let indentation = row.len() - row.trim_start().len();
+                self.cursor_x = if self.cursor_x == indentation {
+                    0
+                } else {
+                    indentation
+                };
             }
             KeyCode::End => {
                 if self.cursor_y < numbers_of_rows {
@@ -567,12 +2800,68 @@ impl CursorController {
             _ => unimplemented!(),
         }
 
+        if matches!(direction, KeyCode::Up | KeyCode::Down) && self.cursor_y < numbers_of_rows {
+            self.cursor_x = editor_rows
+                .get_editor_row(self.cursor_y)
+                .grapheme_index(column);
+        }
         let row_len = if self.cursor_y < numbers_of_rows {
             editor_rows.get_row(self.cursor_y).len()
         } else {
             0
         };
         self.cursor_x = cmp::min(self.cursor_x, row_len);
+        match direction {
+            KeyCode::Up | KeyCode::Down => self.keep_column(column, editor_rows),
+            // Past the end of any row, so moving on stays at the ends.
+            KeyCode::End => self.keep_column(usize::MAX, editor_rows),
+            _ => self.preferred_x = None,
+        }
+    }
+
+    fn preferred_column(&self, editor_rows: &EditorRows) -> usize {
+        match self.preferred_x {
+            Some((column, x, y, changes))
+                if (x, y, changes) == (self.cursor_x, self.cursor_y, editor_rows.changes) =>
+            {
+                column
+            }
+            _ => self.column(editor_rows),
+        }
+    }
+
+    fn keep_column(&mut self, column: usize, editor_rows: &EditorRows) {
+        self.preferred_x = Some((column, self.cursor_x, self.cursor_y, editor_rows.changes));
+    }
+
+    fn clamp(&mut self, editor_rows: &EditorRows) {
+        self.cursor_y = cmp::min(self.cursor_y, editor_rows.number_of_rows());
+        let row = if self.cursor_y < editor_rows.number_of_rows() {
+            editor_rows.get_row(self.cursor_y)
+        } else {
+            ""
+        };
+        self.cursor_x = cmp::min(self.cursor_x, row.len());
+        while !row.is_char_boundary(self.cursor_x) {
+            self.cursor_x -= 1;
+        }
+    }
+
+    fn column(&self, editor_rows: &EditorRows) -> usize {
+        if self.cursor_y < editor_rows.number_of_rows() {
+            editor_rows
+                .get_editor_row(self.cursor_y)
+                .grapheme_column(self.cursor_x)
+        } else {
+            0
+        }
+    }
+
+    fn is_at_end(&self, editor_rows: &EditorRows) -> bool {
+        let numbers_of_rows = editor_rows.number_of_rows();
+        self.cursor_y >= numbers_of_rows
+            || (self.cursor_y == numbers_of_rows - 1
+                && self.cursor_x == editor_rows.get_row(self.cursor_y).len())
     }
 
     fn scroll(&mut self, editor_rows: &EditorRows) {
@@ -580,33 +2869,135 @@ impl CursorController {
         if self.cursor_y < editor_rows.number_of_rows() {
             self.render_x = self.get_render_x(editor_rows.get_editor_row(self.cursor_y))
         }
-        self.row_offset = cmp::min(self.row_offset, self.cursor_y);
-        if self.cursor_y >= self.row_offset + self.screen_rows {
-            self.row_offset = self.cursor_y - self.screen_rows + 1;
+        self.gutter_width = match self.line_numbers {
+            LineNumbers::Off => 0,
+            _ => cmp::max(editor_rows.number_of_rows().to_string().len(), 3) + 1,
+        } + editor_rows.has_signs() as usize;
+        // A window squeezed down to its status line still keeps its cursor.
+        let screen_rows = cmp::max(self.screen_rows, 1);
+        let margin = cmp::min(self.scroll_margin, screen_rows.saturating_sub(1) / 2);
+        let last_row = cmp::max(editor_rows.number_of_rows(), self.cursor_y);
+        self.row_offset = cmp::min(self.row_offset, self.cursor_y.saturating_sub(margin));
+        let bottom = cmp::min(self.cursor_y + margin, last_row);
+        if bottom >= self.row_offset + screen_rows {
+            self.row_offset = bottom - screen_rows + 1;
+        }
+        if editor_rows.soft_wrap {
+            self.column_offset = 0;
+            let bottom_margin = bottom - self.cursor_y;
+            while self.row_offset < self.cursor_y
+                && self.wrapped_cursor_line(editor_rows) + bottom_margin >= screen_rows
+            {
+                self.row_offset += 1;
+            }
+            return;
         }
+        let text_columns = self.text_columns();
+        let cursor_width = if self.cursor_y < editor_rows.number_of_rows() {
+            editor_rows.get_row(self.cursor_y)[self.cursor_x..]
+                .chars()
+                .next()
+                .and_then(|c| c.width())
+                .unwrap_or(1)
+        } else {
+            1
+        };
+        let cursor_end = self.render_x + cmp::max(cursor_width, 1);
         self.column_offset = cmp::min(self.column_offset, self.render_x);
-        if self.render_x >= self.column_offset + self.screen_columns {
-            self.column_offset = self.render_x - self.screen_columns + 1;
+        if cursor_end > self.column_offset + text_columns {
+            self.column_offset = cursor_end.saturating_sub(text_columns);
+        }
+    }
+
+    fn text_columns(&self) -> usize {
+        cmp::max(self.screen_columns.saturating_sub(self.gutter_width), 1)
+    }
+
+    fn row_height(&self, row: &Row) -> usize {
+        row.render.width() / self.text_columns() + 1
+    }
+
+    fn wrapped_cursor_line(&self, editor_rows: &EditorRows) -> usize {
+        (self.row_offset..self.cursor_y)
+            .map(|y| self.row_height(editor_rows.get_editor_row(y)))
+            .sum::<usize>()
+            + self.render_x / self.text_columns()
+    }
+
+    fn screen_position(&self, editor_rows: &EditorRows) -> (usize, usize) {
+        if editor_rows.soft_wrap {
+            (
+                self.gutter_width + self.render_x % self.text_columns(),
+                self.wrapped_cursor_line(editor_rows),
+            )
+        } else {
+            (
+                self.gutter_width + self.render_x - self.column_offset,
+                self.cursor_y - self.row_offset,
+            )
+        }
+    }
+
+    fn screen_lines(&self, editor_rows: &EditorRows) -> Vec<(usize, usize, usize)> {
+        let mut lines = Vec::with_capacity(self.screen_rows);
+        let mut file_row = self.row_offset;
+        while lines.len() < self.screen_rows {
+            if editor_rows.soft_wrap && file_row < editor_rows.number_of_rows() {
+                let text_columns = self.text_columns();
+                (0..self.row_height(editor_rows.get_editor_row(file_row)))
+                    .take(self.screen_rows - lines.len())
+                    .for_each(|segment| lines.push((file_row, segment, segment * text_columns)));
+            } else {
+                lines.push((file_row, 0, self.column_offset));
+            }
+            file_row += 1;
+        }
+        lines
+    }
+
+    fn selection_in(&self, file_row: usize, row: &Row, inclusive: bool) -> Option<(usize, usize)> {
+        let anchor = self.anchor?;
+        let cursor = (self.cursor_x, self.cursor_y);
+        let (start, end) = if (anchor.1, anchor.0) <= (cursor.1, cursor.0) {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+        if file_row < start.1 || file_row > end.1 {
+            return None;
         }
+        if let Some((left, right)) = self.block_columns() {
+            return Some(row.column_range(left, right, inclusive));
+        }
+        // The anchor stays put while its row is edited from elsewhere.
+        let len = row.row_content.len();
+        let from = if file_row == start.1 {
+            row.clamp_x(start.0)
+        } else {
+            0
+        };
+        let to = if file_row != end.1 {
+            len
+        } else if inclusive && end.0 < len {
+            row.next_boundary(row.clamp_x(end.0))
+        } else {
+            row.clamp_x(end.0)
+        };
+        Some((from, to))
     }
 
     fn get_render_x(&self, row: &Row) -> usize {
-        row.row_content[..self.cursor_x]
-            .chars()
-            .fold(0, |render_x, c| {
-                if c == '\t' {
-                    render_x + (TAB_STOP - 1) - (render_x % TAB_STOP) + 1
-                } else {
-                    render_x + 1
-                }
-            })
+        row.render_x(self.cursor_x)
     }
 }
 
-#[derive(Default)]
 struct Row {
     row_content: String,
     render: String,
+    highlight: Vec<HighlightType>,
+    syntax_start: Option<RowState>,
+    syntax_end: RowState,
+    tab_stop: usize,
 }
 
 impl Row {
@@ -614,17 +3005,91 @@ impl Row {
         Self {
             row_content,
             render,
+            highlight: Vec::new(),
+            syntax_start: None,
+            syntax_end: RowState::Normal,
+            tab_stop: TAB_STOP,
+        }
+    }
+
+    fn render_position(&self, at: usize) -> (usize, usize) {
+        self.row_content[..at]
+            .chars()
+            .fold((0, 0), |(index, render_x), c| {
+                if c == '\t' {
+                    let spaces = self.tab_stop - render_x % self.tab_stop;
+                    (index + spaces, render_x + spaces)
+                } else {
+                    (index + 1, render_x + c.width().unwrap_or(0))
+                }
+            })
+    }
+
+    fn render_index(&self, at: usize) -> usize {
+        self.render_position(at).0
+    }
+
+    fn render_x(&self, at: usize) -> usize {
+        self.render_position(at).1
+    }
+
+    fn cursor_x(&self, render_x: usize) -> usize {
+        let mut column = 0;
+        for (index, grapheme) in self.row_content.grapheme_indices(true) {
+            column += if grapheme == "\t" {
+                self.tab_stop - column % self.tab_stop
+            } else {
+                grapheme.width()
+            };
+            if column > render_x {
+                return index;
+            }
+        }
+        self.row_content.len()
+    }
+
+    fn previous_boundary(&self, at: usize) -> usize {
+        self.row_content[..at]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    /// `x` kept inside the row and off the middle of a character.
+    fn clamp_x(&self, x: usize) -> usize {
+        let mut x = cmp::min(x, self.row_content.len());
+        while !self.row_content.is_char_boundary(x) {
+            x -= 1;
         }
+        x
+    }
+
+    fn next_boundary(&self, at: usize) -> usize {
+        at + self.row_content[at..]
+            .graphemes(true)
+            .next()
+            .map_or(0, str::len)
+    }
+
+    fn grapheme_column(&self, at: usize) -> usize {
+        self.row_content[..at].graphemes(true).count()
+    }
+
+    fn grapheme_index(&self, column: usize) -> usize {
+        self.row_content
+            .grapheme_indices(true)
+            .nth(column)
+            .map_or(self.row_content.len(), |(index, _)| index)
     }
 
     fn insert_char(&mut self, at: usize, ch: char) {
         self.row_content.insert(at, ch);
-        EditorRows::render_row(self);
+        EditorRows::render_row(self, self.tab_stop);
     }
 
     fn delete_char(&mut self, at: usize) {
         self.row_content.remove(at);
-        EditorRows::render_row(self);
+        EditorRows::render_row(self, self.tab_stop);
     }
 }
 
@@ -648,12 +3113,12 @@ impl StatusMessage {
 
     fn message(&mut self) -> Option<&String> {
         self.set_time.and_then(|time| {
-            if time.elapsed() > Duration::from_secs(5) {
+            if time.elapsed() > MESSAGE_TIMEOUT {
                 self.message = None;
                 self.set_time = None;
                 None
             } else {
-                Some(self.message.as_ref().unwrap())
+                self.message.as_ref()
             }
         })
     }
@@ -661,58 +3126,184 @@ impl StatusMessage {
 
 #[macro_export]
 macro_rules! prompt {
-	($output:expr, $($args:tt)*) => {{
-		let output:&mut Output = &mut $output;
-		let mut input = String::with_capacity(32);
-		loop {
-		    output.status_message.set_message(format!($($args)*, input));
-		    output.refresh_screen()?;
-		    match Reader.read_key()? {
-		        KeyEvent {
-		            code: KeyCode::Enter,
-		            modifiers: KeyModifiers::NONE,
-		        } => {
-		            if !input.is_empty() {
-		                output.status_message.set_message(String::new());
-		                break;
-		            }
-		        },
-		        KeyEvent {
-		            code: code @ (KeyCode::Char(..) | KeyCode::Tab),
-		            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
-		        } => input.push(match code {
-		            KeyCode::Tab => '\t',
-		            KeyCode::Char(ch) => ch,
-		            _ => unreachable!(),
-		        }),
-		        KeyEvent {
-		            code: KeyCode::Backspace | KeyCode::Delete,
-		            modifiers: KeyModifiers::NONE
-		        } => {
-		            input.pop();
-		        },
-		        KeyEvent {
-		            code: KeyCode::Esc,
-		            ..
-		        } => {
-		            output.status_message.set_message(String::new());
-		            input.clear();
-		            break;
-		        },
-		        _ => {}
-		    }
-		}
-		if input.is_empty() { None } else { Some(input)}
-	}}
+    ($output:expr, $args:tt) => {
+        prompt!(
+            $output,
+            $args,
+            callback = |_: &mut Output, _: &str, _: KeyEvent| {}
+        )
+    };
+    ($output:expr, $args:tt, callback = $callback:expr) => {{
+        let output: &mut Output = $output;
+        let callback = $callback;
+        let mut input = String::with_capacity(32);
+        loop {
+            output.status_message.set_message(format!($args, input));
+            output.refresh_screen()?;
+            let key_event = Reader.read_key()?;
+            match key_event {
+                KeyEvent {
+                    code: KeyCode::Enter,
+                    modifiers: KeyModifiers::NONE,
+                } => {
+                    if !input.is_empty() {
+                        output.status_message.set_message(String::new());
+                        callback(output, &input, key_event);
+                        break;
+                    }
+                }
+                KeyEvent {
+                    code: code @ (KeyCode::Char(..) | KeyCode::Tab),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                } => input.push(match code {
+                    KeyCode::Tab => '\t',
+                    KeyCode::Char(ch) => ch,
+                    _ => unreachable!(),
+                }),
+                KeyEvent {
+                    code: KeyCode::Backspace | KeyCode::Delete,
+                    modifiers: KeyModifiers::NONE,
+                } => {
+                    input.pop();
+                }
+                KeyEvent {
+                    code: KeyCode::Esc, ..
+                } => {
+                    output.status_message.set_message(String::new());
+                    input.clear();
+                    callback(output, &input, key_event);
+                    break;
+                }
+                _ => {}
+            }
+            callback(output, &input, key_event);
+        }
+        if input.is_empty() {
+            None
+        } else {
+            Some(input)
+        }
+    }};
 }
 
 fn main() -> crossterm::Result<()> {
+    let mut args = match cli::parse(env::args().skip(1)) {
+        Ok(Invocation::Edit(args)) => args,
+        Ok(Invocation::Help) => {
+            print!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Ok(Invocation::Version) => {
+            println!("pound {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("pound: {}\nTry 'pound --help' for more information.", err);
+            process::exit(2);
+        }
+    };
+    let config = Config::load();
+    if args.files.len() == 1 && args.files[0].is_dir() {
+        let defaults = config.as_ref().ok().cloned().unwrap_or_default();
+        match run_browser(&args.files[0], &defaults)? {
+            Some(file) => args.files[0] = file,
+            None => return Ok(()),
+        }
+    }
+    if args.files.is_empty() {
+        let defaults = config.as_ref().ok().cloned().unwrap_or_default();
+        match run_welcome(&defaults)? {
+            Choice::Open(file) => args.files.push(file),
+            Choice::New => {}
+            Choice::Quit => return Ok(()),
+        }
+    }
+    if args.view {
+        let mut viewer = Viewer::open(&args.files[0], &config.unwrap_or_default())?;
+        let _clean_up = CleanUp;
+        terminal::enable_raw_mode().expect("Could not turn Raw Mode on.");
+        return viewer.run();
+    }
+    if args.hex || (!args.read_only && args.files.len() == 1 && hex::is_binary(&args.files[0])) {
+        return run_hex_editor(&args.files[0], &config.unwrap_or_default());
+    }
+
     let _clean_up = CleanUp;
 
     terminal::enable_raw_mode().expect("Could not turn Raw Mode on.");
+    execute!(stdout(), event::EnableMouseCapture)?;
 
-    let mut editor = Editor::new();
+    let mut editor = Editor::new(config, args.files, args.read_only);
+    if let Some((line, column)) = args.position {
+        editor.output.goto_position(line, column);
+    }
+    if args.diff {
+        editor.output.start_diff();
+    }
     while editor.run()? {}
+    editor.output.remove_swaps();
 
     Ok(())
 }
+
+fn pasted_char(key: KeyEvent) -> Option<char> {
+    match key {
+        KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+        } => Some(c),
+        KeyEvent {
+            code: KeyCode::Tab,
+            modifiers: KeyModifiers::NONE,
+        } => Some('\t'),
+        KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::NONE,
+        } => Some('\n'),
+        _ => None,
+    }
+}
+
+fn run_browser(dir: &Path, config: &Config) -> crossterm::Result<Option<PathBuf>> {
+    let mut browser = Browser::open(dir, config)?;
+    let _clean_up = CleanUp;
+    terminal::enable_raw_mode().expect("Could not turn Raw Mode on.");
+    browser.run()
+}
+
+fn run_welcome(config: &Config) -> crossterm::Result<Choice> {
+    let mut welcome = Welcome::open(config);
+    let _clean_up = CleanUp;
+    terminal::enable_raw_mode().expect("Could not turn Raw Mode on.");
+    welcome.run()
+}
+
+fn run_hex_editor(path: &Path, config: &Config) -> crossterm::Result<()> {
+    let mut hex_editor = HexEditor::open(path, config)?;
+    let _clean_up = CleanUp;
+    terminal::enable_raw_mode().expect("Could not turn Raw Mode on.");
+    hex_editor.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_width_cuts_between_characters() {
+        let message = "error: ‘foo’ undeclared";
+        assert_eq!(fit_width(message, 8), "error: ‘");
+        assert_eq!(fit_width(message, 100), message);
+        assert_eq!(fit_width("Łukasz Ż", 1), "Ł");
+        assert_eq!(fit_width("日本語", 5), "日本");
+        assert_eq!(fit_width("", 0), "");
+    }
+
+    #[test]
+    fn clamp_x_keeps_to_characters() {
+        let row = Row::new("aé".into(), String::new());
+        assert_eq!(row.clamp_x(2), 1);
+        assert_eq!(row.clamp_x(3), 3);
+        assert_eq!(row.clamp_x(40), 3);
+    }
+}
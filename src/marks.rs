@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::{cmp, fs};
+
+use crossterm::event::KeyCode;
+
+use crate::backup::cache_file;
+use crate::undo::Edit;
+use crate::{Editor, Output};
+
+fn marks_file(file: &Path) -> Option<PathBuf> {
+    cache_file("marks", file, "")
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Marks {
+    positions: BTreeMap<char, (usize, usize)>,
+}
+
+impl Marks {
+    pub(crate) fn load(file: &Path) -> Self {
+        let contents = marks_file(file).and_then(|path| fs::read_to_string(path).ok());
+        let positions = contents
+            .iter()
+            .flat_map(|contents| contents.lines())
+            .filter_map(|line| {
+                let mut fields = line.split(' ');
+                let name = fields.next()?.chars().next()?;
+                let y = fields.next()?.parse().ok()?;
+                let x = fields.next()?.parse().ok()?;
+                Some((name, (x, y)))
+            })
+            .collect();
+        Self { positions }
+    }
+
+    pub(crate) fn save(&self, file: &Path) -> io::Result<()> {
+        let path = marks_file(file)
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no cache directory"))?;
+        if self.positions.is_empty() {
+            return match fs::remove_file(path) {
+                Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+        let mut contents = String::new();
+        for (name, (x, y)) in &self.positions {
+            writeln!(contents, "{} {} {}", name, y, x).unwrap();
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)
+    }
+
+    /// A mark on a deleted row goes away with it.
+    pub(crate) fn adjust(&mut self, edit: &Edit) {
+        self.positions.retain(|_, position| {
+            let (x, y) = *position;
+            *position = match *edit {
+                Edit::InsertRow { y: at, .. } if y >= at => (x, y + 1),
+                Edit::DeleteRow { y: at, .. } if y == at => return false,
+                Edit::DeleteRow { y: at, .. } if y > at => (x, y - 1),
+                Edit::SplitRow { x: at_x, y: at } if y == at && x >= at_x => (x - at_x, y + 1),
+                Edit::SplitRow { y: at, .. } if y > at => (x, y + 1),
+                Edit::JoinRows { x: at_x, y: at } if y == at + 1 => (at_x + x, at),
+                Edit::JoinRows { y: at, .. } if y > at + 1 => (x, y - 1),
+                _ => (x, y),
+            };
+            true
+        });
+    }
+}
+
+impl Output {
+    pub(crate) fn set_mark(&mut self, name: char) {
+        if !name.is_ascii_lowercase() {
+            self.status_message
+                .set_message("Marks are named a to z".into());
+            return;
+        }
+        let cursor_controller = &self.buffer.cursor_controller;
+        let position = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.marks.positions.insert(name, position);
+        if let Some(file) = &editor_rows.filename {
+            editor_rows.marks.save(file).ok();
+        }
+        self.status_message
+            .set_message(format!("Mark {} set", name));
+    }
+
+    pub(crate) fn goto_mark(&mut self, name: char, linewise: bool) {
+        let (x, y) = match self.buffer.editor_rows.marks.positions.get(&name) {
+            Some(&position) => position,
+            None => {
+                self.status_message
+                    .set_message(format!("Mark {} not set", name));
+                return;
+            }
+        };
+        self.push_jump();
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.load_rows(y);
+        let y = cmp::min(y, editor_rows.number_of_rows().saturating_sub(1));
+        let row = editor_rows
+            .row_contents
+            .get(y)
+            .map_or("", |row| row.row_content.as_str());
+        let x = if linewise {
+            row.len() - row.trim_start().len()
+        } else {
+            let mut x = cmp::min(x, row.len());
+            while !row.is_char_boundary(x) {
+                x -= 1;
+            }
+            x
+        };
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        cursor_controller.cursor_x = x;
+        cursor_controller.cursor_y = y;
+        cursor_controller.anchor = None;
+        cursor_controller.block = None;
+    }
+
+    pub(crate) fn list_marks(&mut self) {
+        let marks = &self.buffer.editor_rows.marks.positions;
+        let message = if marks.is_empty() {
+            "No marks set".into()
+        } else {
+            marks
+                .iter()
+                .map(|(name, (x, y))| {
+                    let column = self
+                        .buffer
+                        .editor_rows
+                        .row_contents
+                        .get(*y)
+                        .and_then(|row| row.row_content.get(..*x))
+                        .map_or(*x, |text| text.chars().count());
+                    format!("{} {}:{}", name, y + 1, column + 1)
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+        };
+        self.status_message.set_message(message);
+    }
+}
+
+impl Editor {
+    pub(crate) fn ask_mark(&mut self, set: bool) -> crossterm::Result<()> {
+        let message = if set {
+            "Set mark: (a-z)"
+        } else {
+            "Go to mark: (a-z)"
+        };
+        self.output.status_message.set_message(message.into());
+        self.output.refresh_screen()?;
+        let key = self.reader.read_key()?;
+        self.output.status_message.set_message(String::new());
+        match key.code {
+            KeyCode::Char(name) if set => self.output.set_mark(name),
+            KeyCode::Char(name) => self.output.goto_mark(name, false),
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,635 @@
+use std::{cmp, mem};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::bracket::matching_bracket;
+use crate::motion::{next_paragraph, previous_paragraph};
+use crate::recorder;
+use crate::register::Register;
+use crate::search::SearchDirection;
+use crate::word::{next_word_end, next_word_start, previous_word_start, word_end};
+use crate::{Editor, EditorRows};
+
+const MAX_PASTE: usize = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Mode {
+    Normal,
+    Insert,
+    Visual,
+    VisualBlock,
+}
+
+impl Mode {
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+            Mode::VisualBlock => "VISUAL BLOCK",
+        }
+    }
+
+    pub(crate) fn is_visual(self) -> bool {
+        matches!(self, Mode::Visual | Mode::VisualBlock)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Pending {
+    count: Option<usize>,
+    operator: Option<char>,
+    g: bool,
+    quote: bool,
+    register: Option<char>,
+    /// Set by `q`, `@`, `m`, `` ` ``, `'` and `z` for the key after.
+    naming: Option<char>,
+}
+
+impl Pending {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.count.is_none()
+            && self.operator.is_none()
+            && !self.g
+            && !self.quote
+            && self.register.is_none()
+            && self.naming.is_none()
+    }
+}
+
+impl Editor {
+    /// `None` for keys that should go through the keymap.
+    pub(crate) fn process_modal_key(&mut self, key: KeyEvent) -> crossterm::Result<Option<bool>> {
+        let mode = match self.output.mode {
+            Some(mode) => mode,
+            None => return Ok(None),
+        };
+        if mode == Mode::Insert {
+            if key.code != KeyCode::Esc {
+                return Ok(None);
+            }
+            self.output.clear_selection();
+            self.output.mode = Some(Mode::Normal);
+            if self.output.buffer.cursor_controller.cursor_x > 0 {
+                self.output.move_cursor(KeyCode::Left);
+            }
+            return Ok(Some(true));
+        }
+        let c = match key {
+            KeyEvent {
+                code: KeyCode::Char(c),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            } => c,
+            KeyEvent {
+                code: KeyCode::Enter,
+                modifiers: KeyModifiers::NONE,
+            } => 'j',
+            KeyEvent {
+                code: KeyCode::Backspace,
+                modifiers: KeyModifiers::NONE,
+            } => 'h',
+            KeyEvent {
+                code: KeyCode::Char('v'),
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                self.pending = Pending::default();
+                if mode == Mode::VisualBlock {
+                    self.output.clear_selection();
+                } else {
+                    self.output.start_block();
+                    self.output.mode = Some(Mode::VisualBlock);
+                }
+                return Ok(Some(true));
+            }
+            KeyEvent {
+                code: KeyCode::Char('o'),
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                self.pending = Pending::default();
+                self.output.step_jump_list(true);
+                return Ok(Some(true));
+            }
+            KeyEvent {
+                code: KeyCode::Tab,
+                modifiers: KeyModifiers::NONE,
+            } => {
+                self.pending = Pending::default();
+                self.output.step_jump_list(false);
+                return Ok(Some(true));
+            }
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            } => {
+                self.pending = Pending::default();
+                self.output.clear_selection();
+                return Ok(Some(true));
+            }
+            _ => return Ok(None),
+        };
+        self.process_normal_key(c, mode).map(Some)
+    }
+
+    fn process_normal_key(&mut self, c: char, mode: Mode) -> crossterm::Result<bool> {
+        if self.pending.quote {
+            self.pending.quote = false;
+            self.pending.register = Some(c);
+            return Ok(true);
+        }
+        if let Some(command) = self.pending.naming {
+            let count = mem::take(&mut self.pending).count.unwrap_or(1);
+            match command {
+                'q' => self.toggle_recording(Some(c)),
+                '@' => self.play_macro(c, count),
+                'm' => self.output.set_mark(c),
+                'z' => match c {
+                    't' | 'z' | 'b' => self.output.scroll_cursor_to(c),
+                    _ => {}
+                },
+                _ => self.output.goto_mark(c, command == '\''),
+            }
+            return Ok(true);
+        }
+        if let Some(digit) = c.to_digit(10) {
+            if digit > 0 || self.pending.count.is_some() {
+                let count = self.pending.count.unwrap_or(0);
+                self.pending.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+                return Ok(true);
+            }
+        }
+        let pending = mem::take(&mut self.pending);
+        let count = pending.count.unwrap_or(1);
+        if pending.g && c != 'g' && c != 'J' {
+            return Ok(true);
+        }
+        let cursor = self.cursor();
+        if let Some((target, linewise)) = self.motion(c, pending.count, pending.g, pending.operator)
+        {
+            match pending.operator {
+                Some(operator) if mode == Mode::Normal => {
+                    self.apply_operator(operator, pending.register, cursor, target, linewise)
+                }
+                _ => {
+                    let buffer = &self.output.buffer;
+                    let column = match c {
+                        'j' | 'k' => Some(
+                            buffer
+                                .cursor_controller
+                                .preferred_column(&buffer.editor_rows),
+                        ),
+                        '$' => Some(usize::MAX),
+                        _ => None,
+                    };
+                    self.set_cursor(target);
+                    let buffer = &mut self.output.buffer;
+                    match column {
+                        Some(column) => buffer
+                            .cursor_controller
+                            .keep_column(column, &buffer.editor_rows),
+                        None => buffer.cursor_controller.preferred_x = None,
+                    }
+                }
+            }
+            if mode == Mode::VisualBlock {
+                self.output.track_block(linewise);
+            }
+            return Ok(true);
+        }
+        match c {
+            'g' => self.pending = Pending { g: true, ..pending },
+            '"' => {
+                self.pending = Pending {
+                    quote: true,
+                    ..pending
+                }
+            }
+            '>' | '<' if mode.is_visual() => {
+                self.output.indent_lines(c == '<');
+                self.output.clear_selection();
+            }
+            'd' | 'y' | 'c' | 'x' if mode == Mode::VisualBlock => {
+                self.block_operator(c, pending.register)
+            }
+            'I' | 'A' if mode == Mode::VisualBlock => self.insert_in_block(c == 'A'),
+            'J' if mode.is_visual() => self.output.join_lines(!pending.g),
+            'J' => {
+                let (_, y) = cursor;
+                let rows = self.output.buffer.editor_rows.number_of_rows();
+                let last = cmp::min(
+                    y.saturating_add(cmp::max(count, 2) - 1),
+                    rows.saturating_sub(1),
+                );
+                self.output.join_rows(y, last, !pending.g)
+            }
+            'd' | 'y' | 'c' | 'x' if mode == Mode::Visual => {
+                let (start, end) = self.output.selection().unwrap_or((cursor, cursor));
+                self.output.clear_selection();
+                let operator = if c == 'x' { 'd' } else { c };
+                self.apply_operator(operator, pending.register, start, end, false);
+            }
+            'd' | 'y' | 'c' => match pending.operator {
+                Some(operator) if operator == c => {
+                    let (_, y) = cursor;
+                    let rows = self.output.buffer.editor_rows.number_of_rows();
+                    let last = cmp::min(y.saturating_add(count - 1), cmp::max(rows, y + 1) - 1);
+                    self.apply_operator(c, pending.register, (0, y), (0, last), true)
+                }
+                Some(_) => {}
+                None => {
+                    self.pending = Pending {
+                        operator: Some(c),
+                        ..pending
+                    }
+                }
+            },
+            'x' => {
+                let (x, y) = cursor;
+                let editor_rows = &self.output.buffer.editor_rows;
+                if y < editor_rows.number_of_rows() {
+                    let row = editor_rows.get_editor_row(y);
+                    let end = repeat(count, x, |at| row.next_boundary(at));
+                    let end = cmp::min(end, row.row_content.len());
+                    self.apply_operator('d', pending.register, cursor, (end, y), false)
+                }
+            }
+            'i' => self.output.mode = Some(Mode::Insert),
+            'a' => {
+                if !self.at_line_end() {
+                    self.output.move_cursor(KeyCode::Right);
+                }
+                self.output.mode = Some(Mode::Insert)
+            }
+            'A' => {
+                self.output.move_cursor(KeyCode::End);
+                self.output.mode = Some(Mode::Insert)
+            }
+            'I' => {
+                let (_, y) = cursor;
+                self.set_cursor((first_non_blank(&self.output.buffer.editor_rows, y), y));
+                self.output.mode = Some(Mode::Insert)
+            }
+            'o' => {
+                self.output.move_cursor(KeyCode::End);
+                self.output.insert_newline();
+                self.output.mode = Some(Mode::Insert)
+            }
+            'O' => {
+                let (_, y) = cursor;
+                self.set_cursor((0, y));
+                self.output.insert_newline();
+                self.output.move_cursor(KeyCode::Up);
+                self.output.mode = Some(Mode::Insert)
+            }
+            'v' if mode == Mode::Visual => self.output.clear_selection(),
+            'v' => {
+                let cursor_controller = &mut self.output.buffer.cursor_controller;
+                cursor_controller.anchor.get_or_insert(cursor);
+                cursor_controller.block = None;
+                self.output.mode = Some(Mode::Visual)
+            }
+            'p' | 'P' => self.paste(c == 'p', count, pending.register),
+            'q' if recorder::recording().is_some() => self.toggle_recording(None),
+            'q' | '@' | 'm' | '`' | '\'' | 'z' => {
+                self.pending = Pending {
+                    naming: Some(c),
+                    ..pending
+                }
+            }
+            'u' => self.output.undo(),
+            '.' => self.repeat_edit(count),
+            ':' => return self.run_command(),
+            '/' => self.output.find()?,
+            'n' => self.output.find_again(SearchDirection::Forward),
+            'N' => self.output.find_again(SearchDirection::Backward),
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    pub(crate) fn cursor(&self) -> (usize, usize) {
+        let cursor_controller = &self.output.buffer.cursor_controller;
+        (cursor_controller.cursor_x, cursor_controller.cursor_y)
+    }
+
+    pub(crate) fn set_cursor(&mut self, (x, y): (usize, usize)) {
+        let cursor_controller = &mut self.output.buffer.cursor_controller;
+        cursor_controller.cursor_x = x;
+        cursor_controller.cursor_y = y;
+    }
+
+    fn at_line_end(&self) -> bool {
+        let (x, y) = self.cursor();
+        let editor_rows = &self.output.buffer.editor_rows;
+        y >= editor_rows.number_of_rows() || x >= editor_rows.get_row(y).len()
+    }
+
+    fn motion(
+        &self,
+        c: char,
+        count: Option<usize>,
+        g: bool,
+        operator: Option<char>,
+    ) -> Option<((usize, usize), bool)> {
+        let editor_rows = &self.output.buffer.editor_rows;
+        let (x, y) = self.cursor();
+        let rows = editor_rows.number_of_rows();
+        let last_row = rows.saturating_sub(1);
+        let times = count.unwrap_or(1);
+        let row = |y: usize| {
+            if y < rows {
+                editor_rows.get_row(y)
+            } else {
+                ""
+            }
+        };
+        let keep_column = |target: usize| {
+            let column = self
+                .output
+                .buffer
+                .cursor_controller
+                .preferred_column(editor_rows);
+            if target < rows {
+                (
+                    editor_rows.get_editor_row(target).grapheme_index(column),
+                    target,
+                )
+            } else {
+                (0, target)
+            }
+        };
+        let motion = match c {
+            'h' => {
+                let x = repeat(times, x, |at| match at {
+                    0 => 0,
+                    at => editor_rows.get_editor_row(y).previous_boundary(at),
+                });
+                ((x, y), false)
+            }
+            'l' => {
+                let len = row(y).len();
+                let x = repeat(times, x, |at| match at {
+                    at if at >= len => len,
+                    at => editor_rows.get_editor_row(y).next_boundary(at),
+                });
+                ((x, y), false)
+            }
+            'j' => (keep_column(cmp::min(y + times, last_row)), true),
+            'k' => (keep_column(y.saturating_sub(times)), true),
+            '0' => ((0, y), false),
+            '$' => ((row(y).len(), y), false),
+            'w' if operator == Some('c') => {
+                let end = repeat(times, (x, y), |at| word_end(editor_rows, at));
+                (end, false)
+            }
+            'w' => {
+                let mut target = repeat(times, (x, y), |at| next_word_start(editor_rows, at));
+                if operator.is_some() && target.1 > y {
+                    target = (row(target.1 - 1).len(), target.1 - 1);
+                }
+                (target, false)
+            }
+            'e' => {
+                let end = repeat(times, (x, y), |at| next_word_end(editor_rows, at));
+                match operator {
+                    Some(_) => (end, false),
+                    None if end.1 < rows && end.0 > 0 => (
+                        (
+                            editor_rows.get_editor_row(end.1).previous_boundary(end.0),
+                            end.1,
+                        ),
+                        false,
+                    ),
+                    None => (end, false),
+                }
+            }
+            'b' => (
+                repeat(times, (x, y), |at| previous_word_start(editor_rows, at)),
+                false,
+            ),
+            '}' => (
+                repeat(times, (x, y), |at| next_paragraph(editor_rows, at)),
+                false,
+            ),
+            '{' => (
+                repeat(times, (x, y), |at| previous_paragraph(editor_rows, at)),
+                false,
+            ),
+            '%' => {
+                let target = matching_bracket(editor_rows, (x, y))?;
+                match operator {
+                    Some(_) if (target.1, target.0) > (y, x) => (
+                        (
+                            editor_rows.get_editor_row(target.1).next_boundary(target.0),
+                            target.1,
+                        ),
+                        false,
+                    ),
+                    _ => (target, false),
+                }
+            }
+            'G' => {
+                let target = count.map_or(last_row, |line| cmp::min(line - 1, last_row));
+                ((first_non_blank(editor_rows, target), target), true)
+            }
+            'g' if g => {
+                let target = count.map_or(0, |line| cmp::min(line - 1, last_row));
+                ((first_non_blank(editor_rows, target), target), true)
+            }
+            _ => return None,
+        };
+        Some(motion)
+    }
+
+    fn apply_operator(
+        &mut self,
+        operator: char,
+        register: Option<char>,
+        from: (usize, usize),
+        to: (usize, usize),
+        linewise: bool,
+    ) {
+        if operator != 'y' && self.output.reject_edit() {
+            return;
+        }
+        let (start, end) = ordered(from, to);
+        let editor_rows = &mut self.output.buffer.editor_rows;
+        let rows = editor_rows.number_of_rows();
+        if rows == 0 || start.1 >= rows {
+            return;
+        }
+        if linewise {
+            let (first, last) = (start.1, cmp::min(end.1, rows - 1));
+            let text = (first..=last)
+                .map(|y| format!("{}\n", editor_rows.get_row(y)))
+                .collect();
+            self.registers.store(
+                register,
+                Register {
+                    text,
+                    linewise: true,
+                },
+            );
+            match operator {
+                'd' => {
+                    editor_rows.delete_rows(first, last);
+                    let last_row = editor_rows.number_of_rows().saturating_sub(1);
+                    let y = cmp::min(first, last_row);
+                    self.set_cursor((first_non_blank(&self.output.buffer.editor_rows, y), y));
+                }
+                'c' => {
+                    if last > first {
+                        editor_rows.delete_rows(first + 1, last);
+                    }
+                    let len = editor_rows.get_row(first).len();
+                    editor_rows.replace_range(first, 0, len, "");
+                    self.set_cursor((0, first));
+                    self.output.mode = Some(Mode::Insert);
+                }
+                _ => self.output.buffer.cursor_controller.cursor_y = first,
+            }
+        } else {
+            let end = if end.1 >= rows {
+                (editor_rows.get_row(rows - 1).len(), rows - 1)
+            } else {
+                end
+            };
+            let text = editor_rows.text_range(start, end);
+            self.registers.store(
+                register,
+                Register {
+                    text,
+                    linewise: false,
+                },
+            );
+            if operator != 'y' {
+                editor_rows.delete_range(start, end);
+            }
+            self.set_cursor(start);
+            if operator == 'c' {
+                self.output.mode = Some(Mode::Insert);
+            }
+        }
+        if operator != 'y' {
+            self.output.buffer.dirty += 1;
+        }
+    }
+
+    fn block_operator(&mut self, operator: char, register: Option<char>) {
+        let (text, (y, x, _)) = match (self.output.block_text(), self.output.block_ranges()) {
+            (Some(text), Some(ranges)) => (text, ranges[0]),
+            _ => return,
+        };
+        if operator != 'y' && self.output.reject_edit() {
+            return;
+        }
+        self.registers.store(
+            register,
+            Register {
+                text,
+                linewise: false,
+            },
+        );
+        if operator == 'c' {
+            self.output.delete_block();
+            self.output.mode = Some(Mode::Insert);
+            return;
+        }
+        if operator != 'y' {
+            self.output.delete_block();
+        }
+        self.output.clear_selection();
+        self.set_cursor((x, y));
+    }
+
+    fn insert_in_block(&mut self, append: bool) {
+        let (ranges, (left, _)) = match (
+            self.output.block_ranges(),
+            self.output.buffer.cursor_controller.block_columns(),
+        ) {
+            (Some(ranges), Some(columns)) => (ranges, columns),
+            _ => return,
+        };
+        let editor_rows = &self.output.buffer.editor_rows;
+        let column = if append {
+            ranges
+                .iter()
+                .map(|&(y, _, to)| editor_rows.get_editor_row(y).render_x(to))
+                .max()
+                .unwrap_or(left)
+        } else {
+            left
+        };
+        self.output.collapse_block(column);
+        self.output.mode = Some(Mode::Insert);
+    }
+
+    fn paste(&mut self, after: bool, count: usize, name: Option<char>) {
+        let register = match self.registers.get(name) {
+            Some(register) if register.text.len().saturating_mul(count) > MAX_PASTE => {
+                self.output
+                    .status_message
+                    .set_message("Too many copies to paste".into());
+                return;
+            }
+            Some(register) => Register {
+                text: register.text.repeat(count),
+                linewise: register.linewise,
+            },
+            None => return,
+        };
+        if self.output.reject_edit() {
+            return;
+        }
+        let (x, y) = self.cursor();
+        let editor_rows = &self.output.buffer.editor_rows;
+        let rows = editor_rows.number_of_rows();
+        let at = if register.linewise {
+            (0, if after { y + 1 } else { y })
+        } else if after && y < rows && x < editor_rows.get_row(y).len() {
+            (editor_rows.get_editor_row(y).next_boundary(x), y)
+        } else {
+            (x, y)
+        };
+        let next = if matches!(name, None | Some('"')) {
+            1
+        } else {
+            0
+        };
+        let end = self.put(&register, at, next);
+        if register.linewise {
+            let y = cmp::min(at.1, rows);
+            self.set_cursor((first_non_blank(&self.output.buffer.editor_rows, y), y));
+        } else {
+            self.set_cursor(end);
+        }
+    }
+}
+
+/// Stops early once `step` doesn't move, as at the end of the file.
+fn repeat<T: Copy + PartialEq>(times: usize, from: T, step: impl Fn(T) -> T) -> T {
+    let mut at = from;
+    for _ in 0..times {
+        let next = step(at);
+        if next == at {
+            break;
+        }
+        at = next;
+    }
+    at
+}
+
+fn ordered(a: (usize, usize), b: (usize, usize)) -> ((usize, usize), (usize, usize)) {
+    if (a.1, a.0) <= (b.1, b.0) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn first_non_blank(editor_rows: &EditorRows, y: usize) -> usize {
+    if y < editor_rows.number_of_rows() {
+        let row = editor_rows.get_row(y);
+        row.len() - row.trim_start().len()
+    } else {
+        0
+    }
+}
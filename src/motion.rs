@@ -0,0 +1,112 @@
+use std::cmp;
+
+use crate::{EditorRows, Output};
+
+fn indentation(editor_rows: &EditorRows, y: usize) -> Option<usize> {
+    let row = editor_rows.get_editor_row(y);
+    let content = &row.row_content;
+    let text = content.trim_start();
+    (!text.is_empty()).then(|| row.render_x(content.len() - text.len()))
+}
+
+fn is_blank(editor_rows: &EditorRows, y: usize) -> bool {
+    indentation(editor_rows, y).is_none()
+}
+
+fn at_indentation(editor_rows: &EditorRows, y: usize) -> (usize, usize) {
+    let row = editor_rows.get_row(y);
+    (row.len() - row.trim_start().len(), y)
+}
+
+pub(crate) fn next_paragraph(editor_rows: &EditorRows, (_, y): (usize, usize)) -> (usize, usize) {
+    let rows = editor_rows.number_of_rows();
+    let mut y = y;
+    while y < rows && is_blank(editor_rows, y) {
+        y += 1;
+    }
+    while y < rows && !is_blank(editor_rows, y) {
+        y += 1;
+    }
+    if y < rows {
+        (0, y)
+    } else {
+        let y = rows.saturating_sub(1);
+        (
+            editor_rows
+                .row_contents
+                .get(y)
+                .map_or(0, |row| row.row_content.len()),
+            y,
+        )
+    }
+}
+
+pub(crate) fn previous_paragraph(
+    editor_rows: &EditorRows,
+    (_, y): (usize, usize),
+) -> (usize, usize) {
+    let rows = editor_rows.number_of_rows();
+    if rows == 0 {
+        return (0, 0);
+    }
+    let mut y = cmp::min(y, rows - 1);
+    while y > 0 && is_blank(editor_rows, y) {
+        y -= 1;
+    }
+    while y > 0 && !is_blank(editor_rows, y) {
+        y -= 1;
+    }
+    (0, y)
+}
+
+pub(crate) fn block_start(editor_rows: &EditorRows, (x, y): (usize, usize)) -> (usize, usize) {
+    block_edge(editor_rows, (x, y), false)
+}
+
+pub(crate) fn block_end(editor_rows: &EditorRows, (x, y): (usize, usize)) -> (usize, usize) {
+    block_edge(editor_rows, (x, y), true)
+}
+
+fn block_edge(editor_rows: &EditorRows, (x, y): (usize, usize), down: bool) -> (usize, usize) {
+    let rows = editor_rows.number_of_rows();
+    if y >= rows {
+        return (x, y);
+    }
+    let step = |y: usize| {
+        if down {
+            (y + 1 < rows).then(|| y + 1)
+        } else {
+            y.checked_sub(1)
+        }
+    };
+    let level = match indentation(editor_rows, y) {
+        Some(level) => level,
+        None => return (x, y),
+    };
+    let inside = |y: usize| indentation(editor_rows, y).map_or(true, |indent| indent >= level);
+    let mut edge = y;
+    let mut at = y;
+    while let Some(next) = step(at) {
+        if !inside(next) {
+            break;
+        }
+        at = next;
+        if !is_blank(editor_rows, at) {
+            edge = at;
+        }
+    }
+    if edge == y {
+        edge = step(at).unwrap_or(y);
+    }
+    at_indentation(editor_rows, edge)
+}
+
+impl Output {
+    pub(crate) fn jump(&mut self, motion: fn(&EditorRows, (usize, usize)) -> (usize, usize)) {
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let (x, y) = motion(&self.buffer.editor_rows, cursor);
+        cursor_controller.cursor_x = x;
+        cursor_controller.cursor_y = y;
+    }
+}
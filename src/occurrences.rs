@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+
+use crate::{Output, Row};
+
+const IDLE: Duration = Duration::from_millis(300);
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[derive(Default)]
+pub(crate) struct Occurrences {
+    at: Option<(usize, usize, usize, u64)>,
+    since: Option<Instant>,
+    word: Option<String>,
+}
+
+fn find_word(text: &str, word: &str) -> Vec<(usize, usize)> {
+    text.match_indices(word)
+        .map(|(at, _)| (at, at + word.len()))
+        .filter(|&(from, to)| {
+            !text[..from]
+                .chars()
+                .next_back()
+                .map_or(false, is_identifier)
+                && !text[to..].chars().next().map_or(false, is_identifier)
+        })
+        .collect()
+}
+
+impl Output {
+    fn identifier_at_cursor(&self) -> Option<String> {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let (x, y) = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        if y >= self.buffer.editor_rows.number_of_rows() {
+            return None;
+        }
+        let text = self.buffer.editor_rows.get_row(y);
+        if !text[x..].chars().next().map_or(false, is_identifier) {
+            return None;
+        }
+        let start = text[..x]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_identifier(c))
+            .last()
+            .map_or(x, |(at, _)| at);
+        let end = text[x..]
+            .find(|c| !is_identifier(c))
+            .map_or(text.len(), |len| x + len);
+        let word = &text[start..end];
+        (!word.starts_with(|c: char| c.is_numeric())).then(|| word.into())
+    }
+
+    pub(crate) fn update_occurrences(&mut self) {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let at = Some((
+            self.current_buffer,
+            cursor_controller.cursor_x,
+            cursor_controller.cursor_y,
+            self.buffer.editor_rows.changes,
+        ));
+        let occurrences = &mut self.occurrences;
+        if occurrences.at != at {
+            occurrences.at = at;
+            occurrences.since = Some(Instant::now());
+            occurrences.word = None;
+            return;
+        }
+        if occurrences
+            .since
+            .map_or(false, |since| since.elapsed() >= IDLE)
+        {
+            self.occurrences.since = None;
+            if self.buffer.editor_rows.highlight_occurrences {
+                self.occurrences.word = self.identifier_at_cursor();
+            }
+        }
+    }
+
+    pub(crate) fn occurrence_delay(&self) -> Option<Duration> {
+        let since = self.occurrences.since?;
+        Some(IDLE.saturating_sub(since.elapsed()))
+    }
+
+    pub(crate) fn occurrences_in(&self, file_row: usize, row: &Row) -> Vec<(usize, usize)> {
+        let word = match &self.occurrences.word {
+            Some(word) => word,
+            None => return Vec::new(),
+        };
+        let cursor_controller = &self.buffer.cursor_controller;
+        let (x, y) = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        find_word(&row.row_content, word)
+            .into_iter()
+            .filter(|&(from, to)| !(file_row == y && from <= x && x < to))
+            .map(|(from, to)| (row.render_index(from), row.render_index(to)))
+            .collect()
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Range;
+use std::{cmp, iter};
+
+use crossterm::{cursor, queue, style};
+
+use crate::Output;
+
+impl Output {
+    pub(crate) fn draw_popup(
+        &mut self,
+        lines: &[String],
+        marked: Option<(usize, Range<usize>)>,
+        shift: usize,
+        above: bool,
+    ) -> crossterm::Result<()> {
+        let (columns, rows) = self.win_size;
+        let height = cmp::min(lines.len(), rows);
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let width = cmp::min(width, columns);
+
+        let rect = self.window_rect(self.current_window);
+        let (cursor_x, cursor_y) = self
+            .buffer
+            .cursor_controller
+            .screen_position(&self.buffer.editor_rows);
+        let x = cmp::min((rect.x + cursor_x).saturating_sub(shift), columns - width);
+        let (line, below) = (rect.y + cursor_y, rect.y + cursor_y + 1);
+        let fits_below = below + height <= rows;
+        let y = if line >= height && (above || !fits_below) {
+            line - height
+        } else {
+            below
+        };
+
+        for (i, text) in lines.iter().take(height).enumerate() {
+            queue!(
+                self.editor_contents,
+                cursor::MoveTo(x as u16, (y + i) as u16)
+            )?;
+            self.editor_contents
+                .set_foreground(self.theme.status_bar_foreground);
+            self.editor_contents
+                .set_background(self.theme.status_bar_background);
+            let range = match &marked {
+                Some((marked, range)) if *marked == i => range.clone(),
+                _ => 0..0,
+            };
+            let mut reversed = false;
+            for (column, c) in padded(text, width).enumerate() {
+                if range.contains(&column) != reversed {
+                    reversed = !reversed;
+                    self.editor_contents.push_str(
+                        &if reversed {
+                            style::Attribute::Reverse
+                        } else {
+                            style::Attribute::NoReverse
+                        }
+                        .to_string(),
+                    );
+                }
+                self.editor_contents.push(c);
+            }
+            self.editor_contents
+                .push_str(&style::Attribute::Reset.to_string());
+        }
+        Ok(())
+    }
+}
+
+fn padded(text: &str, width: usize) -> impl Iterator<Item = char> + '_ {
+    text.chars()
+        .chain(iter::repeat(' '))
+        .take(width)
+        .map(|c| if c.is_control() { ' ' } else { c })
+}
@@ -0,0 +1,208 @@
+use std::cmp;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::{cursor, queue};
+
+use crate::{shell, Output, Reader};
+
+/// Lines and columns count from 1.
+pub(crate) struct Location {
+    pub(crate) file: PathBuf,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) message: String,
+}
+
+#[derive(Default)]
+pub(crate) struct Quickfix {
+    title: String,
+    locations: Vec<Location>,
+    current: usize,
+}
+
+impl Quickfix {
+    pub(crate) fn new(title: String, locations: Vec<Location>) -> Self {
+        Self {
+            title,
+            locations,
+            current: 0,
+        }
+    }
+}
+
+fn parse(output: &str) -> Vec<Location> {
+    let mut locations = Vec::new();
+    let mut heading = "";
+    for line in output.lines() {
+        let trimmed = line.trim_start();
+        let (rest, under_heading) = match trimmed.strip_prefix("--> ") {
+            Some(rest) => (rest, true),
+            None => (trimmed, false),
+        };
+        let mut parts = rest.splitn(4, ':');
+        let (file, number) = match (parts.next(), parts.next().map(str::parse)) {
+            (Some(file), Some(Ok(number))) if Path::new(file).is_file() => (file, number),
+            _ => {
+                if trimmed.starts_with("error") || trimmed.starts_with("warning") {
+                    heading = trimmed;
+                }
+                continue;
+            }
+        };
+        let third = parts.next().unwrap_or("");
+        let (column, message) = match third.parse() {
+            Ok(column) => (column, parts.next().unwrap_or("")),
+            Err(_) => (1, rest.splitn(3, ':').nth(2).unwrap_or("")),
+        };
+        locations.push(Location {
+            file: file.into(),
+            line: number,
+            column,
+            message: if under_heading { heading } else { message }.trim().into(),
+        });
+    }
+    locations
+}
+
+impl Output {
+    pub(crate) fn make(&mut self, args: &str) -> crossterm::Result<()> {
+        let command = if args.is_empty() {
+            self.config.make.clone()
+        } else {
+            format!("{} {}", self.config.make, args)
+        };
+        let output = match shell::run(&command, true) {
+            Ok(output) => output,
+            Err(err) => {
+                self.status_message
+                    .set_message(format!("Can't run {}: {}", command, err));
+                return Ok(());
+            }
+        };
+        let text = String::from_utf8_lossy(&output.stdout);
+        self.quickfix = Quickfix::new(command.clone(), parse(&text));
+        if !self.quickfix.locations.is_empty() {
+            return self.quickfix_list();
+        }
+        if output.status.success() {
+            self.status_message
+                .set_message(format!("{} succeeded", command));
+        } else {
+            // Nothing to jump to, so show what went wrong instead.
+            self.open_scratch(&text);
+            self.status_message
+                .set_message(format!("{} failed", command));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn quickfix_list(&mut self) -> crossterm::Result<()> {
+        if self.quickfix.locations.is_empty() {
+            self.status_message
+                .set_message("No locations to list".into());
+            return Ok(());
+        }
+        let lines: Vec<String> = self
+            .quickfix
+            .locations
+            .iter()
+            .map(|location| {
+                format!(
+                    "{}:{}:{}: {}",
+                    location.file.display(),
+                    location.line,
+                    location.column,
+                    location.message
+                )
+            })
+            .collect();
+        let info = format!("{} -- {} locations", self.quickfix.title, lines.len());
+        let last = lines.len() - 1;
+        let mut selected = self.quickfix.current;
+        let mut row_offset = 0;
+        loop {
+            self.draw_list(&lines, selected, &mut row_offset, &info)?;
+            self.status_message
+                .set_message("Quickfix (Use ESC / Arrows / Enter)".into());
+            self.draw_message_bar();
+            queue!(
+                self.editor_contents,
+                cursor::MoveTo(0, (selected - row_offset) as u16),
+                cursor::Show
+            )?;
+            self.editor_contents.flush()?;
+            let page = self.win_size.1;
+            match Reader.read_key()? {
+                KeyEvent {
+                    code: KeyCode::Esc, ..
+                } => {
+                    self.status_message.set_message(String::new());
+                    return Ok(());
+                }
+                KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                } => break,
+                KeyEvent {
+                    code: KeyCode::Up, ..
+                } => selected = selected.saturating_sub(1),
+                KeyEvent {
+                    code: KeyCode::Down,
+                    ..
+                } => selected = cmp::min(selected + 1, last),
+                KeyEvent {
+                    code: KeyCode::PageUp,
+                    ..
+                } => selected = selected.saturating_sub(page),
+                KeyEvent {
+                    code: KeyCode::PageDown,
+                    ..
+                } => selected = cmp::min(selected + page, last),
+                _ => {}
+            }
+        }
+        self.goto_error(selected);
+        Ok(())
+    }
+
+    pub(crate) fn step_error(&mut self, forward: bool) {
+        let count = self.quickfix.locations.len();
+        let current = self.quickfix.current;
+        let next = if forward {
+            Some(current + 1).filter(|&next| next < count)
+        } else {
+            current.checked_sub(1)
+        };
+        match next {
+            Some(next) => self.goto_error(next),
+            None if count == 0 => self
+                .status_message
+                .set_message("No locations to list".into()),
+            None if forward => self
+                .status_message
+                .set_message("No more locations below".into()),
+            None => self
+                .status_message
+                .set_message("No more locations above".into()),
+        }
+    }
+
+    fn goto_error(&mut self, index: usize) {
+        self.quickfix.current = index;
+        let location = &self.quickfix.locations[index];
+        let (file, line, column) = (location.file.clone(), location.line, location.column);
+        let message = format!(
+            "({}/{}) {}",
+            index + 1,
+            self.quickfix.locations.len(),
+            location.message
+        );
+        self.push_jump();
+        if self.visit(&file) {
+            self.goto_position(line, column);
+            self.status_message.set_message(message);
+        }
+    }
+}
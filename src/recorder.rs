@@ -0,0 +1,262 @@
+use std::cell::RefCell;
+use std::mem;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent};
+
+use crate::keymap::Action;
+use crate::modal::Mode;
+use crate::{Editor, Reader};
+
+#[derive(Clone, Copy)]
+pub(crate) struct Recorded {
+    key: KeyEvent,
+    batched: bool,
+}
+
+/// Kept beside the terminal, as every key goes through `Reader`.
+#[derive(Default)]
+struct Tape {
+    recording: Option<(char, Vec<Recorded>)>,
+    command: Vec<Recorded>,
+    replay: Vec<Playing>,
+    batched: bool,
+}
+
+/// Keys played from `at`, then `times - 1` more times from the start.
+struct Playing {
+    keys: Vec<Recorded>,
+    at: usize,
+    times: usize,
+}
+
+impl Tape {
+    fn next_replayed(&mut self) -> Option<Recorded> {
+        let playing = self.replay.last_mut()?;
+        let key = playing.keys[playing.at];
+        playing.at += 1;
+        if playing.at == playing.keys.len() {
+            playing.at = 0;
+            playing.times -= 1;
+            if playing.times == 0 {
+                self.replay.pop();
+            }
+        }
+        Some(key)
+    }
+}
+
+thread_local! {
+    static TAPE: RefCell<Tape> = RefCell::default();
+}
+
+#[derive(Default)]
+pub(crate) struct Repeat {
+    keys: Vec<Recorded>,
+    last: Vec<Recorded>,
+    before: (usize, u64),
+    continuing: bool,
+}
+
+fn play(keys: &[Recorded], count: usize) {
+    if keys.is_empty() || count == 0 {
+        return;
+    }
+    TAPE.with(|tape| {
+        tape.borrow_mut().replay.push(Playing {
+            keys: keys.to_vec(),
+            at: 0,
+            times: count,
+        })
+    });
+}
+
+pub(crate) fn recording() -> Option<char> {
+    TAPE.with(|tape| tape.borrow().recording.as_ref().map(|&(name, _)| name))
+}
+
+impl Reader {
+    fn replayed(&self) -> crossterm::Result<Option<KeyEvent>> {
+        let replaying = TAPE.with(|tape| !tape.borrow().replay.is_empty());
+        if replaying && event::poll(Duration::ZERO)? {
+            TAPE.with(|tape| tape.borrow_mut().replay.clear());
+            return Ok(None);
+        }
+        Ok(TAPE.with(|tape| {
+            let mut tape = tape.borrow_mut();
+            let recorded = tape.next_replayed()?;
+            tape.batched = recorded.batched;
+            Some(recorded.key)
+        }))
+    }
+
+    pub(crate) fn poll_event(&self, timeout: Duration) -> crossterm::Result<Option<Event>> {
+        if let Some(key) = self.replayed()? {
+            return Ok(Some(Event::Key(key)));
+        }
+        if !event::poll(timeout)? {
+            return Ok(None);
+        }
+        let event = event::read()?;
+        if let Event::Key(key) = event {
+            TAPE.with(|tape| {
+                let tape = &mut *tape.borrow_mut();
+                let recorded = Recorded {
+                    key,
+                    batched: false,
+                };
+                tape.command.push(recorded);
+                if let Some((_, keys)) = &mut tape.recording {
+                    keys.push(recorded);
+                }
+            });
+        }
+        Ok(Some(event))
+    }
+
+    pub(crate) fn ready(&self) -> crossterm::Result<bool> {
+        let replayed = TAPE.with(|tape| {
+            let tape = tape.borrow();
+            (!tape.replay.is_empty()).then(|| tape.batched)
+        });
+        if let Some(batched) = replayed {
+            return Ok(batched);
+        }
+        let ready = event::poll(Duration::ZERO)?;
+        if ready {
+            TAPE.with(|tape| {
+                let tape = &mut *tape.borrow_mut();
+                let recorded = tape.recording.as_mut().map(|(_, keys)| keys);
+                for keys in [Some(&mut tape.command), recorded].into_iter().flatten() {
+                    if let Some(last) = keys.last_mut() {
+                        last.batched = true;
+                    }
+                }
+            });
+        }
+        Ok(ready)
+    }
+}
+
+impl Editor {
+    pub(crate) fn toggle_recording(&mut self, name: Option<char>) {
+        let recorded = TAPE.with(|tape| tape.borrow_mut().recording.take());
+        if let Some((name, mut keys)) = recorded {
+            keys.pop();
+            self.registers.store_macro(name, keys);
+            self.output
+                .status_message
+                .set_message(format!("Recorded @{}", name.to_ascii_lowercase()));
+            return;
+        }
+        match name.filter(char::is_ascii_alphabetic) {
+            Some(name) => TAPE.with(|tape| tape.borrow_mut().recording = Some((name, Vec::new()))),
+            None => self
+                .output
+                .status_message
+                .set_message("Macros are recorded into registers a to z".into()),
+        }
+    }
+
+    pub(crate) fn play_macro(&mut self, name: char, count: usize) {
+        match self.registers.get_macro(name) {
+            Some(keys) => play(keys, count),
+            None => self
+                .output
+                .status_message
+                .set_message(format!("No macro in register {}", name)),
+        }
+    }
+
+    pub(crate) fn track_edit(&mut self) {
+        let keys = TAPE.with(|tape| mem::take(&mut tape.borrow_mut().command));
+        let repeat = &mut self.repeat;
+        repeat.keys.extend(keys);
+        let mode = self.output.mode;
+        if !mode.map_or(true, |mode| mode == Mode::Normal && self.pending.is_empty()) {
+            return;
+        }
+        let before = mem::replace(
+            &mut repeat.before,
+            (
+                self.output.current_buffer,
+                self.output.buffer.editor_rows.changes,
+            ),
+        );
+        let keys = mem::take(&mut repeat.keys);
+        // Played by a macro or by repeating, rather than typed.
+        if keys.is_empty() {
+            return;
+        }
+        let undoes = match keys.as_slice() {
+            [recorded] => {
+                let action = self.keymap.get(&recorded.key);
+                matches!(action, Some(Action::Undo | Action::Redo))
+                    || mode.is_some() && recorded.key.code == KeyCode::Char('u')
+            }
+            _ => false,
+        };
+        if before.0 != repeat.before.0 || before.1 == repeat.before.1 || undoes {
+            repeat.continuing = false;
+            return;
+        }
+        if repeat.continuing && mode.is_none() {
+            repeat.last.extend(keys);
+        } else {
+            repeat.last = keys;
+        }
+        repeat.continuing = true;
+    }
+
+    pub(crate) fn repeat_edit(&mut self, count: usize) {
+        if self.repeat.last.is_empty() {
+            self.output
+                .status_message
+                .set_message("No edit to repeat".into());
+            return;
+        }
+        play(&self.repeat.last, count);
+    }
+
+    pub(crate) fn record_macro(&mut self) -> crossterm::Result<()> {
+        if recording().is_some() {
+            self.toggle_recording(None);
+            return Ok(());
+        }
+        self.output
+            .status_message
+            .set_message("Record into register: (a-z)".into());
+        self.output.refresh_screen()?;
+        let name = match self.reader.read_key()?.code {
+            KeyCode::Char(c) => Some(c),
+            _ => None,
+        };
+        self.output.status_message.set_message(String::new());
+        self.toggle_recording(name);
+        Ok(())
+    }
+
+    pub(crate) fn ask_macro(&mut self) -> crossterm::Result<()> {
+        let mut count = None;
+        loop {
+            self.output.status_message.set_message(format!(
+                "Play macro: {}(a count, then a register or @ for the last)",
+                count.map_or(String::new(), |count: usize| format!("{} ", count))
+            ));
+            self.output.refresh_screen()?;
+            let key = self.reader.read_key()?;
+            self.output.status_message.set_message(String::new());
+            match key.code {
+                KeyCode::Char(c) if c.is_ascii_digit() && (c != '0' || count.is_some()) => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                }
+                KeyCode::Char(name) => {
+                    self.play_macro(name, count.unwrap_or(1));
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+}
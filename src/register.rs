@@ -0,0 +1,151 @@
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+
+use crate::recorder::Recorded;
+use crate::Editor;
+
+const RING_SIZE: usize = 10;
+
+#[derive(Clone)]
+pub(crate) struct Register {
+    pub(crate) text: String,
+    pub(crate) linewise: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct Registers {
+    ring: VecDeque<Register>,
+    named: HashMap<char, Register>,
+    macros: HashMap<char, Vec<Recorded>>,
+    last_macro: Option<char>,
+}
+
+impl Registers {
+    pub(crate) fn store(&mut self, name: Option<char>, register: Register) {
+        let register = match name.filter(char::is_ascii_alphabetic) {
+            Some(name) => {
+                let named = self
+                    .named
+                    .entry(name.to_ascii_lowercase())
+                    .or_insert_with(|| Register {
+                        text: String::new(),
+                        linewise: false,
+                    });
+                if name.is_ascii_uppercase() {
+                    named.text.push_str(&register.text);
+                    named.linewise |= register.linewise;
+                } else {
+                    *named = register;
+                }
+                named.clone()
+            }
+            None => register,
+        };
+        self.ring.push_front(register);
+        self.ring.truncate(RING_SIZE);
+    }
+
+    pub(crate) fn store_macro(&mut self, name: char, keys: Vec<Recorded>) {
+        let recorded = self.macros.entry(name.to_ascii_lowercase()).or_default();
+        if name.is_ascii_uppercase() {
+            recorded.extend(keys);
+        } else {
+            *recorded = keys;
+        }
+        self.last_macro = Some(name.to_ascii_lowercase());
+    }
+
+    pub(crate) fn get_macro(&mut self, name: char) -> Option<&[Recorded]> {
+        let name = match name {
+            '@' => self.last_macro?,
+            name => name.to_ascii_lowercase(),
+        };
+        let keys = self.macros.get(&name)?;
+        self.last_macro = Some(name);
+        Some(keys)
+    }
+
+    /// `0` to `9` count back from the newest ring entry.
+    pub(crate) fn get(&self, name: Option<char>) -> Option<&Register> {
+        match name {
+            None | Some('"') => self.ring.front(),
+            Some(digit @ '0'..='9') => self.ring.get(digit as usize - '0' as usize),
+            Some(name) => self.named.get(&name.to_ascii_lowercase()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Paste {
+    start: (usize, usize),
+    end: (usize, usize),
+    linewise: bool,
+    next: usize,
+}
+
+impl Editor {
+    pub(crate) fn put(
+        &mut self,
+        register: &Register,
+        at: (usize, usize),
+        next: usize,
+    ) -> (usize, usize) {
+        let editor_rows = &mut self.output.buffer.editor_rows;
+        let rows = editor_rows.number_of_rows();
+        let start = if register.linewise {
+            (0, cmp::min(at.1, rows))
+        } else {
+            (at.0, cmp::min(at.1, rows))
+        };
+        let end = if register.linewise {
+            let lines = register.text.lines();
+            let count = lines.clone().count();
+            for (i, line) in lines.enumerate() {
+                editor_rows.insert_row(start.1 + i, line.into());
+            }
+            (0, start.1 + count)
+        } else {
+            editor_rows.insert_text(start.0, start.1, &register.text)
+        };
+        self.output.buffer.dirty += 1;
+        self.last_paste = Some(Paste {
+            start,
+            end,
+            linewise: register.linewise,
+            next,
+        });
+        end
+    }
+
+    pub(crate) fn paste_previous(&mut self) {
+        let paste = match self.last_paste {
+            Some(paste) => paste,
+            None => {
+                self.output
+                    .status_message
+                    .set_message("Paste first to go back through older copies".into());
+                return;
+            }
+        };
+        let register = match self.ring_entry(paste.next) {
+            Some(register) => register,
+            None => return,
+        };
+        if self.output.reject_edit() {
+            return;
+        }
+        let editor_rows = &mut self.output.buffer.editor_rows;
+        if paste.linewise && paste.end.1 > paste.start.1 {
+            editor_rows.delete_rows(paste.start.1, paste.end.1 - 1);
+        } else if !paste.linewise {
+            editor_rows.delete_range(paste.start, paste.end);
+        }
+        self.put(&register, paste.start, paste.next + 1);
+        self.set_cursor(paste.start);
+    }
+
+    fn ring_entry(&self, index: usize) -> Option<Register> {
+        let len = self.registers.ring.len();
+        (len > 0).then(|| self.registers.ring[index % len].clone())
+    }
+}
@@ -0,0 +1,182 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+use std::{cmp, fs, thread};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::undo::History;
+use crate::{prompt, Buffer, EditorRows, Output, Reader};
+
+pub(crate) type DiskState = Option<(SystemTime, u64)>;
+
+pub(crate) fn disk_state(file: &Path) -> DiskState {
+    let metadata = fs::metadata(file).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl EditorRows {
+    pub(crate) fn remember_disk_state(&mut self) {
+        self.disk_state = self.filename.as_deref().and_then(disk_state);
+    }
+
+    pub(crate) fn changed_on_disk(&self) -> bool {
+        self.filename
+            .as_deref()
+            .map_or(false, |file| disk_state(file) != self.disk_state)
+    }
+
+    pub(crate) fn text(&mut self) -> String {
+        self.load_all();
+        self.row_contents
+            .iter()
+            .flat_map(|row| [row.row_content.as_str(), self.line_ending.as_str()])
+            .collect()
+    }
+}
+
+impl Output {
+    pub(crate) fn check_disk(&mut self) -> crossterm::Result<()> {
+        let file = match &self.buffer.editor_rows.filename {
+            Some(file) => file.clone(),
+            None => return Ok(()),
+        };
+        let state = disk_state(&file);
+        if state == self.buffer.editor_rows.disk_state {
+            return Ok(());
+        }
+        self.buffer.editor_rows.disk_state = state;
+        if state.is_none() {
+            self.status_message
+                .set_message("The file was deleted on disk".into());
+            return Ok(());
+        }
+        if self.buffer.dirty == 0 {
+            self.reload(file);
+            return Ok(());
+        }
+        loop {
+            let answer = prompt!(
+                self,
+                "The file changed on disk: (k)eep your changes, (r)eload it or (d)iff? {}"
+            );
+            match answer.as_deref() {
+                Some("r") => self.reload(file),
+                Some("d") => self.show_disk_diff(&file),
+                Some("k") | None => {}
+                Some(_) => continue,
+            }
+            return Ok(());
+        }
+    }
+
+    fn reload(&mut self, file: PathBuf) {
+        match EditorRows::from_file(file, &self.config) {
+            Ok(mut editor_rows) => {
+                let old = &mut self.buffer.editor_rows;
+                editor_rows.soft_wrap = old.soft_wrap;
+                editor_rows.show_invisibles = old.show_invisibles;
+                editor_rows.spell = old.spell;
+                editor_rows.highlight_occurrences = old.highlight_occurrences;
+                editor_rows.color_column = old.color_column;
+                editor_rows.read_only = old.read_only;
+                // The language server still has the file open, it gets the
+                // new text as a change.
+                editor_rows.document = old.document.take().map(|mut document| {
+                    document.changes = None;
+                    document
+                });
+                self.buffer.editor_rows = editor_rows;
+                self.buffer.dirty = 0;
+                let editor_rows = &mut self.buffer.editor_rows;
+                let cursor_controller = &mut self.buffer.cursor_controller;
+                editor_rows.load_rows(cursor_controller.cursor_y);
+                let rows = editor_rows.number_of_rows();
+                cursor_controller.cursor_y = cmp::min(cursor_controller.cursor_y, rows);
+                let row = editor_rows
+                    .row_contents
+                    .get(cursor_controller.cursor_y)
+                    .map_or("", |row| row.row_content.as_str());
+                let mut x = cmp::min(cursor_controller.cursor_x, row.len());
+                while !row.is_char_boundary(x) {
+                    x -= 1;
+                }
+                cursor_controller.cursor_x = x;
+                cursor_controller.anchor = None;
+                cursor_controller.block = None;
+                self.status_message
+                    .set_message("Reloaded, the file changed on disk".into());
+            }
+            Err(err) => self
+                .status_message
+                .set_message(format!("Can't reload: {}", err)),
+        }
+    }
+
+    fn show_disk_diff(&mut self, file: &Path) {
+        let text = self.buffer.editor_rows.text();
+        match diff(file, text) {
+            Ok(diff) => self.open_scratch(&diff),
+            Err(err) => self
+                .status_message
+                .set_message(format!("Can't diff: {}", err)),
+        }
+    }
+
+    pub(crate) fn open_scratch(&mut self, text: &str) {
+        let mut editor_rows = EditorRows::empty(&self.config);
+        for (y, line) in text.lines().enumerate() {
+            editor_rows.insert_row(y, line.into());
+        }
+        editor_rows.history = History::new();
+        editor_rows.read_only = true;
+        self.buffers
+            .push(Buffer::new(editor_rows, self.win_size, &self.config));
+        self.switch_buffer(self.buffers.len());
+    }
+}
+
+fn diff(file: &Path, text: String) -> io::Result<String> {
+    let mut child = Command::new("diff")
+        .arg("-u")
+        .arg(file)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || stdin.write_all(text.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer.join().unwrap()?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn save_to_another_path() {
+        let dir = std::env::temp_dir().join(format!("pound-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (file, other) = (dir.join("file.txt"), dir.join("other.txt"));
+        fs::write(&file, "one\ntwo\n").unwrap();
+        fs::write(&other, "old\n").unwrap();
+        let config = Config {
+            backup: crate::backup::Backup::Off,
+            ..Config::default()
+        };
+        let mut editor_rows = EditorRows::from_file(file, &config).unwrap();
+        assert!(!editor_rows.changed_on_disk());
+        editor_rows.set_filename(other.clone());
+        assert!(!editor_rows.changed_on_disk());
+        editor_rows.save_with(crate::atomic::write, true).unwrap();
+        assert_eq!(fs::read_to_string(&other).unwrap(), "one\ntwo\n");
+        fs::write(&other, "changed elsewhere\n").unwrap();
+        assert!(editor_rows.changed_on_disk());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
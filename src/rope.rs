@@ -0,0 +1,191 @@
+use std::cmp;
+use std::iter::FromIterator;
+use std::ops::{Index, IndexMut};
+
+const CHUNK_SIZE: usize = 512;
+
+/// Chunks of rows with a Fenwick tree over their lengths, to find a row in O(log n).
+pub(crate) struct Rope<T> {
+    chunks: Vec<Vec<T>>,
+    tree: Vec<usize>,
+    len: usize,
+}
+
+impl<T> Rope<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            tree: vec![0],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let (chunk, offset) = self.locate(index);
+        self.chunks[chunk].get(offset)
+    }
+
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let (chunk, offset) = self.locate(index);
+        self.chunks[chunk].get_mut(offset)
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.len, "insertion index out of bounds");
+        if self.chunks.is_empty() {
+            self.chunks.push(Vec::with_capacity(CHUNK_SIZE));
+            self.rebuild();
+        }
+        let (chunk, offset) = match self.locate(index) {
+            (chunk, _) if chunk == self.chunks.len() => (chunk - 1, self.chunks[chunk - 1].len()),
+            location => location,
+        };
+        self.chunks[chunk].insert(offset, value);
+        self.len += 1;
+        if self.chunks[chunk].len() > 2 * CHUNK_SIZE {
+            let tail = self.chunks[chunk].split_off(CHUNK_SIZE);
+            self.chunks.insert(chunk + 1, tail);
+            self.rebuild();
+        } else {
+            self.add(chunk, 1);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index out of bounds");
+        let (chunk, offset) = self.locate(index);
+        let value = self.chunks[chunk].remove(offset);
+        self.len -= 1;
+        if self.chunks[chunk].is_empty() {
+            self.chunks.remove(chunk);
+            self.rebuild();
+        } else {
+            self.sub(chunk, 1);
+        }
+        value
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks.iter().flatten()
+    }
+
+    pub(crate) fn iter_mut_from(&mut self, index: usize) -> impl Iterator<Item = &mut T> {
+        let (chunk, offset) = self.locate(index);
+        let start = cmp::min(chunk, self.chunks.len());
+        let mut tail = self.chunks[start..].iter_mut();
+        let first = tail.next().map_or(&mut [][..], |rows| &mut rows[offset..]);
+        first.iter_mut().chain(tail.flatten())
+    }
+
+    fn locate(&self, index: usize) -> (usize, usize) {
+        let mut position = 0;
+        let mut remaining = index;
+        let mut step = (self.tree.len() - 1).next_power_of_two();
+        while step > 0 {
+            let next = position + step;
+            if next < self.tree.len() && self.tree[next] <= remaining {
+                position = next;
+                remaining -= self.tree[next];
+            }
+            step /= 2;
+        }
+        (position, remaining)
+    }
+
+    fn add(&mut self, chunk: usize, amount: usize) {
+        let mut node = chunk + 1;
+        while node < self.tree.len() {
+            self.tree[node] += amount;
+            node += node & node.wrapping_neg();
+        }
+    }
+
+    fn sub(&mut self, chunk: usize, amount: usize) {
+        let mut node = chunk + 1;
+        while node < self.tree.len() {
+            self.tree[node] -= amount;
+            node += node & node.wrapping_neg();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.tree = vec![0; self.chunks.len() + 1];
+        for chunk in 0..self.chunks.len() {
+            self.add(chunk, self.chunks[chunk].len());
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Rope<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(rows: I) -> Self {
+        let mut rows = rows.into_iter();
+        let mut rope = Self::new();
+        loop {
+            let chunk: Vec<T> = rows.by_ref().take(CHUNK_SIZE).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            rope.len += chunk.len();
+            rope.chunks.push(chunk);
+        }
+        rope.rebuild();
+        rope
+    }
+}
+
+impl<T> Index<usize> for Rope<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("row index out of bounds")
+    }
+}
+
+impl<T> IndexMut<usize> for Rope<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("row index out of bounds")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_remove_across_chunks() {
+        let mut rope: Rope<usize> = (0..3000).collect();
+        let mut vec: Vec<usize> = (0..3000).collect();
+        for i in 0..2000 {
+            let at = i * 7 % (vec.len() + 1);
+            rope.insert(at, 10_000 + i);
+            vec.insert(at, 10_000 + i);
+        }
+        for i in 0..2500 {
+            let at = i * 13 % vec.len();
+            assert_eq!(rope.remove(at), vec.remove(at));
+        }
+        assert_eq!(rope.len(), vec.len());
+        assert!(rope.iter().eq(vec.iter()));
+        assert_eq!(rope.get(vec.len()), None);
+    }
+
+    #[test]
+    fn iter_mut_from_middle() {
+        let mut rope: Rope<usize> = (0..2000).collect();
+        rope.iter_mut_from(1500).for_each(|value| *value = 0);
+        assert_eq!(rope[1499], 1499);
+        assert_eq!(rope.iter().filter(|&&value| value == 0).count(), 501);
+        rope[3] = 7;
+        assert_eq!(rope.get_mut(3).copied(), Some(7));
+    }
+}
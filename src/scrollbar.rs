@@ -0,0 +1,59 @@
+use std::cmp;
+use std::ops::Range;
+
+use crossterm::style::Color;
+use crossterm::{cursor, queue};
+
+use crate::window::Rect;
+use crate::Output;
+
+/// At either end only when the window shows that end of the file.
+fn thumb(offset: usize, height: usize, rows: usize) -> Range<usize> {
+    if rows <= height {
+        return 0..height;
+    }
+    let size = cmp::max(height * height / rows, 1);
+    let start = if offset + height >= rows {
+        height - size
+    } else if offset == 0 {
+        0
+    } else {
+        (offset * height / rows).clamp(1, cmp::max(height.saturating_sub(size + 1), 1))
+    };
+    start..start + size
+}
+
+impl Output {
+    pub(crate) fn scrollbar_width(&self, width: usize) -> usize {
+        (self.config.scrollbar && width > 1) as usize
+    }
+
+    pub(crate) fn draw_scrollbar(&mut self, window: usize, rect: Rect) {
+        if self.scrollbar_width(rect.width) == 0 {
+            return;
+        }
+        let height = rect.height - 1;
+        let cursor_controller = self.window_cursor(window);
+        let rows = match self.buffer_slot(self.window_buffer(window)) {
+            Some(slot) => self.buffers[slot].editor_rows.number_of_rows(),
+            None => self.buffer.editor_rows.number_of_rows(),
+        };
+        let thumb = thumb(cursor_controller.row_offset, height, rows);
+        for y in 0..height {
+            queue!(
+                self.editor_contents,
+                cursor::MoveTo((rect.x + rect.width - 1) as u16, (rect.y + y) as u16)
+            )
+            .unwrap();
+            if thumb.contains(&y) {
+                self.editor_contents
+                    .set_foreground(self.theme.scrollbar_thumb);
+                self.editor_contents.push('┃');
+            } else {
+                self.editor_contents.set_foreground(self.theme.scrollbar);
+                self.editor_contents.push('│');
+            }
+        }
+        self.editor_contents.set_foreground(Color::Reset);
+    }
+}
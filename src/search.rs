@@ -0,0 +1,274 @@
+use std::cmp;
+
+use regex::Regex;
+
+use crate::EditorRows;
+
+#[derive(Clone, Copy)]
+pub(crate) enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+#[derive(Clone)]
+pub(crate) enum Pattern {
+    Plain(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub(crate) fn new(keyword: &str, regex: bool) -> Option<Self> {
+        if keyword.is_empty() {
+            return None;
+        }
+        if regex {
+            Regex::new(keyword).ok().map(Pattern::Regex)
+        } else {
+            Some(Pattern::Plain(keyword.into()))
+        }
+    }
+
+    pub(crate) fn find_at(&self, haystack: &str, start: usize) -> Option<(usize, usize)> {
+        match self {
+            Pattern::Plain(keyword) => haystack[start..]
+                .find(keyword.as_str())
+                .map(|at| (start + at, start + at + keyword.len())),
+            Pattern::Regex(regex) => regex.find_at(haystack, start).map(|m| (m.start(), m.end())),
+        }
+    }
+
+    /// The groups are captured in the whole of `haystack`, so anchors see around the match.
+    pub(crate) fn expand(&self, haystack: &str, start: usize, replacement: &str) -> String {
+        let regex = match self {
+            Pattern::Plain(_) => return replacement.into(),
+            Pattern::Regex(regex) => regex,
+        };
+        let mut locations = regex.capture_locations();
+        if regex
+            .captures_read_at(&mut locations, haystack, start)
+            .is_none()
+        {
+            return replacement.into();
+        }
+        let group = |name: &str| {
+            let index = name
+                .parse()
+                .ok()
+                .or_else(|| regex.capture_names().position(|group| group == Some(name)));
+            index
+                .and_then(|index| locations.get(index))
+                .map_or("", |(from, to)| &haystack[from..to])
+        };
+        let mut text = String::new();
+        let mut rest = replacement;
+        while let Some(dollar) = rest.find('$') {
+            text.push_str(&rest[..dollar]);
+            rest = &rest[dollar + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                text.push('$');
+                rest = after;
+                continue;
+            }
+            let braced = rest
+                .strip_prefix('{')
+                .and_then(|inner| Some(inner.split_at(inner.find('}')?)))
+                .filter(|(name, _)| !name.is_empty());
+            let (name, after) = match braced {
+                Some((name, after)) => (name, &after[1..]),
+                None => rest.split_at(
+                    rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(rest.len()),
+                ),
+            };
+            if name.is_empty() {
+                text.push('$');
+            } else {
+                text.push_str(group(name));
+            }
+            rest = after;
+        }
+        text.push_str(rest);
+        text
+    }
+
+    pub(crate) fn replace_all(&self, haystack: &str, replacement: &str) -> (String, usize) {
+        match self {
+            Pattern::Plain(keyword) => (
+                haystack.replace(keyword.as_str(), replacement),
+                haystack.matches(keyword.as_str()).count(),
+            ),
+            Pattern::Regex(regex) => (
+                regex.replace_all(haystack, replacement).into_owned(),
+                regex.find_iter(haystack).count(),
+            ),
+        }
+    }
+
+    fn rfind_before(&self, haystack: &str, end: usize) -> Option<(usize, usize)> {
+        self.ranges(haystack)
+            .into_iter()
+            .take_while(|&(start, _)| start < end)
+            .last()
+    }
+
+    pub(crate) fn ranges(&self, haystack: &str) -> Vec<(usize, usize)> {
+        match self {
+            Pattern::Plain(keyword) => haystack
+                .match_indices(keyword.as_str())
+                .map(|(at, _)| (at, at + keyword.len()))
+                .collect(),
+            Pattern::Regex(regex) => regex
+                .find_iter(haystack)
+                .map(|m| (m.start(), m.end()))
+                .collect(),
+        }
+    }
+}
+
+pub(crate) struct SearchIndex {
+    pub(crate) pattern: Option<Pattern>,
+    pub(crate) last_match: Option<(usize, usize)>,
+    pub(crate) direction: SearchDirection,
+    pub(crate) regex: bool,
+    pub(crate) highlight: Option<Pattern>,
+}
+
+impl SearchIndex {
+    pub(crate) fn new() -> Self {
+        Self {
+            pattern: None,
+            last_match: None,
+            direction: SearchDirection::Forward,
+            regex: false,
+            highlight: None,
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.last_match = None;
+        self.direction = SearchDirection::Forward;
+    }
+}
+
+pub(crate) fn find(
+    editor_rows: &EditorRows,
+    pattern: &Pattern,
+    from_y: usize,
+) -> Option<(usize, usize)> {
+    let number_of_rows = editor_rows.number_of_rows();
+    (0..number_of_rows)
+        .map(|i| (from_y + i) % number_of_rows)
+        .find_map(|y| {
+            pattern
+                .find_at(editor_rows.get_row(y), 0)
+                .map(|(x, _)| (x, y))
+        })
+}
+
+pub(crate) fn find_next(
+    editor_rows: &EditorRows,
+    pattern: &Pattern,
+    (x, y): (usize, usize),
+    direction: SearchDirection,
+) -> Option<(usize, usize)> {
+    let number_of_rows = editor_rows.number_of_rows();
+    if y >= number_of_rows {
+        return match direction {
+            SearchDirection::Forward => find(editor_rows, pattern, 0),
+            SearchDirection::Backward => {
+                find_next(editor_rows, pattern, (0, 0), SearchDirection::Backward)
+            }
+        };
+    }
+    let row = editor_rows.get_row(y);
+    match direction {
+        SearchDirection::Forward => {
+            let next_char = row[x..].chars().next().map_or(1, char::len_utf8);
+            let start = cmp::min(row.len(), x + next_char);
+            if let Some((found, _)) = pattern.find_at(row, start) {
+                return Some((found, y));
+            }
+            (1..=number_of_rows)
+                .map(|i| (y + i) % number_of_rows)
+                .find_map(|y| {
+                    pattern
+                        .find_at(editor_rows.get_row(y), 0)
+                        .map(|(x, _)| (x, y))
+                })
+        }
+        SearchDirection::Backward => {
+            if let Some((found, _)) = pattern.rfind_before(row, x) {
+                return Some((found, y));
+            }
+            (1..=number_of_rows)
+                .map(|i| (y + number_of_rows - i) % number_of_rows)
+                .find_map(|y| {
+                    let row = editor_rows.get_row(y);
+                    pattern
+                        .rfind_before(row, row.len() + 1)
+                        .map(|(x, _)| (x, y))
+                })
+        }
+    }
+}
+
+pub(crate) fn replace_all(
+    editor_rows: &mut EditorRows,
+    pattern: &Pattern,
+    replacement: &str,
+) -> usize {
+    (0..editor_rows.number_of_rows()).fold(0, |replaced, y| {
+        let (text, count) = pattern.replace_all(editor_rows.get_row(y), replacement);
+        if count > 0 {
+            editor_rows.replace_range(y, 0, editor_rows.get_row(y).len(), &text);
+        }
+        replaced + count
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_at() {
+        let plain = Pattern::new("a.", false).unwrap();
+        assert_eq!(plain.find_at("ab a.", 0), Some((3, 5)));
+        let regex = Pattern::new("a.", true).unwrap();
+        assert_eq!(regex.find_at("ab a.", 1), Some((3, 5)));
+        assert_eq!(regex.find_at("ab a.", 4), None);
+        assert!(Pattern::new("", false).is_none());
+        assert!(Pattern::new("(", true).is_none());
+    }
+
+    #[test]
+    fn expand_groups() {
+        let pattern = Pattern::new(r"\b(\w)(?P<r>\w*)", true).unwrap();
+        assert_eq!(pattern.expand("foo bar", 4, "${r}$1-$$-${}"), "arb-$-${}");
+        assert_eq!(pattern.expand("foo bar", 4, "$9$none$"), "$");
+        let plain = Pattern::new("o", false).unwrap();
+        assert_eq!(plain.expand("foo", 1, "$1"), "$1");
+    }
+
+    #[test]
+    fn expand_sees_the_whole_row() {
+        let anchored = Pattern::new(r"^(a)x", true).unwrap();
+        assert_eq!(anchored.expand("ax ax", 0, "[$1]"), "[a]");
+        assert_eq!(anchored.expand("ax ax", 3, "[$1]"), "[$1]");
+        let pattern = Pattern::new(r"(a)x", true).unwrap();
+        assert_eq!(pattern.expand("ax ax", 3, "[$1$2]"), "[a]");
+    }
+
+    #[test]
+    fn replace_all_and_ranges() {
+        let pattern = Pattern::new(r"(\d)", true).unwrap();
+        assert_eq!(
+            pattern.replace_all("a1b22", "<$1>"),
+            ("a<1>b<2><2>".into(), 3)
+        );
+        assert_eq!(pattern.ranges("a1b22"), [(1, 2), (3, 4), (4, 5)]);
+        assert_eq!(pattern.rfind_before("a1b22", 4), Some((3, 4)));
+        let plain = Pattern::new("aa", false).unwrap();
+        assert_eq!(plain.replace_all("aaa", "b"), ("ba".into(), 1));
+    }
+}
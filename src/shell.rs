@@ -0,0 +1,207 @@
+use std::io::{self, stdout, Write};
+use std::process::{self, Command, Stdio};
+use std::{cmp, fs, thread};
+
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, event, execute};
+
+use crate::Output;
+
+pub(crate) fn run(command: &str, merge: bool) -> io::Result<process::Output> {
+    terminal::disable_raw_mode()?;
+    execute!(
+        stdout(),
+        event::DisableMouseCapture,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0)
+    )?;
+    println!("$ {}", command);
+    let script = if merge {
+        format!("exec 2>&1\n{}", command)
+    } else {
+        command.into()
+    };
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output();
+    terminal::enable_raw_mode()?;
+    execute!(stdout(), event::EnableMouseCapture)?;
+    output
+}
+
+fn pipe(command: &str, input: String) -> io::Result<process::Output> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    // A filter may well stop reading early, like `head`.
+    writer.join().unwrap().ok();
+    Ok(output)
+}
+
+fn failure(command: &str, output: &process::Output) -> String {
+    let status = match output.status.code() {
+        Some(code) => format!("exited with {}", code),
+        None => "was killed".into(),
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.lines().next() {
+        Some(error) => format!("{} {}: {}", command, status, error),
+        None => format!("{} {}", command, status),
+    }
+}
+
+impl Output {
+    pub(crate) fn shell(&mut self, command: &str) {
+        if command.is_empty() {
+            self.status_message.set_message("Usage: !<command>".into());
+            return;
+        }
+        let output = match run(command, true) {
+            Ok(output) => output,
+            Err(err) => {
+                self.status_message
+                    .set_message(format!("Can't run {}: {}", command, err));
+                return;
+            }
+        };
+        let text = String::from_utf8_lossy(&output.stdout);
+        if !text.is_empty() {
+            self.open_scratch(&text);
+        }
+        let message = if !output.status.success() {
+            failure(command, &output)
+        } else if text.is_empty() {
+            format!("{} printed nothing", command)
+        } else {
+            String::new()
+        };
+        self.status_message.set_message(message);
+    }
+
+    pub(crate) fn read(&mut self, source: &str) {
+        if source.is_empty() {
+            self.status_message
+                .set_message("Usage: r <file> or r !<command>".into());
+            return;
+        }
+        if self.reject_edit() {
+            return;
+        }
+        let text = match source.strip_prefix('!') {
+            Some(command) => {
+                let command = command.trim();
+                match run(command, false) {
+                    Ok(output) if output.status.success() => {
+                        String::from_utf8_lossy(&output.stdout).into_owned()
+                    }
+                    Ok(output) => {
+                        self.status_message.set_message(failure(command, &output));
+                        return;
+                    }
+                    Err(err) => {
+                        self.status_message
+                            .set_message(format!("Can't run {}: {}", command, err));
+                        return;
+                    }
+                }
+            }
+            None => match fs::read(source) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Err(err) => {
+                    self.status_message
+                        .set_message(format!("Can't read {}: {}", source, err));
+                    return;
+                }
+            },
+        };
+        self.insert_lines_below(&text);
+    }
+
+    pub(crate) fn filter(&mut self, command: &str) {
+        if command.is_empty() {
+            self.status_message
+                .set_message("Usage: filter <command>".into());
+            return;
+        }
+        if self.reject_edit() {
+            return;
+        }
+        self.buffer.editor_rows.load_all();
+        let rows = self.buffer.editor_rows.number_of_rows();
+        let (first, last) = match self.selection() {
+            Some((start, end)) => (start.1, cmp::min(end.1, rows.saturating_sub(1))),
+            None => (0, rows.saturating_sub(1)),
+        };
+        let end = cmp::min(last + 1, rows);
+        let input: String = (first..end)
+            .map(|y| self.buffer.editor_rows.get_row(y).to_string() + "\n")
+            .collect();
+        let output = match pipe(command, input) {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                self.status_message.set_message(failure(command, &output));
+                return;
+            }
+            Err(err) => {
+                self.status_message
+                    .set_message(format!("Can't run {}: {}", command, err));
+                return;
+            }
+        };
+        let text = String::from_utf8_lossy(&output.stdout);
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.history.commit();
+        editor_rows.delete_rows(first, last);
+        for (i, line) in text.lines().enumerate() {
+            editor_rows.insert_row(first + i, line.into());
+        }
+        editor_rows.history.commit();
+        self.clear_selection();
+        self.buffer.cursor_controller.cursor_y = first;
+        self.buffer.cursor_controller.cursor_x = 0;
+        self.buffer.dirty += 1;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = match stderr.lines().next() {
+            Some(error) => format!("{}: {}", command, error),
+            None => format!("Filtered {} lines through {}", end - first, command),
+        };
+        self.status_message.set_message(message);
+    }
+
+    fn insert_lines_below(&mut self, text: &str) {
+        let editor_rows = &mut self.buffer.editor_rows;
+        let y = cmp::min(
+            self.buffer.cursor_controller.cursor_y + 1,
+            editor_rows.number_of_rows(),
+        );
+        editor_rows.history.commit();
+        let mut count = 0;
+        for line in text.lines() {
+            editor_rows.insert_row(y + count, line.into());
+            count += 1;
+        }
+        editor_rows.history.commit();
+        if count == 0 {
+            return;
+        }
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        cursor_controller.cursor_y = y;
+        cursor_controller.cursor_x = 0;
+        cursor_controller.anchor = None;
+        cursor_controller.block = None;
+        self.buffer.dirty += 1;
+        self.status_message
+            .set_message(format!("Inserted {} lines", count));
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::syntax::HighlightType;
+use crate::{Output, Row};
+
+const DICTIONARIES: [&str; 3] = [
+    "/usr/share/hunspell/en_US.dic",
+    "/usr/share/myspell/en_US.dic",
+    "/usr/share/dict/words",
+];
+const MAX_SUGGESTIONS: usize = 10;
+
+pub(crate) struct Dictionary {
+    words: HashSet<String>,
+    letters: Vec<char>,
+}
+
+#[derive(Clone, Copy)]
+enum FlagType {
+    Char,
+    Long,
+    Number,
+}
+
+impl FlagType {
+    fn split(self, flags: &str) -> Vec<String> {
+        match self {
+            FlagType::Char => flags.chars().map(String::from).collect(),
+            FlagType::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars.chunks(2).map(|flag| flag.iter().collect()).collect()
+            }
+            FlagType::Number => flags.split(',').map(|flag| flag.trim().into()).collect(),
+        }
+    }
+}
+
+struct Class {
+    chars: Vec<char>,
+    negated: bool,
+}
+
+impl Class {
+    fn parse(condition: &str) -> Vec<Self> {
+        let mut classes = Vec::new();
+        let mut chars = condition.chars();
+        while let Some(c) = chars.next() {
+            classes.push(match c {
+                '.' => Self {
+                    chars: Vec::new(),
+                    negated: true,
+                },
+                '[' => {
+                    let mut set: Vec<char> = chars.by_ref().take_while(|&c| c != ']').collect();
+                    let negated = set.first() == Some(&'^');
+                    if negated {
+                        set.remove(0);
+                    }
+                    Self {
+                        chars: set,
+                        negated,
+                    }
+                }
+                c => Self {
+                    chars: vec![c],
+                    negated: false,
+                },
+            });
+        }
+        classes
+    }
+
+    fn matches(&self, c: char) -> bool {
+        self.chars.contains(&c) != self.negated
+    }
+}
+
+struct Affix {
+    prefix: bool,
+    cross: bool,
+    strip: String,
+    add: String,
+    condition: Vec<Class>,
+}
+
+impl Affix {
+    fn apply(&self, word: &str) -> Option<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let n = self.condition.len();
+        if chars.len() < n || word.chars().count() <= self.strip.chars().count() {
+            return None;
+        }
+        if self.prefix {
+            let stem = word.strip_prefix(self.strip.as_str())?;
+            let fits = self
+                .condition
+                .iter()
+                .zip(&chars)
+                .all(|(class, &c)| class.matches(c));
+            fits.then(|| format!("{}{}", self.add, stem))
+        } else {
+            let stem = word.strip_suffix(self.strip.as_str())?;
+            let end = &chars[chars.len() - n..];
+            let fits = self
+                .condition
+                .iter()
+                .zip(end)
+                .all(|(class, &c)| class.matches(c));
+            fits.then(|| format!("{}{}", stem, self.add))
+        }
+    }
+}
+
+fn decode(bytes: &[u8], utf8: bool) -> String {
+    if utf8 {
+        String::from_utf8_lossy(bytes).into()
+    } else {
+        bytes.iter().map(|&byte| byte as char).collect()
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '\''
+}
+
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut start = None;
+    text.char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+        .filter_map(move |(i, c)| match (start, is_word(c)) {
+            (None, true) => {
+                start = Some(i);
+                None
+            }
+            (Some(from), false) => {
+                start = None;
+                Some((from, &text[from..i]))
+            }
+            _ => None,
+        })
+        .filter_map(|(from, word)| {
+            let trimmed = word.trim_start_matches('\'');
+            let from = from + word.len() - trimmed.len();
+            let word = trimmed.trim_end_matches('\'');
+            let checked = word.chars().count() > 1
+                && word.chars().all(char::is_alphabetic)
+                && !word.chars().skip(1).any(char::is_uppercase);
+            checked.then(|| (from, word))
+        })
+}
+
+fn match_case(word: &str, like: &str) -> String {
+    match like.chars().next() {
+        Some(first) if first.is_uppercase() => {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        _ => word.into(),
+    }
+}
+
+impl Dictionary {
+    pub(crate) fn load(path: Option<&Path>) -> io::Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => DICTIONARIES
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.exists())
+                .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no dictionary found"))?,
+        };
+        let affixes = path.with_extension("aff");
+        let mut dictionary =
+            if path.extension().map_or(false, |ext| ext == "dic") && affixes.exists() {
+                Self::hunspell(&fs::read(&path)?, &fs::read(&affixes)?)
+            } else {
+                let words = String::from_utf8_lossy(&fs::read(&path)?)
+                    .lines()
+                    .map(|line| line.split('/').next().unwrap_or("").trim().into())
+                    .filter(|word: &String| !word.is_empty())
+                    .collect();
+                Self {
+                    words,
+                    letters: ('a'..='z').chain(['\'']).collect(),
+                }
+            };
+        match Self::personal().map(fs::read_to_string) {
+            Some(Ok(personal)) => dictionary
+                .words
+                .extend(personal.lines().map(|line| line.trim().into())),
+            Some(Err(err)) if err.kind() != ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        Ok(dictionary)
+    }
+
+    fn hunspell(words: &[u8], affixes: &[u8]) -> Self {
+        let aff = String::from_utf8_lossy(affixes);
+        let utf8 = aff
+            .lines()
+            .find_map(|line| line.strip_prefix("SET "))
+            .map_or(true, |set| set.trim().eq_ignore_ascii_case("UTF-8"));
+        let aff = decode(affixes, utf8);
+        let mut flag_type = FlagType::Char;
+        let mut letters = Vec::new();
+        let mut affixes: HashMap<String, Vec<Affix>> = HashMap::new();
+        let mut cross = HashMap::new();
+        // Flags of words only valid with an affix, or not at all.
+        let mut bare = HashSet::new();
+        let mut forbidden = None;
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", "long"] => flag_type = FlagType::Long,
+                ["FLAG", "num"] => flag_type = FlagType::Number,
+                ["TRY", chars] => letters = chars.to_lowercase().chars().collect(),
+                ["NEEDAFFIX" | "ONLYINCOMPOUND", flag] => {
+                    bare.insert(flag.to_string());
+                }
+                ["FORBIDDENWORD", flag] => forbidden = Some(flag.to_string()),
+                [kind @ ("PFX" | "SFX"), flag, rest @ ..] => match cross.get(*flag) {
+                    None => {
+                        cross.insert(flag.to_string(), rest.first() == Some(&"Y"));
+                    }
+                    Some(&allowed) => {
+                        let field = |i: usize| match rest.get(i) {
+                            Some(&"0") | None => "",
+                            Some(field) => field,
+                        };
+                        let add = field(1).split('/').next().unwrap_or("");
+                        affixes.entry(flag.to_string()).or_default().push(Affix {
+                            prefix: *kind == "PFX",
+                            cross: allowed,
+                            strip: field(0).into(),
+                            add: add.into(),
+                            condition: Class::parse(rest.get(2).copied().unwrap_or(".")),
+                        });
+                    }
+                },
+                _ => {}
+            }
+        }
+        let mut expanded = HashSet::new();
+        for entry in decode(words, utf8).lines().skip(1) {
+            let entry = entry.split(|c| c == '\t' || c == ' ').next().unwrap_or("");
+            let (stem, flags) = match entry.split_once('/') {
+                Some((stem, flags)) => (stem, flag_type.split(flags)),
+                None => (entry, Vec::new()),
+            };
+            if stem.is_empty()
+                || forbidden
+                    .as_ref()
+                    .map_or(false, |flag| flags.contains(flag))
+            {
+                continue;
+            }
+            if !flags.iter().any(|flag| bare.contains(flag)) {
+                expanded.insert(stem.to_string());
+            }
+            let rules: Vec<&Affix> = flags
+                .iter()
+                .filter_map(|flag| affixes.get(flag))
+                .flatten()
+                .collect();
+            for suffix in rules.iter().filter(|affix| !affix.prefix) {
+                if let Some(word) = suffix.apply(stem) {
+                    if suffix.cross {
+                        for prefix in rules.iter().filter(|affix| affix.prefix && affix.cross) {
+                            expanded.extend(prefix.apply(&word));
+                        }
+                    }
+                    expanded.insert(word);
+                }
+            }
+            for prefix in rules.iter().filter(|affix| affix.prefix) {
+                expanded.extend(prefix.apply(stem));
+            }
+        }
+        for c in 'a'..='z' {
+            if !letters.contains(&c) {
+                letters.push(c);
+            }
+        }
+        Self {
+            words: expanded,
+            letters,
+        }
+    }
+
+    fn personal() -> Option<PathBuf> {
+        Some(config::config_dir()?.join("words"))
+    }
+
+    fn check(&self, word: &str) -> bool {
+        self.words.contains(word)
+            || self.words.contains(&word.to_lowercase())
+            || word
+                .strip_suffix("'s")
+                .map_or(false, |stem| self.check(stem))
+    }
+
+    fn suggest(&self, word: &str) -> Vec<String> {
+        let lowercase = word.to_lowercase();
+        let near = self.edits(&lowercase);
+        let mut found: Vec<String> = near
+            .iter()
+            .filter(|near| self.check(near))
+            .cloned()
+            .collect();
+        if found.is_empty() {
+            found = near
+                .iter()
+                .flat_map(|near| self.edits(near))
+                .filter(|farther| self.check(farther))
+                .collect();
+        }
+        let mut seen = HashSet::new();
+        found
+            .into_iter()
+            .map(|candidate| match_case(&candidate, word))
+            .filter(|candidate| *candidate != word && seen.insert(candidate.clone()))
+            .take(MAX_SUGGESTIONS)
+            .collect()
+    }
+
+    fn edits(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let joined = |parts: &[&[char]]| parts.concat().into_iter().collect::<String>();
+        let mut edits = Vec::new();
+        for i in 1..chars.len() {
+            edits.push(joined(&[
+                &chars[..i - 1],
+                &[chars[i], chars[i - 1]],
+                &chars[i + 1..],
+            ]));
+        }
+        for i in 0..chars.len() {
+            for &c in &self.letters {
+                if c != chars[i] {
+                    edits.push(joined(&[&chars[..i], &[c], &chars[i + 1..]]));
+                }
+            }
+        }
+        for i in 0..chars.len() {
+            edits.push(joined(&[&chars[..i], &chars[i + 1..]]));
+        }
+        for i in 0..=chars.len() {
+            for &c in &self.letters {
+                edits.push(joined(&[&chars[..i], &[c], &chars[i..]]));
+            }
+        }
+        edits
+    }
+}
+
+struct Suggestions {
+    buffer: usize,
+    start: (usize, usize),
+    original: String,
+    words: Vec<String>,
+    /// `words.len()` for the original word.
+    current: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct Spelling {
+    dictionary: Option<Dictionary>,
+    failed: bool,
+    suggestions: Option<Suggestions>,
+}
+
+impl Spelling {
+    pub(crate) fn misspelled(&self, row: &Row, prose: bool) -> Vec<(usize, usize)> {
+        let dictionary = match &self.dictionary {
+            Some(dictionary) => dictionary,
+            None => return Vec::new(),
+        };
+        words(&row.row_content)
+            .filter(|&(from, _)| {
+                let highlight = row.highlight.get(row.render_index(from));
+                match highlight {
+                    Some(HighlightType::Comment | HighlightType::MultilineComment) => true,
+                    Some(HighlightType::String | HighlightType::Attribute) => false,
+                    _ => prose,
+                }
+            })
+            .filter(|(_, word)| !dictionary.check(word))
+            .map(|(from, word)| (from, from + word.len()))
+            .collect()
+    }
+}
+
+impl Output {
+    pub(crate) fn update_spelling(&mut self) {
+        if self.buffer.editor_rows.spell {
+            self.load_dictionary();
+        }
+    }
+
+    fn load_dictionary(&mut self) -> bool {
+        let spelling = &mut self.spelling;
+        if spelling.dictionary.is_none() && !spelling.failed {
+            match Dictionary::load(self.config.dictionary.as_deref()) {
+                Ok(dictionary) => spelling.dictionary = Some(dictionary),
+                Err(err) => {
+                    spelling.failed = true;
+                    self.status_message
+                        .set_message(format!("Can't load dictionary: {}", err));
+                }
+            }
+        }
+        self.spelling.dictionary.is_some()
+    }
+
+    pub(crate) fn enable_spelling(&mut self, enable: bool) {
+        self.buffer.editor_rows.spell = enable;
+        if enable {
+            self.spelling.failed = false;
+        }
+    }
+
+    fn word_at_cursor(&self) -> Option<(usize, String)> {
+        let cursor_controller = &self.buffer.cursor_controller;
+        let (x, y) = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        if y >= self.buffer.editor_rows.number_of_rows() {
+            return None;
+        }
+        words(self.buffer.editor_rows.get_row(y))
+            .find(|&(from, word)| from <= x && x <= from + word.len())
+            .map(|(from, word)| (from, word.into()))
+    }
+
+    pub(crate) fn suggest_spelling(&mut self) {
+        if !self.load_dictionary() || self.reject_edit() {
+            return;
+        }
+        let cursor_controller = &self.buffer.cursor_controller;
+        let (x, y) = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let cycling = self
+            .spelling
+            .suggestions
+            .as_ref()
+            .map_or(false, |suggestions| {
+                let shown = suggestions
+                    .words
+                    .get(suggestions.current)
+                    .unwrap_or(&suggestions.original);
+                suggestions.buffer == self.current_buffer
+                    && (suggestions.start.0 + shown.len(), suggestions.start.1) == (x, y)
+            });
+        if !cycling {
+            let (start, word) = match self.word_at_cursor() {
+                Some(word) => word,
+                None => {
+                    self.status_message
+                        .set_message("No word under the cursor".into());
+                    return;
+                }
+            };
+            let dictionary = self.spelling.dictionary.as_ref().unwrap();
+            if dictionary.check(&word) {
+                self.status_message
+                    .set_message(format!("\"{}\" is spelled correctly", word));
+                return;
+            }
+            let words = dictionary.suggest(&word);
+            if words.is_empty() {
+                self.status_message
+                    .set_message(format!("No suggestions for \"{}\"", word));
+                return;
+            }
+            self.spelling.suggestions = Some(Suggestions {
+                buffer: self.current_buffer,
+                start: (start, y),
+                current: words.len(),
+                original: word,
+                words,
+            });
+        }
+        let suggestions = self.spelling.suggestions.as_mut().unwrap();
+        let shown = suggestions
+            .words
+            .get(suggestions.current)
+            .unwrap_or(&suggestions.original)
+            .len();
+        suggestions.current = (suggestions.current + 1) % (suggestions.words.len() + 1);
+        let (replacement, message) = match suggestions.words.get(suggestions.current) {
+            Some(word) => (
+                word.clone(),
+                format!(
+                    "Suggestion {} of {}",
+                    suggestions.current + 1,
+                    suggestions.words.len()
+                ),
+            ),
+            None => (suggestions.original.clone(), "Back to the original".into()),
+        };
+        let start = suggestions.start;
+        let editor_rows = &mut self.buffer.editor_rows;
+        editor_rows.history.commit();
+        editor_rows.delete_range(start, (start.0 + shown, start.1));
+        self.buffer.cursor_controller.cursor_x = start.0;
+        self.insert_text(&replacement);
+        self.buffer.editor_rows.history.commit();
+        self.status_message.set_message(message);
+    }
+
+    pub(crate) fn add_word(&mut self) {
+        if !self.load_dictionary() {
+            return;
+        }
+        let word = match self.word_at_cursor() {
+            Some((_, word)) => word,
+            None => {
+                self.status_message
+                    .set_message("No word under the cursor".into());
+                return;
+            }
+        };
+        let saved = Dictionary::personal()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no config directory"))
+            .and_then(|path| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", word)
+            });
+        let message = match saved {
+            Ok(()) => format!("Added \"{}\" to the dictionary", word),
+            Err(err) => format!("Can't save \"{}\": {}", word, err),
+        };
+        self.status_message.set_message(message);
+        self.spelling
+            .dictionary
+            .as_mut()
+            .unwrap()
+            .words
+            .insert(word);
+    }
+}
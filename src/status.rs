@@ -0,0 +1,150 @@
+use std::mem;
+
+use crate::encoding::Encoding;
+use crate::{recorder, Output};
+
+pub(crate) const DEFAULT_FORMAT: &str =
+    "%(%M %)%(%q %)%f%( %r%)%( %b%) %m -- %L lines%( %s%)%=%y%( | %e%) | %i | %n | %l:%c";
+
+/// The text left and right of `%=`. A `%(` group is dropped when its segments are empty.
+pub(crate) fn expand(format: &str, segment: impl Fn(char) -> Option<String>) -> (String, String) {
+    let mut left = None;
+    // The text of each group opened, and whether a segment in it has text.
+    let mut groups = vec![(String::new(), true)];
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        let depth = groups.len();
+        let (text, shown) = groups.last_mut().unwrap();
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => text.push('%'),
+            Some('(') => groups.push((String::new(), false)),
+            Some(')') if depth > 1 => close_group(&mut groups),
+            Some('=') if depth == 1 && left.is_none() => left = Some(mem::take(text)),
+            Some(name) => match segment(name) {
+                Some(segment) => {
+                    *shown |= !segment.is_empty();
+                    text.push_str(&segment);
+                }
+                None => {
+                    text.push('%');
+                    text.push(name);
+                }
+            },
+            None => text.push('%'),
+        }
+    }
+    while groups.len() > 1 {
+        close_group(&mut groups);
+    }
+    let (rest, _) = groups.pop().unwrap();
+    match left {
+        Some(left) => (left, rest),
+        None => (rest, String::new()),
+    }
+}
+
+fn close_group(groups: &mut Vec<(String, bool)>) {
+    let (text, shown) = groups.pop().unwrap();
+    if shown {
+        let (outer, outer_shown) = groups.last_mut().unwrap();
+        outer.push_str(&text);
+        *outer_shown = true;
+    }
+}
+
+impl Output {
+    pub(crate) fn status_segment(&self, window: usize, name: char) -> Option<String> {
+        let cursor_controller = self.window_cursor(window);
+        let buffer_index = self.window_buffer(window);
+        let buffer = match self.buffer_slot(buffer_index) {
+            Some(slot) => &self.buffers[slot],
+            None => &self.buffer,
+        };
+        let editor_rows = &buffer.editor_rows;
+        let current = window == self.current_window;
+        let flag = |set: bool, text: &str| if set { text.into() } else { String::new() };
+        Some(match name {
+            'f' => buffer.name().into(),
+            'm' => flag(buffer.dirty > 0, "(modified)"),
+            'r' => flag(editor_rows.read_only, "[read-only]"),
+            'b' if self.buffers.is_empty() => String::new(),
+            'b' => format!("[{}/{}]", buffer_index + 1, self.buffers.len() + 1),
+            'M' => match self.mode {
+                Some(mode) if current => format!("[{}]", mode.name()),
+                _ => String::new(),
+            },
+            'q' => match recorder::recording() {
+                Some(name) if current => format!("[recording @{}]", name),
+                _ => String::new(),
+            },
+            's' => flag(self.search_index.regex, "[regex]"),
+            'L' => format!(
+                "{}{}",
+                editor_rows.number_of_rows(),
+                flag(editor_rows.loader.is_some(), "+")
+            ),
+            'y' => editor_rows
+                .syntax
+                .as_ref()
+                .map_or("no ft", |syntax| syntax.file_type())
+                .into(),
+            'e' => match editor_rows.encoding {
+                Encoding::Utf8 => String::new(),
+                encoding => encoding.name().into(),
+            },
+            'i' => editor_rows.indent.name(),
+            'n' => format!(
+                "{}{}",
+                flag(editor_rows.mixed_line_endings, "mixed "),
+                editor_rows.line_ending.name()
+            ),
+            'l' => (cursor_controller.cursor_y + 1).to_string(),
+            'c' => (cursor_controller.column(editor_rows) + 1).to_string(),
+            'p' => {
+                let rows = editor_rows.number_of_rows();
+                let line = cursor_controller.cursor_y + 1;
+                (if line >= rows { 100 } else { line * 100 / rows }).to_string()
+            }
+            'g' => editor_rows.branch.clone().unwrap_or_default(),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(name: char) -> Option<String> {
+        match name {
+            'f' => Some("main.rs".into()),
+            'm' => Some(String::new()),
+            'l' => Some("4".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn segments_and_sides() {
+        assert_eq!(
+            expand("%f 100%% %x%=%l", segment),
+            ("main.rs 100% %x".into(), "4".into())
+        );
+        assert_eq!(expand("%f%", segment), ("main.rs%".into(), String::new()));
+        assert_eq!(expand("%=a%=b", segment), (String::new(), "a%=b".into()));
+    }
+
+    #[test]
+    fn groups() {
+        assert_eq!(expand("[%( %m%)]", segment).0, "[]");
+        assert_eq!(expand("[%( %f%)]", segment).0, "[ main.rs]");
+        assert_eq!(expand("%(a%(%m%)b%)", segment).0, "");
+        assert_eq!(expand("%(a%(%l%)b%)", segment).0, "a4b");
+        assert_eq!(expand("%(%l", segment).0, "4");
+        assert_eq!(expand("%)", segment).0, "%)");
+    }
+}
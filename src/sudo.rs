@@ -0,0 +1,71 @@
+use std::io::{self, stdout, ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::{execute, terminal};
+
+use crate::backup::Backup;
+use crate::{prompt, Output, Reader};
+
+/// `sudo tee` keeps the file's inode, so also its mode and owner.
+fn write(file: &Path, bytes: &[u8]) -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(stdout(), terminal::Clear(terminal::ClearType::All))?;
+    println!("Saving {} with sudo", file.display());
+    let result = tee(file, bytes);
+    terminal::enable_raw_mode()?;
+    result
+}
+
+fn tee(file: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut child = Command::new("sudo")
+        .arg("tee")
+        .arg("--")
+        .arg(file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(bytes)?;
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(ErrorKind::Other, "sudo tee failed"))
+    }
+}
+
+impl Output {
+    pub(crate) fn save_denied(&mut self) -> crossterm::Result<()> {
+        loop {
+            let answer = prompt!(
+                self,
+                "Can't save, permission denied: save with (s)udo, save (a)s or (c)ancel? {}"
+            );
+            match answer.as_deref() {
+                Some("s") => {
+                    // A backup beside the file is denied along with the file.
+                    let backup = !matches!(self.buffer.editor_rows.backup, Backup::Beside);
+                    match self.write_buffer_with(write, backup) {
+                        Ok(len) => self.saved(len),
+                        Err(err) => self
+                            .status_message
+                            .set_message(format!("Can't save with sudo: {}", err)),
+                    }
+                    return Ok(());
+                }
+                Some("a") => match prompt!(self, "Save as: {}") {
+                    Some(file) => {
+                        self.buffer.editor_rows.set_filename(file.into());
+                        return self.save();
+                    }
+                    None => continue,
+                },
+                Some("c") | None => {
+                    self.status_message.set_message("Save aborted".into());
+                    return Ok(());
+                }
+                Some(_) => continue,
+            }
+        }
+    }
+}
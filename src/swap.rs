@@ -0,0 +1,131 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, iter, mem};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::backup::{cache_file, create_private};
+use crate::{prompt, Buffer, Editor, Output, Reader};
+
+const SWAP_IDLE: Duration = Duration::from_secs(2);
+
+fn swap_file(file: &Path) -> Option<PathBuf> {
+    cache_file("swap", file, ".swp")
+}
+
+impl Buffer {
+    fn update_swap(&mut self) -> io::Result<()> {
+        let swap = match self.editor_rows.filename.as_deref().and_then(swap_file) {
+            // Not to write over one left behind before recovery was offered.
+            Some(swap) if !self.editor_rows.read_only && self.swap_checked => swap,
+            _ => return Ok(()),
+        };
+        if self.dirty == 0 {
+            if self.swapped != 0 {
+                self.swapped = 0;
+                fs::remove_file(swap).ok();
+            }
+            return Ok(());
+        }
+        if self.dirty == self.swapped {
+            return Ok(());
+        }
+        self.write_swap(&swap)?;
+        self.swapped = self.dirty;
+        Ok(())
+    }
+
+    fn write_swap(&mut self, swap: &Path) -> io::Result<()> {
+        self.editor_rows.load_all();
+        fs::create_dir_all(swap.parent().unwrap())?;
+        let mut file = io::BufWriter::new(create_private(swap)?);
+        for row in self.editor_rows.row_contents.iter() {
+            file.write_all(row.row_content.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()
+    }
+
+    fn remove_swap(&self) {
+        if let Some(swap) = self.editor_rows.filename.as_deref().and_then(swap_file) {
+            if self.swapped != 0 {
+                fs::remove_file(swap).ok();
+            }
+        }
+    }
+}
+
+impl Editor {
+    pub(crate) fn update_swap(&mut self) {
+        if !self.output.config.swap || self.last_input.elapsed() < SWAP_IDLE {
+            return;
+        }
+        let output = &mut self.output;
+        let failed = iter::once(&mut output.buffer)
+            .chain(&mut output.buffers)
+            .filter_map(|buffer| buffer.update_swap().err())
+            .last();
+        if let Some(err) = failed {
+            output
+                .status_message
+                .set_message(format!("Can't write swap file: {}", err));
+        }
+    }
+}
+
+impl Output {
+    pub(crate) fn remove_swaps(&self) {
+        self.buffer.remove_swap();
+        self.buffers.iter().for_each(Buffer::remove_swap);
+    }
+
+    /// Asks once for each buffer, when it is first the current one.
+    pub(crate) fn offer_recovery(&mut self) -> crossterm::Result<()> {
+        if mem::replace(&mut self.buffer.swap_checked, true) {
+            return Ok(());
+        }
+        let swap = match self
+            .buffer
+            .editor_rows
+            .filename
+            .as_deref()
+            .and_then(swap_file)
+        {
+            Some(swap) if swap.exists() => swap,
+            _ => return Ok(()),
+        };
+        let answer = prompt!(
+            self,
+            "Found unsaved changes from a previous session. Recover them? (y/n) {}"
+        );
+        match answer.as_deref() {
+            Some("y" | "Y") => match fs::read_to_string(&swap) {
+                Ok(contents) => {
+                    let editor_rows = &mut self.buffer.editor_rows;
+                    editor_rows.load_all();
+                    let rows = editor_rows.number_of_rows();
+                    if rows > 0 {
+                        editor_rows.delete_rows(0, rows - 1);
+                    }
+                    for (y, line) in contents.lines().enumerate() {
+                        editor_rows.insert_row(y, line.into());
+                    }
+                    editor_rows.history.commit();
+                    self.buffer.dirty += 1;
+                    self.buffer.swapped = self.buffer.dirty;
+                    self.status_message
+                        .set_message("Recovered, save to keep the changes".into());
+                }
+                Err(err) => self
+                    .status_message
+                    .set_message(format!("Can't read swap file: {}", err)),
+            },
+            Some(_) => {
+                fs::remove_file(swap).ok();
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
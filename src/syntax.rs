@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use crate::rope::Rope;
+use crate::undo::Edit;
+use crate::Row;
+
+use generic::DEFINITIONS;
+use markdown::MarkdownHighlight;
+use rust::RustHighlight;
+#[cfg(feature = "tree-sitter-highlight")]
+use treesitter::TreeSitterHighlight;
+
+mod generic;
+mod markdown;
+mod rust;
+#[cfg(feature = "tree-sitter-highlight")]
+mod treesitter;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HighlightType {
+    Normal,
+    Number,
+    String,
+    CharLiteral,
+    Comment,
+    MultilineComment,
+    Keyword,
+    Type,
+    Lifetime,
+    Attribute,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RowState {
+    Normal,
+    MultilineComment,
+    NestedComment(usize),
+    String,
+    RawString(usize),
+    MultilineString(usize),
+    CodeBlock,
+}
+
+impl Default for RowState {
+    fn default() -> Self {
+        RowState::Normal
+    }
+}
+
+pub(crate) trait SyntaxHighlight {
+    fn file_type(&self) -> &str;
+
+    fn line_comment(&self) -> Option<&str> {
+        None
+    }
+
+    fn highlight_row(&self, render: &str, state: RowState) -> (Vec<HighlightType>, RowState);
+
+    fn edit(&mut self, _edit: &Edit, _rows: &Rope<Row>) {}
+
+    fn update_syntax(&mut self, at: usize, rows: &mut Rope<Row>) {
+        let mut state = match at {
+            0 => RowState::Normal,
+            _ => rows[at - 1].syntax_end,
+        };
+        for (index, row) in (at..).zip(rows.iter_mut_from(at)) {
+            if index > at && row.syntax_start == Some(state) {
+                break;
+            }
+            let (highlight, end) = self.highlight_row(&row.render, state);
+            row.highlight = highlight;
+            row.syntax_start = Some(state);
+            row.syntax_end = end;
+            state = end;
+        }
+    }
+}
+
+pub(crate) fn select(path: &Path, first_line: Option<&str>) -> Option<Box<dyn SyntaxHighlight>> {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let interpreter = first_line.and_then(shebang_interpreter);
+    if let Some("md" | "markdown") = extension {
+        return Some(Box::new(MarkdownHighlight));
+    }
+    DEFINITIONS
+        .iter()
+        .find(|definition| {
+            extension.map_or(false, |extension| {
+                definition.extensions.contains(&extension)
+            }) || interpreter.map_or(false, |interpreter| {
+                definition.interpreters.contains(&interpreter)
+            })
+        })
+        .map(|definition| -> Box<dyn SyntaxHighlight> {
+            match definition.file_type() {
+                // Rust has a highlighter of its own.
+                "rust" => rust_highlighter(),
+                _ => Box::new(*definition),
+            }
+        })
+}
+
+#[cfg(feature = "tree-sitter-highlight")]
+fn rust_highlighter() -> Box<dyn SyntaxHighlight> {
+    match TreeSitterHighlight::rust() {
+        Some(highlighter) => Box::new(highlighter),
+        None => Box::new(RustHighlight),
+    }
+}
+
+#[cfg(not(feature = "tree-sitter-highlight"))]
+fn rust_highlighter() -> Box<dyn SyntaxHighlight> {
+    Box::new(RustHighlight)
+}
+
+fn shebang_interpreter(line: &str) -> Option<&str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    match program {
+        "env" => words.find(|word| !word.starts_with('-')),
+        _ => Some(program),
+    }
+}
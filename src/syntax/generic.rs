@@ -0,0 +1,256 @@
+use super::{HighlightType, RowState, SyntaxHighlight};
+
+#[derive(Clone, Copy)]
+pub(crate) struct SyntaxDefinition {
+    file_type: &'static str,
+    pub(super) extensions: &'static [&'static str],
+    pub(super) interpreters: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    multiline_comment: Option<(&'static str, &'static str)>,
+    multiline_strings: &'static [&'static str],
+    string_quotes: &'static [char],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    section_headers: bool,
+}
+
+const RUST: SyntaxDefinition = SyntaxDefinition {
+    file_type: "rust",
+    extensions: &["rs"],
+    interpreters: &[],
+    line_comment: Some("//"),
+    multiline_comment: Some(("/*", "*/")),
+    multiline_strings: &[],
+    string_quotes: &['"', '\''],
+    keywords: &[],
+    types: &[],
+    section_headers: false,
+};
+
+const C: SyntaxDefinition = SyntaxDefinition {
+    file_type: "c",
+    extensions: &["c", "h"],
+    interpreters: &[],
+    line_comment: Some("//"),
+    multiline_comment: Some(("/*", "*/")),
+    multiline_strings: &[],
+    string_quotes: &['"', '\''],
+    keywords: &[
+        "auto", "break", "case", "const", "continue", "default", "do", "else", "enum", "extern",
+        "for", "goto", "if", "inline", "register", "return", "sizeof", "static", "struct",
+        "switch", "typedef", "union", "volatile", "while", "#include", "#define", "#ifdef",
+        "#ifndef", "#endif", "#if", "#else", "#pragma",
+    ],
+    types: &[
+        "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void", "bool",
+        "size_t", "NULL",
+    ],
+    section_headers: false,
+};
+
+const PYTHON: SyntaxDefinition = SyntaxDefinition {
+    file_type: "python",
+    extensions: &["py", "pyw"],
+    interpreters: &["python", "python2", "python3"],
+    line_comment: Some("#"),
+    multiline_comment: None,
+    multiline_strings: &["\"\"\"", "'''"],
+    string_quotes: &['"', '\''],
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+        "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with",
+        "yield",
+    ],
+    types: &[
+        "True", "False", "None", "self", "int", "str", "float", "list", "dict", "set", "tuple",
+        "bool", "bytes", "object",
+    ],
+    section_headers: false,
+};
+
+const JSON: SyntaxDefinition = SyntaxDefinition {
+    file_type: "json",
+    extensions: &["json"],
+    interpreters: &[],
+    line_comment: None,
+    multiline_comment: None,
+    multiline_strings: &[],
+    string_quotes: &['"'],
+    keywords: &["true", "false", "null"],
+    types: &[],
+    section_headers: false,
+};
+
+const TOML: SyntaxDefinition = SyntaxDefinition {
+    file_type: "toml",
+    extensions: &["toml"],
+    interpreters: &[],
+    line_comment: Some("#"),
+    multiline_comment: None,
+    multiline_strings: &["\"\"\"", "'''"],
+    string_quotes: &['"', '\''],
+    keywords: &["true", "false"],
+    types: &[],
+    section_headers: true,
+};
+
+pub(crate) const DEFINITIONS: &[SyntaxDefinition] = &[RUST, C, PYTHON, JSON, TOML];
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || ",.()+-/*=~%<>[]{};:&|!^?".contains(c)
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '#'
+}
+
+fn starts_with(chars: &[char], at: usize, token: &str) -> bool {
+    token
+        .chars()
+        .enumerate()
+        .all(|(offset, c)| chars.get(at + offset) == Some(&c))
+}
+
+fn scan_until(
+    chars: &[char],
+    highlight: &mut [HighlightType],
+    at: usize,
+    token: &str,
+    highlight_type: HighlightType,
+) -> Option<usize> {
+    let len = token.chars().count();
+    match (at..chars.len()).find(|&i| starts_with(chars, i, token)) {
+        Some(end) => {
+            highlight[at..end + len].fill(highlight_type);
+            Some(end + len)
+        }
+        None => {
+            highlight[at..].fill(highlight_type);
+            None
+        }
+    }
+}
+
+impl SyntaxHighlight for SyntaxDefinition {
+    fn file_type(&self) -> &str {
+        self.file_type
+    }
+
+    fn line_comment(&self) -> Option<&str> {
+        self.line_comment
+    }
+
+    fn highlight_row(&self, render: &str, state: RowState) -> (Vec<HighlightType>, RowState) {
+        let chars: Vec<char> = render.chars().collect();
+        let mut highlight = vec![HighlightType::Normal; chars.len()];
+        let resumed = match state {
+            RowState::MultilineComment => self
+                .multiline_comment
+                .map(|(_, end)| (end, HighlightType::MultilineComment)),
+            RowState::MultilineString(index) => self
+                .multiline_strings
+                .get(index)
+                .map(|&token| (token, HighlightType::String)),
+            _ => None,
+        };
+        let mut i = match resumed {
+            Some((token, highlight_type)) => {
+                match scan_until(&chars, &mut highlight, 0, token, highlight_type) {
+                    Some(end) => end,
+                    None => return (highlight, state),
+                }
+            }
+            None => 0,
+        };
+        if self.section_headers && chars[i..].iter().find(|c| !c.is_whitespace()) == Some(&'[') {
+            if let Some(end) = chars[i..]
+                .iter()
+                .rposition(|&c| c == ']')
+                .map(|end| i + end)
+            {
+                highlight[i..=end].fill(HighlightType::Type);
+                i = end + 1;
+            }
+        }
+        while i < chars.len() {
+            let c = chars[i];
+            let word_start = i == 0 || is_separator(chars[i - 1]);
+            if let Some(token) = self.line_comment {
+                if starts_with(&chars, i, token) {
+                    highlight[i..].fill(HighlightType::Comment);
+                    break;
+                }
+            }
+            if let Some((start, end)) = self.multiline_comment {
+                if starts_with(&chars, i, start) {
+                    let at = i + start.chars().count();
+                    highlight[i..at].fill(HighlightType::MultilineComment);
+                    match scan_until(
+                        &chars,
+                        &mut highlight,
+                        at,
+                        end,
+                        HighlightType::MultilineComment,
+                    ) {
+                        Some(end) => i = end,
+                        None => return (highlight, RowState::MultilineComment),
+                    }
+                    continue;
+                }
+            }
+            if let Some(index) = self
+                .multiline_strings
+                .iter()
+                .position(|token| starts_with(&chars, i, token))
+            {
+                let token = self.multiline_strings[index];
+                let at = i + token.chars().count();
+                highlight[i..at].fill(HighlightType::String);
+                match scan_until(&chars, &mut highlight, at, token, HighlightType::String) {
+                    Some(end) => i = end,
+                    None => return (highlight, RowState::MultilineString(index)),
+                }
+                continue;
+            }
+            if self.string_quotes.contains(&c) {
+                highlight[i] = HighlightType::String;
+                i += 1;
+                while i < chars.len() {
+                    highlight[i] = HighlightType::String;
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        highlight[i + 1] = HighlightType::String;
+                        i += 1;
+                    } else if chars[i] == c {
+                        break;
+                    }
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+            if word_start && c.is_ascii_digit() {
+                let mut end = i;
+                while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '.') {
+                    end += 1;
+                }
+                highlight[i..end].fill(HighlightType::Number);
+                i = end;
+                continue;
+            }
+            if word_start && is_word(c) {
+                let len = chars[i..].iter().take_while(|&&c| is_word(c)).count();
+                let word: String = chars[i..i + len].iter().collect();
+                if self.keywords.contains(&word.as_str()) {
+                    highlight[i..i + len].fill(HighlightType::Keyword);
+                } else if self.types.contains(&word.as_str()) {
+                    highlight[i..i + len].fill(HighlightType::Type);
+                }
+                i += len;
+                continue;
+            }
+            i += 1;
+        }
+        (highlight, RowState::Normal)
+    }
+}
@@ -0,0 +1,96 @@
+use super::{HighlightType, RowState, SyntaxHighlight};
+
+pub(crate) struct MarkdownHighlight;
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+impl SyntaxHighlight for MarkdownHighlight {
+    fn file_type(&self) -> &str {
+        "markdown"
+    }
+
+    fn highlight_row(&self, render: &str, state: RowState) -> (Vec<HighlightType>, RowState) {
+        let chars: Vec<char> = render.chars().collect();
+        let mut highlight = vec![HighlightType::Normal; chars.len()];
+        if state == RowState::CodeBlock || is_fence(render) {
+            highlight.fill(HighlightType::String);
+            let in_block = (state == RowState::CodeBlock) != is_fence(render);
+            return (
+                highlight,
+                if in_block {
+                    RowState::CodeBlock
+                } else {
+                    RowState::Normal
+                },
+            );
+        }
+        let indent = chars.iter().take_while(|c| c.is_whitespace()).count();
+        let line = &chars[indent..];
+        let heading = line.iter().take_while(|&&c| c == '#').count();
+        if (1..=6).contains(&heading) && line.get(heading).map_or(true, |c| c.is_whitespace()) {
+            highlight.fill(HighlightType::Keyword);
+            return (highlight, RowState::Normal);
+        }
+        if line.first() == Some(&'>') {
+            highlight.fill(HighlightType::Comment);
+            return (highlight, RowState::Normal);
+        }
+        let marker = match line {
+            ['-' | '*' | '+', ' ', ..] => 1,
+            _ => {
+                let digits = line.iter().take_while(|c| c.is_ascii_digit()).count();
+                match line.get(digits..digits + 2) {
+                    Some(['.' | ')', ' ']) if digits > 0 => digits + 1,
+                    _ => 0,
+                }
+            }
+        };
+        highlight[indent..indent + marker].fill(HighlightType::Number);
+        let mut i = indent + marker;
+        while i < chars.len() {
+            let closing = |delimiter: char, from: usize| {
+                chars[from..]
+                    .iter()
+                    .position(|&c| c == delimiter)
+                    .map(|end| from + end)
+            };
+            match chars[i] {
+                '`' => {
+                    if let Some(end) = closing('`', i + 1) {
+                        highlight[i..=end].fill(HighlightType::String);
+                        i = end + 1;
+                        continue;
+                    }
+                }
+                '[' => {
+                    if let Some(end) = closing(']', i + 1) {
+                        if chars.get(end + 1) == Some(&'(') {
+                            let end = closing(')', end + 1).unwrap_or(end);
+                            highlight[i..=end].fill(HighlightType::Attribute);
+                            i = end + 1;
+                            continue;
+                        }
+                    }
+                }
+                delimiter @ ('*' | '_') => {
+                    let strong = chars.get(i + 1) == Some(&delimiter);
+                    let from = if strong { i + 2 } else { i + 1 };
+                    if let Some(end) = closing(delimiter, from) {
+                        let end = if strong { end + 1 } else { end };
+                        if end > from && end < chars.len() {
+                            highlight[i..=end].fill(HighlightType::Type);
+                            i = end + 1;
+                            continue;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        (highlight, RowState::Normal)
+    }
+}
@@ -0,0 +1,237 @@
+use super::{HighlightType, RowState, SyntaxHighlight};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "union",
+    "unsafe", "use", "where", "while",
+];
+
+const TYPES: &[&str] = &[
+    "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32",
+    "u64", "u128", "usize", "str", "Self", "String", "Vec", "Box", "Option", "Result", "Some",
+    "None", "Ok", "Err",
+];
+
+pub(crate) struct RustHighlight;
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl RustHighlight {
+    fn scan_nested_comment(
+        chars: &[char],
+        highlight: &mut [HighlightType],
+        mut i: usize,
+        mut depth: usize,
+    ) -> (usize, RowState) {
+        while i < chars.len() {
+            highlight[i] = HighlightType::MultilineComment;
+            match (chars[i], chars.get(i + 1)) {
+                ('/', Some('*')) => {
+                    highlight[i + 1] = HighlightType::MultilineComment;
+                    depth += 1;
+                    i += 2;
+                }
+                ('*', Some('/')) => {
+                    highlight[i + 1] = HighlightType::MultilineComment;
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        return (i, RowState::Normal);
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        (i, RowState::NestedComment(depth))
+    }
+
+    fn scan_string(
+        chars: &[char],
+        highlight: &mut [HighlightType],
+        mut i: usize,
+    ) -> (usize, RowState) {
+        while i < chars.len() {
+            highlight[i] = HighlightType::String;
+            match chars[i] {
+                '\\' => {
+                    if let Some(escaped) = highlight.get_mut(i + 1) {
+                        *escaped = HighlightType::String;
+                    }
+                    i += 2;
+                }
+                '"' => return (i + 1, RowState::Normal),
+                _ => i += 1,
+            }
+        }
+        (i, RowState::String)
+    }
+
+    fn scan_raw_string(
+        chars: &[char],
+        highlight: &mut [HighlightType],
+        mut i: usize,
+        hashes: usize,
+    ) -> (usize, RowState) {
+        while i < chars.len() {
+            highlight[i] = HighlightType::String;
+            if chars[i] == '"' && (1..=hashes).all(|offset| chars.get(i + offset) == Some(&'#')) {
+                highlight[i..=i + hashes].fill(HighlightType::String);
+                return (i + hashes + 1, RowState::Normal);
+            }
+            i += 1;
+        }
+        (i, RowState::RawString(hashes))
+    }
+
+    fn raw_string_start(chars: &[char], i: usize) -> Option<(usize, usize)> {
+        let mut at = i;
+        if chars.get(at) == Some(&'b') {
+            at += 1;
+        }
+        if chars.get(at) != Some(&'r') {
+            return None;
+        }
+        at += 1;
+        let hashes = chars[at..].iter().take_while(|&&c| c == '#').count();
+        match chars.get(at + hashes) {
+            Some('"') => Some((at + hashes + 1 - i, hashes)),
+            _ => None,
+        }
+    }
+
+    fn char_literal_len(chars: &[char], i: usize) -> Option<usize> {
+        match chars.get(i + 1)? {
+            '\\' => chars
+                .get(i + 3..)?
+                .iter()
+                .position(|&c| c == '\'')
+                .map(|end| end + 4),
+            _ if chars.get(i + 2) == Some(&'\'') => Some(3),
+            _ => None,
+        }
+    }
+}
+
+impl SyntaxHighlight for RustHighlight {
+    fn file_type(&self) -> &str {
+        "rust"
+    }
+
+    fn line_comment(&self) -> Option<&str> {
+        Some("//")
+    }
+
+    fn highlight_row(&self, render: &str, state: RowState) -> (Vec<HighlightType>, RowState) {
+        let chars: Vec<char> = render.chars().collect();
+        let mut highlight = vec![HighlightType::Normal; chars.len()];
+        let (mut i, mut state) = match state {
+            RowState::NestedComment(depth) => {
+                Self::scan_nested_comment(&chars, &mut highlight, 0, depth)
+            }
+            RowState::String => Self::scan_string(&chars, &mut highlight, 0),
+            RowState::RawString(hashes) => Self::scan_raw_string(&chars, &mut highlight, 0, hashes),
+            _ => (0, RowState::Normal),
+        };
+        while i < chars.len() {
+            let c = chars[i];
+            let word_start = i == 0 || !is_identifier(chars[i - 1]);
+            match (c, chars.get(i + 1)) {
+                ('/', Some('/')) => {
+                    highlight[i..].fill(HighlightType::Comment);
+                    break;
+                }
+                ('/', Some('*')) => {
+                    highlight[i..i + 2].fill(HighlightType::MultilineComment);
+                    let (end, new_state) =
+                        Self::scan_nested_comment(&chars, &mut highlight, i + 2, 1);
+                    i = end;
+                    state = new_state;
+                    continue;
+                }
+                ('"', _) => {
+                    highlight[i] = HighlightType::String;
+                    let (end, new_state) = Self::scan_string(&chars, &mut highlight, i + 1);
+                    i = end;
+                    state = new_state;
+                    continue;
+                }
+                ('b', Some('"')) if word_start => {
+                    highlight[i] = HighlightType::String;
+                    i += 1;
+                    continue;
+                }
+                ('\'', _) => {
+                    if let Some(len) = Self::char_literal_len(&chars, i) {
+                        highlight[i..i + len].fill(HighlightType::CharLiteral);
+                        i += len;
+                        continue;
+                    }
+                    let len = 1 + chars[i + 1..]
+                        .iter()
+                        .take_while(|&&c| is_identifier(c))
+                        .count();
+                    highlight[i..i + len].fill(HighlightType::Lifetime);
+                    i += len;
+                    continue;
+                }
+                ('#', Some('[')) | ('#', Some('!')) => {
+                    let mut depth = 0;
+                    let len = chars[i..]
+                        .iter()
+                        .position(|&c| {
+                            match c {
+                                '[' => depth += 1,
+                                ']' => depth -= 1,
+                                _ => return false,
+                            }
+                            depth == 0
+                        })
+                        .map_or(chars.len() - i, |end| end + 1);
+                    highlight[i..i + len].fill(HighlightType::Attribute);
+                    i += len;
+                    continue;
+                }
+                _ => {}
+            }
+            if word_start && (c == 'r' || c == 'b') {
+                if let Some((len, hashes)) = Self::raw_string_start(&chars, i) {
+                    highlight[i..i + len].fill(HighlightType::String);
+                    let (end, new_state) =
+                        Self::scan_raw_string(&chars, &mut highlight, i + len, hashes);
+                    i = end;
+                    state = new_state;
+                    continue;
+                }
+            }
+            if word_start && c.is_ascii_digit() {
+                let mut end = i;
+                while end < chars.len()
+                    && (is_identifier(chars[end])
+                        || (chars[end] == '.'
+                            && chars.get(end + 1).map_or(false, char::is_ascii_digit)))
+                {
+                    end += 1;
+                }
+                highlight[i..end].fill(HighlightType::Number);
+                i = end;
+                continue;
+            }
+            if word_start && is_identifier(c) {
+                let len = chars[i..].iter().take_while(|&&c| is_identifier(c)).count();
+                let word: String = chars[i..i + len].iter().collect();
+                if KEYWORDS.contains(&word.as_str()) {
+                    highlight[i..i + len].fill(HighlightType::Keyword);
+                } else if TYPES.contains(&word.as_str()) {
+                    highlight[i..i + len].fill(HighlightType::Type);
+                }
+                i += len;
+                continue;
+            }
+            i += 1;
+        }
+        (highlight, state)
+    }
+}
@@ -0,0 +1,188 @@
+use std::cmp;
+
+use tree_sitter::{InputEdit, Parser, Point, Query, QueryCursor, Tree};
+
+use super::{HighlightType, RowState, SyntaxHighlight};
+use crate::rope::Rope;
+use crate::undo::Edit;
+use crate::Row;
+
+pub(crate) struct TreeSitterHighlight {
+    parser: Parser,
+    query: Query,
+    tree: Option<Tree>,
+    edited_rows: Option<(usize, usize)>,
+}
+
+impl TreeSitterHighlight {
+    pub(crate) fn rust() -> Option<Self> {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_rust::language()).ok()?;
+        let query = Query::new(
+            tree_sitter_rust::language(),
+            tree_sitter_rust::HIGHLIGHT_QUERY,
+        )
+        .ok()?;
+        Some(Self {
+            parser,
+            query,
+            tree: None,
+            edited_rows: None,
+        })
+    }
+
+    fn highlight_rows(&self, rows: &mut Rope<Row>, text: &str, start: usize, end: usize) {
+        let tree = match &self.tree {
+            Some(tree) => tree,
+            None => return,
+        };
+        rows.iter_mut_from(start)
+            .take(end + 1 - start)
+            .for_each(|row| {
+                row.highlight = vec![HighlightType::Normal; row.render.chars().count()]
+            });
+        let mut cursor = QueryCursor::new();
+        cursor.set_point_range(Point::new(start, 0)..Point::new(end + 1, 0));
+        for (query_match, index) in cursor.captures(&self.query, tree.root_node(), text.as_bytes())
+        {
+            let capture = query_match.captures[index];
+            let highlight_type =
+                match capture_highlight(&self.query.capture_names()[capture.index as usize]) {
+                    Some(highlight_type) => highlight_type,
+                    None => continue,
+                };
+            let (from, to) = (capture.node.start_position(), capture.node.end_position());
+            let first = cmp::max(from.row, start);
+            let last = cmp::min(to.row, end);
+            for (y, row) in (first..=last).zip(rows.iter_mut_from(first)) {
+                let len = row.row_content.len();
+                let from_x = if y == from.row {
+                    row.render_index(cmp::min(from.column, len))
+                } else {
+                    0
+                };
+                let to_x = if y == to.row {
+                    row.render_index(cmp::min(to.column, len))
+                } else {
+                    row.highlight.len()
+                };
+                if from_x < to_x {
+                    row.highlight[from_x..to_x].fill(highlight_type);
+                }
+            }
+        }
+    }
+}
+
+fn capture_highlight(name: &str) -> Option<HighlightType> {
+    let highlight_type = match name.split('.').next()? {
+        "comment" => HighlightType::Comment,
+        "string" | "escape" => HighlightType::String,
+        "constant" => HighlightType::Number,
+        "keyword" => HighlightType::Keyword,
+        "type" | "constructor" => HighlightType::Type,
+        "label" => HighlightType::Lifetime,
+        "attribute" => HighlightType::Attribute,
+        _ => return None,
+    };
+    Some(highlight_type)
+}
+
+fn row_offset(rows: &Rope<Row>, y: usize) -> usize {
+    rows.iter()
+        .take(y)
+        .map(|row| row.row_content.len() + 1)
+        .sum()
+}
+
+fn input_edit(rows: &Rope<Row>, y: usize, x: usize, removed: &str, inserted: &str) -> InputEdit {
+    let end_point = |text: &str| match text.rfind('\n') {
+        Some(newline) => Point::new(y + text.matches('\n').count(), text.len() - newline - 1),
+        None => Point::new(y, x + text.len()),
+    };
+    let start_byte = row_offset(rows, y) + x;
+    InputEdit {
+        start_byte,
+        old_end_byte: start_byte + removed.len(),
+        new_end_byte: start_byte + inserted.len(),
+        start_position: Point::new(y, x),
+        old_end_position: end_point(removed),
+        new_end_position: end_point(inserted),
+    }
+}
+
+impl SyntaxHighlight for TreeSitterHighlight {
+    fn file_type(&self) -> &str {
+        "rust"
+    }
+
+    fn line_comment(&self) -> Option<&str> {
+        Some("//")
+    }
+
+    fn highlight_row(&self, render: &str, state: RowState) -> (Vec<HighlightType>, RowState) {
+        (vec![HighlightType::Normal; render.chars().count()], state)
+    }
+
+    fn edit(&mut self, edit: &Edit, rows: &Rope<Row>) {
+        let mut buffer = [0; 4];
+        let (input, edited_rows) = match edit {
+            Edit::InsertChar { x, y, ch } => (
+                input_edit(rows, *y, *x, "", ch.encode_utf8(&mut buffer)),
+                (*y, *y),
+            ),
+            Edit::DeleteChar { x, y, ch } => (
+                input_edit(rows, *y, *x, ch.encode_utf8(&mut buffer), ""),
+                (*y, *y),
+            ),
+            Edit::InsertRow { y, content } => (
+                input_edit(rows, *y, 0, "", &format!("{}\n", content)),
+                (*y, *y + 1),
+            ),
+            Edit::DeleteRow { y, content } => (
+                input_edit(rows, *y, 0, &format!("{}\n", content), ""),
+                (*y, *y),
+            ),
+            Edit::SplitRow { x, y } => (input_edit(rows, *y, *x, "", "\n"), (*y, *y + 1)),
+            Edit::JoinRows { x, y } => (input_edit(rows, *y, *x, "\n", ""), (*y, *y)),
+            Edit::Replace {
+                y, start, old, new, ..
+            } => (input_edit(rows, *y, *start, old, new), (*y, *y)),
+        };
+        if let Some(tree) = &mut self.tree {
+            tree.edit(&input);
+        }
+        self.edited_rows = Some(match self.edited_rows {
+            Some((start, end)) => (cmp::min(start, edited_rows.0), cmp::max(end, edited_rows.1)),
+            None => edited_rows,
+        });
+    }
+
+    fn update_syntax(&mut self, at: usize, rows: &mut Rope<Row>) {
+        let text: String = rows
+            .iter()
+            .flat_map(|row| [row.row_content.as_str(), "\n"])
+            .collect();
+        let tree = match self.parser.parse(&text, self.tree.as_ref()) {
+            Some(tree) => tree,
+            None => return,
+        };
+        let mut ranges: Vec<(usize, usize)> = match &self.tree {
+            Some(old_tree) => old_tree
+                .changed_ranges(&tree)
+                .map(|range| (range.start_point.row, range.end_point.row))
+                .collect(),
+            None => vec![(0, rows.len())],
+        };
+        ranges.extend(self.edited_rows.take());
+        ranges.push((at, at));
+        self.tree = Some(tree);
+        let last_row = rows.len() - 1;
+        ranges
+            .into_iter()
+            .filter(|&(start, _)| start <= last_row)
+            .for_each(|(start, end)| {
+                self.highlight_rows(rows, &text, start, cmp::min(end, last_row))
+            });
+    }
+}
@@ -0,0 +1,295 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+
+use crossterm::style::Color;
+use toml::Value;
+
+use crate::syntax::HighlightType;
+
+const LIGHT: &str = r##"
+[syntax]
+number = "dark_cyan"
+string = "dark_green"
+char = "dark_green"
+comment = "grey"
+keyword = "dark_blue"
+type = "dark_magenta"
+lifetime = "dark_cyan"
+attribute = "dark_yellow"
+
+[ui]
+status_bar_foreground = "white"
+status_bar_background = "dark_grey"
+line_number = "grey"
+cursor_line = 255
+cursor_column = 255
+occurrence = 252
+color_column = 254
+scrollbar = 252
+scrollbar_thumb = "grey"
+matching_bracket = "grey"
+trailing_whitespace = "red"
+invisible = "grey"
+git_added = "dark_green"
+git_modified = "dark_yellow"
+git_deleted = "dark_red"
+diff_added = "green"
+diff_changed = "cyan"
+diff_text = "red"
+conflict_ours = "green"
+conflict_theirs = "cyan"
+conflict_marker = "grey"
+diagnostic_error = "dark_red"
+diagnostic_warning = "dark_yellow"
+diagnostic_info = "dark_blue"
+"##;
+
+const SOLARIZED: &str = r##"
+[syntax]
+normal = "#839496"
+number = "#d33682"
+string = "#2aa198"
+char = "#2aa198"
+comment = "#586e75"
+keyword = "#859900"
+type = "#b58900"
+lifetime = "#6c71c4"
+attribute = "#cb4b16"
+
+[ui]
+status_bar_foreground = "#002b36"
+status_bar_background = "#93a1a1"
+line_number = "#586e75"
+cursor_line = "#073642"
+cursor_column = "#073642"
+occurrence = "#1c4652"
+color_column = "#073642"
+scrollbar = "#073642"
+scrollbar_thumb = "#586e75"
+matching_bracket = "#073642"
+trailing_whitespace = "#dc322f"
+invisible = "#586e75"
+git_added = "#859900"
+git_modified = "#b58900"
+git_deleted = "#dc322f"
+diff_added = "#0f3a2b"
+diff_changed = "#073642"
+diff_text = "#5c3a12"
+conflict_ours = "#0f3a2b"
+conflict_theirs = "#073642"
+conflict_marker = "#586e75"
+diagnostic_error = "#dc322f"
+diagnostic_warning = "#b58900"
+diagnostic_info = "#268bd2"
+"##;
+
+pub(crate) struct Theme {
+    normal: Color,
+    number: Color,
+    string: Color,
+    char_literal: Color,
+    comment: Color,
+    keyword: Color,
+    type_: Color,
+    lifetime: Color,
+    attribute: Color,
+    pub(crate) status_bar_foreground: Color,
+    pub(crate) status_bar_background: Color,
+    pub(crate) line_number: Color,
+    pub(crate) cursor_line: Color,
+    pub(crate) cursor_column: Color,
+    pub(crate) occurrence: Color,
+    pub(crate) color_column: Color,
+    pub(crate) scrollbar: Color,
+    pub(crate) scrollbar_thumb: Color,
+    pub(crate) matching_bracket: Color,
+    pub(crate) trailing_whitespace: Color,
+    pub(crate) invisible: Color,
+    pub(crate) git_added: Color,
+    pub(crate) git_modified: Color,
+    pub(crate) git_deleted: Color,
+    pub(crate) diff_added: Color,
+    pub(crate) diff_changed: Color,
+    pub(crate) diff_text: Color,
+    pub(crate) conflict_ours: Color,
+    pub(crate) conflict_theirs: Color,
+    pub(crate) conflict_marker: Color,
+    pub(crate) diagnostic_error: Color,
+    pub(crate) diagnostic_warning: Color,
+    pub(crate) diagnostic_info: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            normal: Color::Reset,
+            number: Color::Cyan,
+            string: Color::Green,
+            char_literal: Color::DarkGreen,
+            comment: Color::DarkGrey,
+            keyword: Color::Yellow,
+            type_: Color::Magenta,
+            lifetime: Color::Blue,
+            attribute: Color::DarkYellow,
+            status_bar_foreground: Color::Black,
+            status_bar_background: Color::Grey,
+            line_number: Color::DarkGrey,
+            cursor_line: Color::AnsiValue(235),
+            cursor_column: Color::AnsiValue(235),
+            occurrence: Color::AnsiValue(238),
+            color_column: Color::AnsiValue(236),
+            scrollbar: Color::AnsiValue(238),
+            scrollbar_thumb: Color::Grey,
+            matching_bracket: Color::DarkGrey,
+            trailing_whitespace: Color::DarkRed,
+            invisible: Color::DarkGrey,
+            git_added: Color::Green,
+            git_modified: Color::Yellow,
+            git_deleted: Color::Red,
+            diff_added: Color::DarkGreen,
+            diff_changed: Color::DarkBlue,
+            diff_text: Color::DarkRed,
+            conflict_ours: Color::DarkGreen,
+            conflict_theirs: Color::DarkBlue,
+            conflict_marker: Color::DarkGrey,
+            diagnostic_error: Color::Red,
+            diagnostic_warning: Color::Yellow,
+            diagnostic_info: Color::Blue,
+        }
+    }
+}
+
+impl Theme {
+    pub(crate) fn load(name: &str) -> io::Result<Self> {
+        match name {
+            "dark" => Ok(Self::default()),
+            "light" => Self::parse(LIGHT),
+            "solarized" => Self::parse(SOLARIZED),
+            path => Self::parse(&fs::read_to_string(path)?),
+        }
+    }
+
+    fn parse(source: &str) -> io::Result<Self> {
+        let value: Value = source.parse().map_err(invalid_data)?;
+        let mut theme = Self::default();
+        if let Some(syntax) = value.get("syntax") {
+            for (key, slot) in [
+                ("normal", &mut theme.normal),
+                ("number", &mut theme.number),
+                ("string", &mut theme.string),
+                ("char", &mut theme.char_literal),
+                ("comment", &mut theme.comment),
+                ("keyword", &mut theme.keyword),
+                ("type", &mut theme.type_),
+                ("lifetime", &mut theme.lifetime),
+                ("attribute", &mut theme.attribute),
+            ] {
+                read_color(syntax, key, slot)?;
+            }
+        }
+        if let Some(ui) = value.get("ui") {
+            read_color(
+                ui,
+                "status_bar_foreground",
+                &mut theme.status_bar_foreground,
+            )?;
+            read_color(
+                ui,
+                "status_bar_background",
+                &mut theme.status_bar_background,
+            )?;
+            read_color(ui, "cursor_line", &mut theme.cursor_line)?;
+            read_color(ui, "cursor_column", &mut theme.cursor_column)?;
+            read_color(ui, "occurrence", &mut theme.occurrence)?;
+            read_color(ui, "color_column", &mut theme.color_column)?;
+            read_color(ui, "scrollbar", &mut theme.scrollbar)?;
+            read_color(ui, "scrollbar_thumb", &mut theme.scrollbar_thumb)?;
+            read_color(ui, "matching_bracket", &mut theme.matching_bracket)?;
+            read_color(ui, "trailing_whitespace", &mut theme.trailing_whitespace)?;
+            read_color(ui, "invisible", &mut theme.invisible)?;
+            read_color(ui, "git_added", &mut theme.git_added)?;
+            read_color(ui, "git_modified", &mut theme.git_modified)?;
+            read_color(ui, "git_deleted", &mut theme.git_deleted)?;
+            read_color(ui, "diff_added", &mut theme.diff_added)?;
+            read_color(ui, "diff_changed", &mut theme.diff_changed)?;
+            read_color(ui, "diff_text", &mut theme.diff_text)?;
+            read_color(ui, "conflict_ours", &mut theme.conflict_ours)?;
+            read_color(ui, "conflict_theirs", &mut theme.conflict_theirs)?;
+            read_color(ui, "conflict_marker", &mut theme.conflict_marker)?;
+            read_color(ui, "diagnostic_error", &mut theme.diagnostic_error)?;
+            read_color(ui, "diagnostic_warning", &mut theme.diagnostic_warning)?;
+            read_color(ui, "diagnostic_info", &mut theme.diagnostic_info)?;
+        }
+        Ok(theme)
+    }
+
+    pub(crate) fn syntax_color(&self, highlight: HighlightType) -> Color {
+        match highlight {
+            HighlightType::Normal => self.normal,
+            HighlightType::Number => self.number,
+            HighlightType::String => self.string,
+            HighlightType::CharLiteral => self.char_literal,
+            HighlightType::Comment | HighlightType::MultilineComment => self.comment,
+            HighlightType::Keyword => self.keyword,
+            HighlightType::Type => self.type_,
+            HighlightType::Lifetime => self.lifetime,
+            HighlightType::Attribute => self.attribute,
+        }
+    }
+}
+
+fn read_color(table: &Value, key: &str, slot: &mut Color) -> io::Result<()> {
+    match table.get(key) {
+        Some(Value::String(name)) => {
+            *slot = parse_color(name)
+                .ok_or_else(|| invalid_data(format!("unknown color {:?} for {}", name, key)))?
+        }
+        Some(Value::Integer(value)) if (0..=255).contains(value) => {
+            *slot = Color::AnsiValue(*value as u8)
+        }
+        Some(_) => return Err(invalid_data(format!("invalid color for {}", key))),
+        None => {}
+    }
+    Ok(())
+}
+
+fn parse_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |at: usize| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok();
+        return Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+    let color = match name {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "dark_grey" => Color::DarkGrey,
+        "red" => Color::Red,
+        "dark_red" => Color::DarkRed,
+        "green" => Color::Green,
+        "dark_green" => Color::DarkGreen,
+        "yellow" => Color::Yellow,
+        "dark_yellow" => Color::DarkYellow,
+        "blue" => Color::Blue,
+        "dark_blue" => Color::DarkBlue,
+        "magenta" => Color::Magenta,
+        "dark_magenta" => Color::DarkMagenta,
+        "cyan" => Color::Cyan,
+        "dark_cyan" => Color::DarkCyan,
+        "white" => Color::White,
+        "grey" => Color::Grey,
+        _ => return None,
+    };
+    Some(color)
+}
+
+pub(crate) fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(
+    error: E,
+) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,332 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{fs, io, mem};
+
+use crate::backup::{cache_file, create_private};
+
+const GROUP_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+pub(crate) enum Edit {
+    InsertChar {
+        x: usize,
+        y: usize,
+        ch: char,
+    },
+    DeleteChar {
+        x: usize,
+        y: usize,
+        ch: char,
+    },
+    InsertRow {
+        y: usize,
+        content: String,
+    },
+    DeleteRow {
+        y: usize,
+        content: String,
+    },
+    SplitRow {
+        x: usize,
+        y: usize,
+    },
+    JoinRows {
+        x: usize,
+        y: usize,
+    },
+    Replace {
+        y: usize,
+        start: usize,
+        old: String,
+        new: String,
+    },
+}
+
+impl Edit {
+    pub(crate) fn inverse(&self) -> Edit {
+        match self.clone() {
+            Edit::InsertChar { x, y, ch } => Edit::DeleteChar { x, y, ch },
+            Edit::DeleteChar { x, y, ch } => Edit::InsertChar { x, y, ch },
+            Edit::InsertRow { y, content } => Edit::DeleteRow { y, content },
+            Edit::DeleteRow { y, content } => Edit::InsertRow { y, content },
+            Edit::SplitRow { x, y } => Edit::JoinRows { x, y },
+            Edit::JoinRows { x, y } => Edit::SplitRow { x, y },
+            Edit::Replace { y, start, old, new } => Edit::Replace {
+                y,
+                start,
+                old: new,
+                new: old,
+            },
+        }
+    }
+
+    pub(crate) fn cursor_before(&self) -> (usize, usize) {
+        match *self {
+            Edit::InsertChar { x, y, .. }
+            | Edit::DeleteChar { x, y, .. }
+            | Edit::SplitRow { x, y }
+            | Edit::JoinRows { x, y } => (x, y),
+            Edit::InsertRow { y, .. } | Edit::DeleteRow { y, .. } => (0, y),
+            Edit::Replace { y, start, .. } => (start, y),
+        }
+    }
+
+    pub(crate) fn cursor_after(&self) -> (usize, usize) {
+        match self {
+            Edit::InsertChar { x, y, ch } => (x + ch.len_utf8(), *y),
+            Edit::SplitRow { y, .. } | Edit::InsertRow { y, .. } => (0, y + 1),
+            Edit::Replace { y, start, new, .. } => (start + new.len(), *y),
+            _ => self.cursor_before(),
+        }
+    }
+}
+
+pub(crate) struct History {
+    undo_stack: Vec<Vec<Edit>>,
+    redo_stack: Vec<Vec<Edit>>,
+    pending: Vec<Edit>,
+    last_record: Option<Instant>,
+}
+
+impl History {
+    pub(crate) fn new() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            pending: Vec::new(),
+            last_record: None,
+        }
+    }
+
+    pub(crate) fn record(&mut self, edit: Edit) {
+        if self.breaks_group(&edit) {
+            self.commit();
+        }
+        self.pending.push(edit);
+        self.last_record = Some(Instant::now());
+        self.redo_stack.clear();
+    }
+
+    fn breaks_group(&self, edit: &Edit) -> bool {
+        let paused = self
+            .last_record
+            .map_or(true, |time| time.elapsed() > GROUP_TIMEOUT);
+        match (self.pending.last(), edit) {
+            (Some(Edit::InsertChar { ch: last, .. }), Edit::InsertChar { ch, .. }) => {
+                paused || (ch.is_whitespace() && !last.is_whitespace())
+            }
+            (Some(Edit::DeleteChar { .. }), Edit::DeleteChar { .. }) => paused,
+            (Some(Edit::InsertChar { .. }), Edit::DeleteChar { .. })
+            | (Some(Edit::DeleteChar { .. }), Edit::InsertChar { .. }) => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn commit(&mut self) {
+        if !self.pending.is_empty() {
+            self.undo_stack.push(mem::take(&mut self.pending));
+        }
+    }
+
+    pub(crate) fn take_undo(&mut self) -> Option<Vec<Edit>> {
+        self.commit();
+        self.undo_stack.pop()
+    }
+
+    pub(crate) fn take_redo(&mut self) -> Option<Vec<Edit>> {
+        self.commit();
+        self.redo_stack.pop()
+    }
+
+    pub(crate) fn push_undo(&mut self, edits: Vec<Edit>) {
+        self.undo_stack.push(edits);
+    }
+
+    pub(crate) fn push_redo(&mut self, edits: Vec<Edit>) {
+        self.redo_stack.push(edits);
+    }
+}
+
+impl History {
+    pub(crate) fn save(&self, file: &Path, content_hash: u64) -> io::Result<()> {
+        let path = match undo_file(file) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut contents = format!("{}\n{:x}\n", HEADER, content_hash);
+        self.undo_stack.iter().for_each(|group| {
+            contents.push_str("u\n");
+            group
+                .iter()
+                .for_each(|edit| write_edit(&mut contents, edit));
+        });
+        self.redo_stack.iter().for_each(|group| {
+            contents.push_str("r\n");
+            group
+                .iter()
+                .for_each(|edit| write_edit(&mut contents, edit));
+        });
+        create_private(&path)?.write_all(contents.as_bytes())
+    }
+
+    pub(crate) fn load(file: &Path, content_hash: u64) -> Option<Self> {
+        let contents = fs::read_to_string(undo_file(file)?).ok()?;
+        let mut lines = contents.split('\n');
+        if lines.next()? != HEADER || u64::from_str_radix(lines.next()?, 16).ok()? != content_hash {
+            return None;
+        }
+        let mut history = History::new();
+        let mut stack = &mut history.undo_stack;
+        while let Some(line) = lines.next() {
+            let mut fields = line.split(' ');
+            let tag = fields.next()?;
+            let mut number = || -> Option<usize> { fields.next()?.parse().ok() };
+            let edit = match tag {
+                "" => break,
+                "u" | "r" => {
+                    stack = if tag == "u" {
+                        &mut history.undo_stack
+                    } else {
+                        &mut history.redo_stack
+                    };
+                    stack.push(Vec::new());
+                    continue;
+                }
+                "i" | "d" => {
+                    let (x, y) = (number()?, number()?);
+                    let ch = char::from_u32(number()? as u32)?;
+                    if tag == "i" {
+                        Edit::InsertChar { x, y, ch }
+                    } else {
+                        Edit::DeleteChar { x, y, ch }
+                    }
+                }
+                "+" | "-" => {
+                    let y = number()?;
+                    let content = lines.next()?.into();
+                    if tag == "+" {
+                        Edit::InsertRow { y, content }
+                    } else {
+                        Edit::DeleteRow { y, content }
+                    }
+                }
+                "s" => Edit::SplitRow {
+                    x: number()?,
+                    y: number()?,
+                },
+                "j" => Edit::JoinRows {
+                    x: number()?,
+                    y: number()?,
+                },
+                "c" => {
+                    let (y, start) = (number()?, number()?);
+                    Edit::Replace {
+                        y,
+                        start,
+                        old: lines.next()?.into(),
+                        new: lines.next()?.into(),
+                    }
+                }
+                _ => return None,
+            };
+            stack.last_mut()?.push(edit);
+        }
+        Some(history)
+    }
+}
+
+const HEADER: &str = "pound-undo 1";
+
+fn write_edit(contents: &mut String, edit: &Edit) {
+    let line = match edit {
+        Edit::InsertChar { x, y, ch } => format!("i {} {} {}\n", x, y, *ch as u32),
+        Edit::DeleteChar { x, y, ch } => format!("d {} {} {}\n", x, y, *ch as u32),
+        Edit::InsertRow { y, content } => format!("+ {}\n{}\n", y, content),
+        Edit::DeleteRow { y, content } => format!("- {}\n{}\n", y, content),
+        Edit::SplitRow { x, y } => format!("s {} {}\n", x, y),
+        Edit::JoinRows { x, y } => format!("j {} {}\n", x, y),
+        Edit::Replace { y, start, old, new } => format!("c {} {}\n{}\n{}\n", y, start, old, new),
+    };
+    contents.push_str(&line);
+}
+
+fn undo_file(file: &Path) -> Option<PathBuf> {
+    cache_file("undo", file, "")
+}
+
+pub(crate) fn content_hash<'a>(rows: impl Iterator<Item = &'a str>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    rows.for_each(|row| row.hash(&mut hasher));
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(x: usize, ch: char) -> Edit {
+        Edit::InsertChar { x, y: 0, ch }
+    }
+
+    #[test]
+    fn inverse_and_cursor() {
+        let edit = Edit::Replace {
+            y: 2,
+            start: 1,
+            old: "a".into(),
+            new: "bcd".into(),
+        };
+        assert_eq!(edit.cursor_after(), (4, 2));
+        assert!(matches!(
+            edit.inverse(),
+            Edit::Replace { ref old, ref new, .. } if old == "bcd" && new == "a"
+        ));
+        assert!(matches!(
+            insert(3, 'é').inverse(),
+            Edit::DeleteChar { x: 3, ch: 'é', .. }
+        ));
+        assert_eq!(insert(3, 'é').cursor_after(), (5, 0));
+        assert_eq!(Edit::SplitRow { x: 3, y: 1 }.cursor_after(), (0, 2));
+    }
+
+    #[test]
+    fn groups_words() {
+        let mut history = History::new();
+        for (x, ch) in "ab cd".chars().enumerate() {
+            history.record(insert(x, ch));
+        }
+        history.record(Edit::DeleteChar {
+            x: 4,
+            y: 0,
+            ch: 'd',
+        });
+        assert_eq!(history.take_undo().unwrap().len(), 1);
+        assert_eq!(history.take_undo().unwrap().len(), 3);
+        assert_eq!(history.take_undo().unwrap().len(), 2);
+        assert!(history.take_undo().is_none());
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut history = History::new();
+        history.record(insert(0, 'a'));
+        let edits = history.take_undo().unwrap();
+        history.push_redo(edits);
+        history.record(insert(0, 'b'));
+        assert!(history.take_redo().is_none());
+    }
+
+    #[test]
+    fn content_hash_separates_rows() {
+        assert_ne!(
+            content_hash(["ab", "c"].iter().copied()),
+            content_hash(["a", "bc"].iter().copied())
+        );
+    }
+}
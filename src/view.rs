@@ -0,0 +1,218 @@
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+use memmap2::Mmap;
+use unicode_width::UnicodeWidthChar;
+
+use crate::config::Config;
+use crate::theme::Theme;
+use crate::{EditorContents, Reader};
+
+pub(crate) struct Viewer {
+    map: Option<Mmap>,
+    name: String,
+    line_starts: Vec<usize>,
+    indexed: usize,
+    row_offset: usize,
+    column_offset: usize,
+    win_size: (usize, usize),
+    editor_contents: EditorContents,
+    tab_stop: usize,
+    theme: Theme,
+}
+
+impl Viewer {
+    pub(crate) fn open(path: &Path, config: &Config) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only read. Truncating the file while it is being
+        // viewed is outside of what a pager can guard against.
+        let map = match file.metadata()?.len() {
+            0 => None,
+            _ => Some(unsafe { Mmap::map(&file)? }),
+        };
+        let win_size = terminal::size()
+            .map(|(x, y)| (x as usize, y as usize - 1))
+            .unwrap();
+        Ok(Self {
+            line_starts: if map.is_some() { vec![0] } else { Vec::new() },
+            map,
+            name: path.display().to_string(),
+            indexed: 0,
+            row_offset: 0,
+            column_offset: 0,
+            win_size,
+            editor_contents: EditorContents::new(),
+            tab_stop: config.tab_stop,
+            theme: config.theme().unwrap_or_default(),
+        })
+    }
+
+    pub(crate) fn run(&mut self) -> crossterm::Result<()> {
+        let reader = Reader;
+        loop {
+            self.refresh_screen()?;
+            match reader.read_key()? {
+                KeyEvent {
+                    code: KeyCode::Char('q'),
+                    modifiers: KeyModifiers::NONE,
+                }
+                | KeyEvent {
+                    code: KeyCode::Char('q'),
+                    modifiers: KeyModifiers::CONTROL,
+                } => {
+                    queue!(self.editor_contents, cursor::Show)?;
+                    return self.editor_contents.flush();
+                }
+                KeyEvent {
+                    code: KeyCode::Up, ..
+                } => self.row_offset = self.row_offset.saturating_sub(1),
+                KeyEvent {
+                    code: KeyCode::Down | KeyCode::Enter,
+                    ..
+                } => self.scroll_to(self.row_offset + 1),
+                KeyEvent {
+                    code: KeyCode::PageUp,
+                    ..
+                } => self.row_offset = self.row_offset.saturating_sub(self.win_size.1),
+                KeyEvent {
+                    code: KeyCode::PageDown | KeyCode::Char(' '),
+                    ..
+                } => self.scroll_to(self.row_offset + self.win_size.1),
+                KeyEvent {
+                    code: KeyCode::Home,
+                    ..
+                } => self.row_offset = 0,
+                KeyEvent {
+                    code: KeyCode::End, ..
+                } => self.scroll_to(usize::MAX),
+                KeyEvent {
+                    code: KeyCode::Left,
+                    ..
+                } => self.column_offset = self.column_offset.saturating_sub(self.tab_stop),
+                KeyEvent {
+                    code: KeyCode::Right,
+                    ..
+                } => self.column_offset += self.tab_stop,
+                _ => {}
+            }
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or(&[])
+    }
+
+    fn index_to(&mut self, line: usize) {
+        let len = self.bytes().len();
+        while self.line_starts.len() <= line && self.indexed < len {
+            let start = *self.line_starts.last().unwrap();
+            match self.bytes()[start..].iter().position(|&byte| byte == b'\n') {
+                Some(newline) if start + newline + 1 < len => {
+                    self.line_starts.push(start + newline + 1);
+                    self.indexed = start + newline + 1;
+                }
+                _ => self.indexed = len,
+            }
+        }
+    }
+
+    fn scroll_to(&mut self, row_offset: usize) {
+        self.index_to(row_offset.saturating_add(self.win_size.1));
+        let last_page = self.line_starts.len().saturating_sub(self.win_size.1);
+        self.row_offset = cmp::min(row_offset, last_page);
+    }
+
+    fn line(&self, at: usize) -> &[u8] {
+        let bytes = self.bytes();
+        let start = self.line_starts[at];
+        let end = self
+            .line_starts
+            .get(at + 1)
+            .map_or(bytes.len(), |&next| next - 1);
+        let line = &bytes[start..cmp::max(start, end)];
+        line.strip_suffix(b"\r").unwrap_or(line)
+    }
+
+    fn refresh_screen(&mut self) -> crossterm::Result<()> {
+        self.index_to(self.row_offset + self.win_size.1);
+        queue!(self.editor_contents, cursor::Hide, cursor::MoveTo(0, 0))?;
+        for i in 0..self.win_size.1 {
+            let file_row = self.row_offset + i;
+            if file_row < self.line_starts.len() {
+                let text = render(
+                    &String::from_utf8_lossy(self.line(file_row)),
+                    self.column_offset,
+                    self.win_size.0,
+                    self.tab_stop,
+                );
+                self.editor_contents.push_str(&text);
+            } else {
+                self.editor_contents.push('~');
+            }
+            queue!(
+                self.editor_contents,
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+            self.editor_contents.push_str("\r\n");
+        }
+        self.draw_status_bar();
+        self.editor_contents.flush()
+    }
+
+    fn draw_status_bar(&mut self) {
+        let total = if self.indexed >= self.bytes().len() {
+            self.line_starts.len().to_string()
+        } else {
+            format!("{}+", self.line_starts.len())
+        };
+        let info = format!("{} [view] -- {} lines", self.name, total);
+        let line_info = format!(
+            "{}/{} | q = Quit",
+            cmp::min(self.row_offset + 1, self.line_starts.len()),
+            total
+        );
+        let width = self.win_size.0;
+        let mut status: String = info.chars().take(width).collect();
+        let status_len = status.chars().count();
+        if status_len + line_info.len() <= width {
+            status.push_str(&" ".repeat(width - status_len - line_info.len()));
+            status.push_str(&line_info);
+        }
+        self.editor_contents
+            .set_foreground(self.theme.status_bar_foreground);
+        self.editor_contents
+            .set_background(self.theme.status_bar_background);
+        self.editor_contents.push_str(&status);
+        self.editor_contents
+            .push_str(&style::Attribute::Reset.to_string());
+    }
+}
+
+fn render(line: &str, column_offset: usize, width: usize, tab_stop: usize) -> String {
+    let mut rendered = String::new();
+    let mut render_x = 0;
+    for c in line.chars() {
+        let (text, c_width) = match c {
+            '\t' => {
+                let spaces = tab_stop - render_x % tab_stop;
+                (" ".repeat(spaces), spaces)
+            }
+            c if c.is_control() => ("?".into(), 1),
+            c => (c.to_string(), c.width().unwrap_or(0)),
+        };
+        if render_x >= column_offset && render_x + c_width <= column_offset + width {
+            rendered.push_str(&text);
+        }
+        render_x += c_width;
+        if render_x >= column_offset + width {
+            break;
+        }
+    }
+    rendered
+}
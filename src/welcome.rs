@@ -0,0 +1,213 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{cmp, env, fs};
+
+use crossterm::event::KeyCode;
+use crossterm::style;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+
+use crate::backup::cache_dir;
+use crate::config::Config;
+use crate::keymap::{Action, Keymap};
+use crate::{EditorContents, Reader, VERSION};
+
+const RECENT_FILES: usize = 20;
+
+const HINTS: [(&str, &str); 6] = [
+    ("quit", "Quit"),
+    ("open", "Open file"),
+    ("save", "Save"),
+    ("find", "Find"),
+    ("command-line", "Command line"),
+    ("help", "Keys and commands"),
+];
+
+fn recent_list() -> Option<PathBuf> {
+    Some(cache_dir()?.join("recent"))
+}
+
+fn recent_files() -> Vec<PathBuf> {
+    let contents = recent_list().and_then(|path| fs::read_to_string(path).ok());
+    contents
+        .iter()
+        .flat_map(|contents| contents.lines())
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+pub(crate) fn remember(file: &Path) -> io::Result<()> {
+    let path = match recent_list() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let file = match fs::canonicalize(file) {
+        Ok(file) => file,
+        // Not written yet, so nothing to open again.
+        Err(_) => return Ok(()),
+    };
+    let mut files = recent_files();
+    files.retain(|other| *other != file);
+    files.insert(0, file);
+    files.truncate(RECENT_FILES);
+    let mut contents = String::new();
+    for file in &files {
+        contents.push_str(&file.to_string_lossy());
+        contents.push('\n');
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+fn display_path(path: &Path) -> String {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    match home
+        .as_deref()
+        .and_then(|home| path.strip_prefix(home).ok())
+    {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+pub(crate) enum Choice {
+    Open(PathBuf),
+    New,
+    Quit,
+}
+
+pub(crate) struct Welcome {
+    files: Vec<PathBuf>,
+    /// 0 for a new file, then the recent files.
+    selected: usize,
+    keymap: Keymap,
+    hints: Vec<String>,
+    win_size: (usize, usize),
+    editor_contents: EditorContents,
+}
+
+impl Welcome {
+    pub(crate) fn open(config: &Config) -> Self {
+        let win_size = terminal::size()
+            .map(|(x, y)| (x as usize, y as usize))
+            .unwrap();
+        let keymap = Keymap::new(&config.keys);
+        let bindings = keymap.bindings();
+        let mut hints = vec!["Up/Down = Select".into(), "Enter = Open".into()];
+        hints.extend(HINTS.iter().filter_map(|&(action, label)| {
+            let (_, keys) = bindings.iter().find(|&&(name, _)| name == action)?;
+            Some(format!("{} = {}", keys.first()?, label))
+        }));
+        Self {
+            files: recent_files(),
+            selected: 0,
+            keymap,
+            hints: hints.chunks(4).map(|hints| hints.join(" | ")).collect(),
+            win_size,
+            editor_contents: EditorContents::new(),
+        }
+    }
+
+    pub(crate) fn run(&mut self) -> crossterm::Result<Choice> {
+        let reader = Reader;
+        loop {
+            self.refresh_screen()?;
+            let key = reader.read_key()?;
+            let last = self.files.len();
+            if self.keymap.get(&key) == Some(Action::Quit) {
+                return Ok(Choice::Quit);
+            }
+            match key.code {
+                KeyCode::Esc => return Ok(Choice::New),
+                KeyCode::Enter => {
+                    return Ok(match self.selected {
+                        0 => Choice::New,
+                        selected => Choice::Open(self.files[selected - 1].clone()),
+                    })
+                }
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => self.selected = cmp::min(self.selected + 1, last),
+                KeyCode::Home => self.selected = 0,
+                KeyCode::End => self.selected = last,
+                KeyCode::Char(c @ '1'..='9') if key.modifiers.is_empty() => {
+                    let index = c.to_digit(10).unwrap() as usize;
+                    if let Some(file) = self.files.get(index - 1) {
+                        return Ok(Choice::Open(file.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn lines(&self, rows: usize) -> (Vec<String>, usize) {
+        let mut lines = vec![
+            format!("Pound Editor --- Version {}", VERSION),
+            String::new(),
+            "   New file (Esc)".into(),
+        ];
+        if !self.files.is_empty() {
+            lines.push(String::new());
+            lines.push("Recent files".into());
+        }
+        let first_file = lines.len();
+        // Room for the hints, below a blank line.
+        let room = rows.saturating_sub(first_file + self.hints.len() + 1);
+        let offset = self.selected.saturating_sub(room);
+        for (i, file) in self.files.iter().enumerate().skip(offset).take(room) {
+            let key = if i < 9 {
+                format!("{}", i + 1)
+            } else {
+                " ".into()
+            };
+            lines.push(format!(" {} {}", key, display_path(file)));
+        }
+        lines.push(String::new());
+        lines.extend(self.hints.iter().cloned());
+        let selected = match self.selected {
+            0 => 2,
+            selected => first_file + selected - 1 - offset,
+        };
+        (lines, selected)
+    }
+
+    fn refresh_screen(&mut self) -> crossterm::Result<()> {
+        let (columns, rows) = self.win_size;
+        let (lines, selected) = self.lines(rows);
+        let width = lines.iter().map(|line| line.chars().count()).max();
+        let width = cmp::min(width.unwrap_or(0), columns);
+        let left = (columns - width) / 2;
+        let top = rows.saturating_sub(lines.len()) / 3;
+        queue!(self.editor_contents, cursor::Hide, cursor::MoveTo(0, 0))?;
+        for y in 0..rows {
+            let line = y.checked_sub(top).and_then(|i| lines.get(i));
+            if let Some(line) = line {
+                let line: String = line.chars().take(columns - left).collect();
+                self.editor_contents.push_str(&" ".repeat(left));
+                if y - top == selected {
+                    self.editor_contents
+                        .push_str(&style::Attribute::Reverse.to_string());
+                } else if y - top == 0 {
+                    self.editor_contents
+                        .push_str(&style::Attribute::Bold.to_string());
+                }
+                self.editor_contents.push_str(&line);
+                self.editor_contents
+                    .push_str(&style::Attribute::Reset.to_string());
+            } else {
+                self.editor_contents.push('~');
+            }
+            queue!(
+                self.editor_contents,
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+            if y + 1 < rows {
+                self.editor_contents.push_str("\r\n");
+            }
+        }
+        self.editor_contents.flush()
+    }
+}
@@ -0,0 +1,158 @@
+use std::mem;
+
+use crate::CursorController;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct Rect {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+pub(crate) struct Window {
+    pub(crate) buffer: usize,
+    pub(crate) cursor_controller: CursorController,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Rectangles include the status line. Side by side windows have a separator column.
+pub(crate) enum Layout {
+    Window(usize),
+    Split {
+        vertical: bool,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Layout {
+    pub(crate) fn split(&mut self, window: usize, new_window: usize, vertical: bool) {
+        match self {
+            Layout::Window(id) if *id == window => {
+                *self = Layout::Split {
+                    vertical,
+                    first: Box::new(Layout::Window(window)),
+                    second: Box::new(Layout::Window(new_window)),
+                }
+            }
+            Layout::Window(_) => {}
+            Layout::Split { first, second, .. } => {
+                first.split(window, new_window, vertical);
+                second.split(window, new_window, vertical);
+            }
+        }
+    }
+
+    pub(crate) fn close(&mut self, window: usize) {
+        self.remove(window);
+        self.renumber(window);
+    }
+
+    fn remove(&mut self, window: usize) {
+        if let Layout::Split { first, second, .. } = self {
+            let sibling = if matches!(**first, Layout::Window(id) if id == window) {
+                mem::replace(&mut **second, Layout::Window(0))
+            } else if matches!(**second, Layout::Window(id) if id == window) {
+                mem::replace(&mut **first, Layout::Window(0))
+            } else {
+                first.remove(window);
+                second.remove(window);
+                return;
+            };
+            *self = sibling;
+        }
+    }
+
+    fn renumber(&mut self, removed: usize) {
+        match self {
+            Layout::Window(id) if *id > removed => *id -= 1,
+            Layout::Window(_) => {}
+            Layout::Split { first, second, .. } => {
+                first.renumber(removed);
+                second.renumber(removed);
+            }
+        }
+    }
+
+    pub(crate) fn arrange(
+        &self,
+        area: Rect,
+        windows: &mut Vec<(usize, Rect)>,
+        separators: &mut Vec<Rect>,
+    ) {
+        match self {
+            Layout::Window(id) => windows.push((*id, area)),
+            Layout::Split {
+                vertical: true,
+                first,
+                second,
+            } => {
+                let width = area.width.saturating_sub(1) / 2;
+                first.arrange(Rect { width, ..area }, windows, separators);
+                separators.push(Rect {
+                    x: area.x + width,
+                    width: 1,
+                    ..area
+                });
+                second.arrange(
+                    Rect {
+                        x: area.x + width + 1,
+                        width: area.width.saturating_sub(width + 1),
+                        ..area
+                    },
+                    windows,
+                    separators,
+                );
+            }
+            Layout::Split {
+                vertical: false,
+                first,
+                second,
+            } => {
+                let height = area.height / 2;
+                first.arrange(Rect { height, ..area }, windows, separators);
+                second.arrange(
+                    Rect {
+                        y: area.y + height,
+                        height: area.height - height,
+                        ..area
+                    },
+                    windows,
+                    separators,
+                );
+            }
+        }
+    }
+}
+
+pub(crate) fn neighbour(
+    windows: &[(usize, Rect)],
+    from: Rect,
+    (x, y): (usize, usize),
+    direction: Direction,
+) -> Option<usize> {
+    let adjacent = |rect: &Rect| match direction {
+        Direction::Left => rect.x + rect.width + 1 == from.x,
+        Direction::Right => from.x + from.width + 1 == rect.x,
+        Direction::Up => rect.y + rect.height == from.y,
+        Direction::Down => from.y + from.height == rect.y,
+    };
+    let beside = |rect: &Rect| match direction {
+        Direction::Left | Direction::Right => rect.y <= y && y < rect.y + rect.height,
+        Direction::Up | Direction::Down => rect.x <= x && x <= rect.x + rect.width,
+    };
+    let candidates = windows.iter().filter(|(_, rect)| adjacent(rect));
+    candidates
+        .clone()
+        .find(|(_, rect)| beside(rect))
+        .or_else(|| candidates.clone().next())
+        .map(|&(id, _)| id)
+}
@@ -0,0 +1,200 @@
+use std::cmp;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{EditorRows, Output};
+
+/// A run of punctuation counts as one word.
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut words: Vec<(usize, usize)> = Vec::new();
+    let mut punctuation = false;
+    for (at, segment) in text.split_word_bound_indices() {
+        if segment.chars().all(char::is_whitespace) {
+            punctuation = false;
+            continue;
+        }
+        let end = at + segment.len();
+        let is_word = segment.chars().any(|c| c.is_alphanumeric() || c == '_');
+        match words.last_mut() {
+            Some((_, last_end)) if punctuation && !is_word && *last_end == at => *last_end = end,
+            _ => words.push((at, end)),
+        }
+        punctuation = !is_word;
+    }
+    words
+}
+
+fn row(editor_rows: &EditorRows, y: usize) -> &str {
+    if y < editor_rows.number_of_rows() {
+        editor_rows.get_row(y)
+    } else {
+        ""
+    }
+}
+
+pub(crate) fn word_right(editor_rows: &EditorRows, (x, y): (usize, usize)) -> (usize, usize) {
+    let text = row(editor_rows, y);
+    if x >= text.len() {
+        return if y + 1 < editor_rows.number_of_rows() {
+            (0, y + 1)
+        } else {
+            (x, y)
+        };
+    }
+    let end = words(text)
+        .into_iter()
+        .map(|(_, end)| end)
+        .find(|&end| end > x)
+        .unwrap_or_else(|| text.len());
+    (end, y)
+}
+
+pub(crate) fn word_left(editor_rows: &EditorRows, (x, y): (usize, usize)) -> (usize, usize) {
+    if x == 0 {
+        return match y.checked_sub(1) {
+            Some(y) => (row(editor_rows, y).len(), y),
+            None => (0, 0),
+        };
+    }
+    let start = words(row(editor_rows, y))
+        .into_iter()
+        .map(|(start, _)| start)
+        .filter(|&start| start < x)
+        .last()
+        .unwrap_or(0);
+    (start, y)
+}
+
+pub(crate) fn word_end(editor_rows: &EditorRows, (x, y): (usize, usize)) -> (usize, usize) {
+    let text = row(editor_rows, y);
+    let words = words(text);
+    let end = match words.iter().find(|&&(_, end)| end > x) {
+        Some(&(start, end)) if start <= x => end,
+        Some(&(start, _)) => start,
+        None => text.len(),
+    };
+    (cmp::max(end, x), y)
+}
+
+pub(crate) fn next_word_start(editor_rows: &EditorRows, (x, y): (usize, usize)) -> (usize, usize) {
+    let rows = editor_rows.number_of_rows();
+    if y >= rows {
+        return (x, y);
+    }
+    if let Some((start, _)) = words(row(editor_rows, y))
+        .into_iter()
+        .find(|&(start, _)| start > x)
+    {
+        return (start, y);
+    }
+    for y in y + 1..rows {
+        let text = row(editor_rows, y);
+        match words(text).first() {
+            Some(&(start, _)) => return (start, y),
+            None if text.is_empty() => return (0, y),
+            None => {}
+        }
+    }
+    (row(editor_rows, rows - 1).len(), rows - 1)
+}
+
+pub(crate) fn previous_word_start(
+    editor_rows: &EditorRows,
+    (x, y): (usize, usize),
+) -> (usize, usize) {
+    let y = cmp::min(y, editor_rows.number_of_rows());
+    let x = if y < editor_rows.number_of_rows() {
+        x
+    } else {
+        0
+    };
+    if let Some((start, _)) = words(row(editor_rows, y))
+        .into_iter()
+        .filter(|&(start, _)| start < x)
+        .last()
+    {
+        return (start, y);
+    }
+    for y in (0..y).rev() {
+        let text = row(editor_rows, y);
+        match words(text).last() {
+            Some(&(start, _)) => return (start, y),
+            None if text.is_empty() => return (0, y),
+            None => {}
+        }
+    }
+    (0, 0)
+}
+
+pub(crate) fn next_word_end(editor_rows: &EditorRows, (x, y): (usize, usize)) -> (usize, usize) {
+    let rows = editor_rows.number_of_rows();
+    if y >= rows {
+        return (x, y);
+    }
+    let after = editor_rows
+        .get_editor_row(y)
+        .next_boundary(cmp::min(x, row(editor_rows, y).len()));
+    if let Some((_, end)) = words(row(editor_rows, y))
+        .into_iter()
+        .find(|&(_, end)| end > after)
+    {
+        return (end, y);
+    }
+    for y in y + 1..rows {
+        if let Some(&(_, end)) = words(row(editor_rows, y)).first() {
+            return (end, y);
+        }
+    }
+    (row(editor_rows, rows - 1).len(), rows - 1)
+}
+
+impl Output {
+    pub(crate) fn move_word(&mut self, forward: bool) {
+        let cursor_controller = &mut self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        let (x, y) = if forward {
+            word_right(&self.buffer.editor_rows, cursor)
+        } else {
+            word_left(&self.buffer.editor_rows, cursor)
+        };
+        cursor_controller.cursor_x = x;
+        cursor_controller.cursor_y = y;
+    }
+
+    pub(crate) fn delete_word(&mut self, forward: bool) {
+        if self.reject_edit() {
+            return;
+        }
+        let cursor_controller = &self.buffer.cursor_controller;
+        let cursor = (cursor_controller.cursor_x, cursor_controller.cursor_y);
+        if cursor.1 >= self.buffer.editor_rows.number_of_rows() {
+            return;
+        }
+        let (start, end) = if forward {
+            (cursor, word_right(&self.buffer.editor_rows, cursor))
+        } else {
+            (word_left(&self.buffer.editor_rows, cursor), cursor)
+        };
+        if start == end {
+            return;
+        }
+        self.buffer.editor_rows.delete_range(start, end);
+        self.buffer.cursor_controller.cursor_x = start.0;
+        self.buffer.cursor_controller.cursor_y = start.1;
+        self.buffer.dirty += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_split_at_boundaries() {
+        assert_eq!(words("foo_bar  baz"), [(0, 7), (9, 12)]);
+        assert_eq!(words("a->b"), [(0, 1), (1, 3), (3, 4)]);
+        assert_eq!(words("x ;; y"), [(0, 1), (2, 4), (5, 6)]);
+        assert_eq!(words("héllo wörld"), [(0, 6), (7, 13)]);
+        assert!(words("   ").is_empty());
+    }
+}